@@ -0,0 +1,64 @@
+//! Benchmarks of the hot path of the event dispatch: parsing the ids of a request, setting a
+//! variable, evaluating the guards of the active nodes and taking the enabled transition.
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use open_api_matcher::{OpenApi, ValidatedValue};
+
+use state_machines::{Node, StateId, StateMachine, VariableId};
+
+fn read_state_chart(file_name: &str) -> Node {
+    let open_api_file = std::fs::File::open("StateMachines.yml").unwrap();
+    let open_api = OpenApi::new(&open_api_file).unwrap();
+    let sc = std::fs::read_to_string(file_name).unwrap();
+    let sc_schema = open_api.get_schema("#/components/schemas/Node").unwrap();
+    let vvsc = ValidatedValue::new(&sc, &sc_schema, &open_api).unwrap();
+    (&vvsc).try_into().unwrap()
+}
+
+fn variable_id(state_machine: &StateMachine, path: &str) -> VariableId {
+    format!("smv:///{}/{path}", state_machine.id().id().unwrap()).parse().unwrap()
+}
+
+fn bench_ids(c: &mut Criterion) {
+    let state_id = "sms:///4711/Review-Task/Escalated";
+    let variable_id = "smv:///4711/Review-Task/Escalated/approved";
+    c.bench_function("ids/state-id", |b| {
+        b.iter(|| StateId::from(black_box(state_id)).node_id().unwrap().clone())
+    });
+    c.bench_function("ids/variable-id", |b| {
+        b.iter(|| black_box(variable_id).parse::<VariableId>().unwrap().node_id())
+    });
+}
+
+fn bench_dispatch(c: &mut Criterion) {
+    let state_chart = read_state_chart("tests/review-task.json");
+
+    c.bench_function("dispatch/set-variable", |b| {
+        b.iter_batched(
+            || StateMachine::new(state_chart.clone()).unwrap(),
+            |mut state_machine| {
+                let priority = variable_id(&state_machine, "Review-Task/priority");
+                state_machine.set_variable(&priority, &ValidatedValue::Integer(1)).unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("dispatch/transition", |b| {
+        b.iter_batched(
+            || StateMachine::new(state_chart.clone()).unwrap(),
+            |mut state_machine| {
+                let priority = variable_id(&state_machine, "Review-Task/priority");
+                state_machine.set_variable(&priority, &ValidatedValue::Integer(3)).unwrap();
+                let approved = variable_id(&state_machine, "Review-Task/Escalated/approved");
+                state_machine.set_variable(&approved, &ValidatedValue::Bool(true)).unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    let state_machine = StateMachine::new(state_chart).unwrap();
+    c.bench_function("dispatch/enabled-events", |b| {
+        b.iter(|| black_box(&state_machine).enabled_events().unwrap())
+    });
+}
+
+criterion_group!(benches, bench_ids, bench_dispatch);
+criterion_main!(benches);
@@ -0,0 +1,173 @@
+use crate::error::StateChartError;
+use crate::ids::{EventId, NodeId, StateId, VariableReference};
+use crate::state_charts::VariableValue;
+
+/// The change of the state machine, requested by an action. The effects are applied by the state
+/// machine, which checks them against the declarations of the state chart.
+#[derive(Debug, PartialEq)]
+pub enum Effect {
+    /// Assigns the value to the referenced variable.
+    Assign(VariableReference, VariableValue),
+    /// Puts the event into the internal queue of the state machine.
+    Raise(EventId),
+    /// Starts a child state machine from the state chart. The id of the child is assigned to the
+    /// optionally referenced variable.
+    Invoke(NodeId, Option<VariableReference>),
+    /// Sends the event to another state machine.
+    Send(StateId, EventId),
+}
+
+/// Executes the action with the given name. The actions are provided by the service and cannot
+/// be defined by a state chart.
+/// The parameters are passed as they are written in the state chart, as the first parameter of
+/// assign and increment is the reference of the variable to change. All other parameters are
+/// resolved with the provided function.
+pub fn execute<F>(name: &str, parameters: &[VariableValue], resolve: F) -> Result<Vec<Effect>, StateChartError>
+where
+    F: Fn(&VariableValue) -> Result<VariableValue, StateChartError>,
+{
+    match (name, parameters) {
+        ("assign", [variable, value]) => Ok(vec![Effect::Assign(reference(name, variable)?, resolve(value)?)]),
+        ("increment", [variable]) => increment(name, variable, VariableValue::Integer(1), resolve),
+        ("increment", [variable, amount]) => {
+            let amount = resolve(amount)?;
+            increment(name, variable, amount, resolve)
+        }
+        ("raise", [event]) => Ok(vec![Effect::Raise(event_id(name, event)?)]),
+        ("invoke", [state_chart]) => Ok(vec![Effect::Invoke(state_chart_id(name, state_chart)?, None)]),
+        ("invoke", [state_chart, variable]) => Ok(vec![Effect::Invoke(
+            state_chart_id(name, state_chart)?,
+            Some(reference(name, variable)?),
+        )]),
+        ("send", [state_machine, event]) => {
+            let state_machine = match resolve(state_machine)? {
+                VariableValue::String(id) => StateId::from(id),
+                _ => return Err(StateChartError::InvalidActionArguments(name.into())),
+            };
+            if state_machine.id().is_err() {
+                return Err(StateChartError::InvalidActionArguments(name.into()));
+            }
+            Ok(vec![Effect::Send(state_machine, event_id(name, event)?)])
+        }
+        ("assign" | "increment" | "raise" | "invoke" | "send", _) => {
+            Err(StateChartError::InvalidActionArguments(name.into()))
+        }
+        _ => Err(StateChartError::UnknownAction(name.into())),
+    }
+}
+
+/// Adds the amount to the current value of the variable. Integers and numbers can be mixed. An
+/// integer, which leaves its range, is an error.
+fn increment<F>(name: &str, variable: &VariableValue, amount: VariableValue, resolve: F) -> Result<Vec<Effect>, StateChartError>
+where
+    F: Fn(&VariableValue) -> Result<VariableValue, StateChartError>,
+{
+    let reference = reference(name, variable)?;
+    let value = match (resolve(variable)?, amount) {
+        (VariableValue::Integer(a), VariableValue::Integer(b)) => {
+            VariableValue::Integer(a.checked_add(b).ok_or_else(|| StateChartError::Overflow(reference.to_string()))?)
+        }
+        (VariableValue::Integer(a), VariableValue::Number(b)) => VariableValue::Number(a as f64 + b),
+        (VariableValue::Number(a), VariableValue::Integer(b)) => VariableValue::Number(a + b as f64),
+        (VariableValue::Number(a), VariableValue::Number(b)) => VariableValue::Number(a + b),
+        _ => return Err(StateChartError::InvalidActionArguments(name.into())),
+    };
+    Ok(vec![Effect::Assign(reference, value)])
+}
+
+/// Provides the id of the event, which is written literally in the state chart.
+fn event_id(name: &str, event: &VariableValue) -> Result<EventId, StateChartError> {
+    match event {
+        VariableValue::String(event) => event
+            .parse()
+            .map_err(|_| StateChartError::InvalidActionArguments(name.into())),
+        _ => Err(StateChartError::InvalidActionArguments(name.into())),
+    }
+}
+
+/// Provides the absolute id of the state chart, which is started by the action.
+fn state_chart_id(name: &str, state_chart: &VariableValue) -> Result<NodeId, StateChartError> {
+    match state_chart {
+        VariableValue::String(id) => {
+            let node_id = NodeId::from(id.as_str());
+            match node_id.path() {
+                Ok(_) => Ok(node_id),
+                Err(_) => Err(StateChartError::InvalidActionArguments(name.into())),
+            }
+        }
+        _ => Err(StateChartError::InvalidActionArguments(name.into())),
+    }
+}
+
+/// Provides the reference of the variable, which is changed by the action.
+fn reference(name: &str, variable: &VariableValue) -> Result<VariableReference, StateChartError> {
+    match variable {
+        VariableValue::Reference(reference) => Ok(reference.clone()),
+        _ => Err(StateChartError::InvalidActionArguments(name.into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(value: &VariableValue) -> Result<VariableValue, StateChartError> {
+        match value {
+            VariableValue::Reference(_) => Ok(VariableValue::Integer(41)),
+            _ => Ok(value.clone()),
+        }
+    }
+
+    fn reference(reference: &str) -> VariableValue {
+        VariableValue::Reference(reference.parse().unwrap())
+    }
+
+    #[test]
+    fn test_assign() {
+        let variable = reference("smv:///Order/amount");
+        let effects = execute("assign", &[variable.clone(), VariableValue::Integer(7)], resolve).unwrap();
+        assert_eq!(vec![Effect::Assign("smv:///Order/amount".parse().unwrap(), VariableValue::Integer(7))], effects);
+        execute("assign", &[VariableValue::String("amount".into()), VariableValue::Integer(7)], resolve).unwrap_err();
+        execute("assign", &[variable], resolve).unwrap_err();
+        execute("unknown", &[], resolve).unwrap_err();
+    }
+
+    #[test]
+    fn test_increment() {
+        let variable = reference("smv:///Order/amount");
+        let effects = execute("increment", std::slice::from_ref(&variable), resolve).unwrap();
+        assert_eq!(vec![Effect::Assign("smv:///Order/amount".parse().unwrap(), VariableValue::Integer(42))], effects);
+        let effects = execute("increment", &[variable.clone(), VariableValue::Number(0.5)], resolve).unwrap();
+        assert_eq!(vec![Effect::Assign("smv:///Order/amount".parse().unwrap(), VariableValue::Number(41.5))], effects);
+        execute("increment", &[variable.clone(), VariableValue::Boolean(true)], resolve).unwrap_err();
+        let overflow = execute("increment", &[variable, VariableValue::Integer(i64::MAX)], resolve);
+        assert!(matches!(overflow, Err(StateChartError::Overflow(reference)) if reference == "smv:///Order/amount"));
+    }
+
+    #[test]
+    fn test_raise() {
+        let effects = execute("raise", &[VariableValue::String("sme:///check".into())], resolve).unwrap();
+        assert_eq!(vec![Effect::Raise("sme:///check".parse().unwrap())], effects);
+        execute("raise", &[VariableValue::String("check".into())], resolve).unwrap_err();
+        execute("raise", &[VariableValue::Integer(1)], resolve).unwrap_err();
+    }
+
+    #[test]
+    fn test_invoke_and_send() {
+        let state_chart = VariableValue::String("scn:///Review-Step".into());
+        let variable = reference("smv:///Case/child");
+        let effects = execute("invoke", &[state_chart.clone(), variable], resolve).unwrap();
+        assert_eq!(vec![Effect::Invoke(NodeId::new("Review-Step"), Some("smv:///Case/child".parse().unwrap()))], effects);
+        execute("invoke", &[VariableValue::String("Review-Step".into())], resolve).unwrap_err();
+
+        let state_machine = VariableValue::String("sms:///4711/Review-Step".into());
+        let event = VariableValue::String("sme:///approve".into());
+        let effects = execute("send", &[state_machine, event.clone()], resolve).unwrap();
+        assert_eq!(
+            vec![Effect::Send("sms:///4711/Review-Step".into(), "sme:///approve".parse().unwrap())],
+            effects
+        );
+        // The referenced variable doesn't contain the id of a state machine.
+        execute("send", &[reference("smv:///Case/child"), event], resolve).unwrap_err();
+    }
+}
@@ -0,0 +1,527 @@
+use clap::{Parser, Subcommand};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use state_machines::coverage::Coverage;
+use state_machines::diagram::{self, Format};
+use state_machines::fuzz::{self, Settings};
+use state_machines::model_checker::{Bounds, Property, StateSpace, Verdict};
+use state_machines::state_charts::ActionCall;
+use state_machines::{lint, scenario, validation, EventId, Node, StateChartError, StateMachine};
+
+/// The address, the server is listening on by default.
+pub const DEFAULT_BIND: &str = "127.0.0.1:3000";
+/// The specification of the interface, which also contains the schema of the state charts.
+pub const DEFAULT_SPEC: &str = "StateMachines.yml";
+
+/// Validates, renders and simulates state charts offline, or serves the state machines.
+#[derive(Parser, Debug)]
+#[command(name = "state-machines", version, about)]
+pub struct Cli {
+    /// Without command, the server is started with the default settings.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Validates the state chart against the semantic rules.
+    Validate {
+        /// The state chart as JSON or as SCXML document (*.scxml).
+        chart: PathBuf,
+    },
+    /// Analyses the state chart and reports unreachable states, deadlocks, shadowed transitions
+    /// and events, which are never raised. Warnings let the command fail.
+    Lint {
+        /// The state chart as JSON or as SCXML document (*.scxml).
+        chart: PathBuf,
+    },
+    /// Renders the state chart as diagram to the standard output.
+    Render {
+        /// The state chart as JSON or as SCXML document (*.scxml).
+        chart: PathBuf,
+        /// One of dot, mermaid or plantuml.
+        #[arg(long, default_value = "dot")]
+        format: String,
+    },
+    /// Starts a state machine of the state chart and sends the events one after the other. The
+    /// configuration and the executed actions are printed after every step.
+    Simulate {
+        /// The state chart as JSON or as SCXML document (*.scxml).
+        chart: PathBuf,
+        /// The comma separated names or ids of the events.
+        #[arg(long, value_delimiter = ',')]
+        events: Vec<String>,
+    },
+    /// Runs the scenario files and reports for each file, if it passed or where it failed.
+    Scenario {
+        /// The scenarios as JSON files, which reference their state charts relative to the file.
+        #[arg(required = true)]
+        scenarios: Vec<PathBuf>,
+        /// The directory, the coverage of each state chart is written to as JSON and diagram.
+        #[arg(long)]
+        coverage: Option<PathBuf>,
+        /// The format of the coverage diagram: dot, mermaid or plantuml.
+        #[arg(long, default_value = "dot")]
+        format: String,
+    },
+    /// Reports the coverage of the state chart by the logs of its state machines in the database.
+    Coverage {
+        /// The state chart as JSON or as SCXML document (*.scxml).
+        chart: PathBuf,
+        /// The SQLite database file of the server.
+        #[arg(long)]
+        database: PathBuf,
+        /// The directory, the coverage is written to as JSON and diagram.
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+        /// The format of the coverage diagram: dot, mermaid or plantuml.
+        #[arg(long, default_value = "dot")]
+        format: String,
+    },
+    /// Checks the temporal properties of the state chart on its state space, e.g. "AG EF Done".
+    /// Properties, which are violated or undecided within the bounds, let the command fail.
+    Check {
+        /// The state chart as JSON or as SCXML document (*.scxml).
+        chart: PathBuf,
+        /// The properties with the names or paths of the nodes as atoms.
+        #[arg(long = "property", required = true)]
+        properties: Vec<String>,
+        /// The maximal number of steps from the start.
+        #[arg(long, default_value_t = Bounds::default().depth)]
+        depth: usize,
+        /// The maximal number of explored states.
+        #[arg(long, default_value_t = Bounds::default().states)]
+        states: usize,
+    },
+    /// Sends random inputs to state machines of the state chart and checks its invariants after
+    /// every step.
+    Fuzz {
+        /// The state chart as JSON or as SCXML document (*.scxml).
+        chart: PathBuf,
+        /// The seed of the first run. Without a seed, it is taken from the clock.
+        #[arg(long)]
+        seed: Option<u64>,
+        /// The number of runs, each with a new state machine.
+        #[arg(long, default_value_t = Settings::default().runs)]
+        runs: usize,
+        /// The number of inputs per run.
+        #[arg(long, default_value_t = Settings::default().steps)]
+        steps: usize,
+    },
+    /// Serves the state charts and state machines over HTTP.
+    Serve {
+        #[arg(long, default_value = DEFAULT_BIND)]
+        bind: SocketAddr,
+        /// The SQLite database file. Without a file, the data is kept in memory only.
+        #[arg(long)]
+        database: Option<PathBuf>,
+        #[arg(long, default_value = DEFAULT_SPEC)]
+        spec: PathBuf,
+    },
+}
+impl Default for Command {
+    fn default() -> Self {
+        Command::Serve { bind: DEFAULT_BIND.parse().unwrap(), database: None, spec: DEFAULT_SPEC.into() }
+    }
+}
+
+/// A command fails, if a file can't be read or the state chart is invalid.
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    StateChart(#[from] StateChartError),
+    #[error("The state chart can't be read: {0}")]
+    Read(state_machines::Error),
+    #[error("The server isn't available, as the http feature isn't enabled.")]
+    NoServer,
+    #[error("The state chart has {0} warnings.")]
+    Warnings(usize),
+    #[error("{0} of {1} scenarios failed.")]
+    ScenariosFailed(usize, usize),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("The database can't be read: {0}")]
+    Database(String),
+    #[error("{0} of {1} properties don't hold.")]
+    PropertiesFailed(usize, usize),
+    #[error("The run with seed {0} violates the invariants.")]
+    FuzzFailed(u64),
+    #[error("The server can't be started: {0}")]
+    Server(#[from] state_machines::Error),
+}
+
+/// Runs one of the offline commands and writes the result to the output. The server is started by
+/// the caller, as it needs the runtime of the http feature.
+pub fn run(command: Command, out: &mut impl Write) -> Result<(), CliError> {
+    match command {
+        Command::Validate { chart } => {
+            let state_chart = load_state_chart(&chart)?;
+            writeln!(out, "{}: {} is valid.", chart.display(), state_chart.id())?;
+        }
+        Command::Lint { chart } => {
+            let warnings = lint::lint(&load_state_chart(&chart)?);
+            for warning in &warnings {
+                writeln!(out, "{}: warning: {warning}", chart.display())?;
+            }
+            if !warnings.is_empty() {
+                return Err(CliError::Warnings(warnings.len()));
+            }
+            writeln!(out, "{}: no warnings.", chart.display())?;
+        }
+        Command::Render { chart, format } => {
+            let format = Format::try_from(format.as_str())?;
+            let state_chart = load_state_chart(&chart)?;
+            write!(out, "{}", diagram::render(&state_chart, format, &[])?)?;
+        }
+        Command::Simulate { chart, events } => simulate(load_state_chart(&chart)?, &events, out)?,
+        Command::Scenario { scenarios, coverage, format } => {
+            let format = Format::try_from(format.as_str())?;
+            run_scenarios(&scenarios, coverage.as_deref().map(|dir| (dir, format)), out)?
+        }
+        Command::Coverage { chart, database, out: dir, format } => {
+            let format = Format::try_from(format.as_str())?;
+            let mut coverage = Coverage::new(load_state_chart(&chart)?);
+            if !database.exists() {
+                return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} doesn't exist.", database.display())).into());
+            }
+            let pool = Pool::new(SqliteConnectionManager::file(&database)).map_err(|err| CliError::Database(err.to_string()))?;
+            let connection = pool.get().map_err(|err| CliError::Database(err.to_string()))?;
+            let logs = coverage.record_database(&connection).map_err(|err| CliError::Database(err.to_string()))?;
+            writeln!(out, "{logs} state machines in {}", database.display())?;
+            write_coverage(&coverage, &dir, format, out)?;
+        }
+        Command::Check { chart, properties, depth, states } => {
+            check_properties(&load_state_chart(&chart)?, &properties, &Bounds { depth, states }, out)?
+        }
+        Command::Fuzz { chart, seed, runs, steps } => {
+            let seed = seed.unwrap_or_else(clock_seed);
+            let state_chart = load_state_chart(&chart)?;
+            match fuzz::fuzz(&state_chart, &Settings { seed, runs, steps }) {
+                Ok(summary) => writeln!(
+                    out,
+                    "{}: {} inputs in {runs} runs from seed {seed}, {} rejected",
+                    state_chart.id(),
+                    summary.inputs,
+                    summary.rejected
+                )?,
+                Err(failure) => {
+                    write!(out, "{}: {failure}", state_chart.id())?;
+                    return Err(CliError::FuzzFailed(failure.seed));
+                }
+            }
+        }
+        Command::Serve { .. } => return Err(CliError::NoServer),
+    }
+    Ok(())
+}
+
+/// Reads the state chart from a JSON file or from an SCXML document, like the scenarios and the
+/// code generator do. The semantic of the state chart is validated too.
+pub fn load_state_chart(chart: &Path) -> Result<Node, CliError> {
+    let state_chart = Node::read(chart).map_err(CliError::Read)?;
+    validation::validate(&state_chart)?;
+    Ok(state_chart)
+}
+
+/// Sends the events to a new state machine of the state chart. Messages to other state machines
+/// aren't delivered, as there is no database with further state charts.
+fn simulate(state_chart: Node, events: &[String], out: &mut impl Write) -> Result<(), CliError> {
+    let mut state_machine = StateMachine::new(state_chart)?;
+    write_step(&mut state_machine, 0, out)?;
+    for event in events {
+        let event: EventId = if event.contains(':') { event.parse()? } else { format!("sme:///{event}").parse()? };
+        writeln!(out, "event: {event}")?;
+        let logged = state_machine.log().entries().len();
+        state_machine.send_event(&event)?;
+        write_step(&mut state_machine, logged, out)?;
+    }
+    Ok(())
+}
+
+/// Runs all scenarios, even if one of them fails, and writes the result of each scenario. The
+/// coverage of the state charts by the passed scenarios is written to the directory.
+fn run_scenarios(scenarios: &[PathBuf], coverage: Option<(&Path, Format)>, out: &mut impl Write) -> Result<(), CliError> {
+    let mut failed = 0;
+    let mut coverages: Vec<Coverage> = Vec::new();
+    for path in scenarios {
+        match scenario::run_file(path) {
+            Ok(mut outcome) => {
+                writeln!(out, "{}: ok", path.display())?;
+                let state_chart = outcome.state_machine.state_chart();
+                let index = match coverages.iter().position(|coverage| coverage.state_chart().id() == state_chart.id()) {
+                    Some(index) => index,
+                    None => {
+                        coverages.push(Coverage::new(state_chart.clone()));
+                        coverages.len() - 1
+                    }
+                };
+                coverages[index].record(outcome.state_machine.take_observations());
+            }
+            Err(err) => {
+                failed += 1;
+                writeln!(out, "{}: failed: {err}", path.display())?;
+            }
+        }
+    }
+    if let Some((dir, format)) = coverage {
+        for coverage in &coverages {
+            write_coverage(coverage, dir, format, out)?;
+        }
+    }
+    if failed > 0 {
+        return Err(CliError::ScenariosFailed(failed, scenarios.len()));
+    }
+    Ok(())
+}
+
+/// Explores the state space of the state chart and checks every property. The counter-example of
+/// a violated property is written after it.
+fn check_properties(state_chart: &Node, properties: &[String], bounds: &Bounds, out: &mut impl Write) -> Result<(), CliError> {
+    let properties = properties
+        .iter()
+        .map(|property| Ok((property, Property::parse(property, state_chart)?)))
+        .collect::<Result<Vec<(&String, Property)>, StateChartError>>()?;
+    let state_space = StateSpace::explore(state_chart, bounds)?;
+    let extent = if state_space.is_complete() { "all" } else { "bounded" };
+    writeln!(out, "{}: {} states explored ({extent})", state_chart.id(), state_space.len())?;
+    let mut failed = 0;
+    for (text, property) in &properties {
+        match state_space.check(property) {
+            Verdict::Holds => writeln!(out, "{text}: holds")?,
+            Verdict::Violated(counter_example) => {
+                failed += 1;
+                write!(out, "{text}: violated\n{counter_example}")?;
+            }
+            Verdict::Undecided => {
+                failed += 1;
+                writeln!(out, "{text}: undecided within {} steps and {} states", bounds.depth, bounds.states)?;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(CliError::PropertiesFailed(failed, properties.len()));
+    }
+    Ok(())
+}
+
+/// Writes the coverage report as JSON and the annotated diagram into the directory. Both files are
+/// named after the state chart. A summary is written to the output.
+fn write_coverage(coverage: &Coverage, dir: &Path, format: Format, out: &mut impl Write) -> Result<(), CliError> {
+    let report = coverage.report();
+    let name = coverage.state_chart().id().path()?.to_string();
+    std::fs::create_dir_all(dir)?;
+    let json = dir.join(format!("{name}.coverage.json"));
+    std::fs::write(&json, serde_json::to_string_pretty(&report)?)?;
+    std::fs::write(dir.join(format!("{name}.coverage.{}", format.extension())), coverage.render(format)?)?;
+    let ratio = |covered: usize, total: usize| format!("{covered}/{total}");
+    writeln!(
+        out,
+        "{name}: {} nodes, {} transitions, {} guards covered, see {}",
+        ratio(report.nodes().covered().len(), report.nodes().total()),
+        ratio(report.transitions().covered().len(), report.transitions().total()),
+        ratio(report.guards().covered().len(), report.guards().total()),
+        json.display()
+    )?;
+    Ok(())
+}
+
+/// Writes the executed actions, the log entries since the given position and the configuration.
+fn write_step(state_machine: &mut StateMachine, logged: usize, out: &mut impl Write) -> Result<(), CliError> {
+    state_machine.take_messages();
+    for (node_id, action_call) in state_machine.take_executed_actions() {
+        writeln!(out, "  action: {} in {}", format_action(&action_call), node_id)?;
+    }
+    for entry in state_machine.log().entries().iter().skip(logged) {
+        match entry.entry().columns() {
+            ("event", _, _) => {}
+            (entry_type, subject, Some(object)) => writeln!(out, "  {entry_type}: {subject} -> {object}")?,
+            (entry_type, subject, None) => writeln!(out, "  {entry_type}: {subject}")?,
+        }
+    }
+    let configuration: Vec<String> = state_machine.configuration()?.iter().map(|node_id| node_id.to_string()).collect();
+    writeln!(out, "configuration: {}", configuration.join(", "))?;
+    Ok(())
+}
+
+/// The seed of a fuzz run without given seed. It differs between the runs of the command.
+fn clock_seed() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+fn format_action(action_call: &ActionCall) -> String {
+    let parameters: Vec<String> = action_call
+        .parameters()
+        .iter()
+        .map(|parameter| format!("{}={}", parameter.name(), parameter.value()))
+        .collect();
+    format!("{}({})", action_call.name().as_str(), parameters.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_command(command: Command) -> Result<String, CliError> {
+        let mut out = Vec::new();
+        run(command, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let cli = Cli::try_parse_from(["state-machines", "simulate", "chart.json", "--events", "start,done"]).unwrap();
+        match cli.command {
+            Some(Command::Simulate { events, .. }) => assert_eq!(vec!["start", "done"], events),
+            _ => panic!("Expected the simulate command!"),
+        }
+        let cli = Cli::try_parse_from(["state-machines", "serve", "--bind", "0.0.0.0:8080", "--database", "sm.db"]).unwrap();
+        match cli.command {
+            Some(Command::Serve { bind, database, .. }) => {
+                assert_eq!(8080, bind.port());
+                assert_eq!(Some(PathBuf::from("sm.db")), database);
+            }
+            _ => panic!("Expected the serve command!"),
+        }
+        assert!(Cli::try_parse_from(["state-machines"]).unwrap().command.is_none());
+        assert!(Cli::try_parse_from(["state-machines", "scenario"]).is_err());
+    }
+
+    #[test]
+    fn test_validate_and_render() {
+        let validate = |chart: &str| Command::Validate { chart: chart.into() };
+        assert!(run_command(validate("tests/tally-task.json")).unwrap().contains("is valid"));
+        assert!(run_command(validate("tests/media-player.scxml")).unwrap().contains("is valid"));
+        assert!(matches!(run_command(validate("tests/missing.json")), Err(CliError::Read(state_machines::Error::Io(_)))));
+
+        let render = |format: &str| Command::Render {
+            chart: "tests/tally-task.json".into(),
+            format: format.into(),
+        };
+        assert!(run_command(render("dot")).unwrap().starts_with("digraph"));
+        match run_command(render("svg")) {
+            Err(CliError::StateChart(StateChartError::UnknownDiagramFormat(format))) => assert_eq!("svg", format),
+            _ => panic!("Expected an unknown diagram format!"),
+        }
+    }
+
+    #[test]
+    fn test_lint() {
+        let lint = |chart: &str| Command::Lint { chart: chart.into() };
+        assert_eq!("tests/media-player.scxml: no warnings.\n", run_command(lint("tests/media-player.scxml")).unwrap());
+        let mut out = Vec::new();
+        assert!(matches!(run(lint("tests/tally-task.json"), &mut out), Err(CliError::Warnings(1))));
+        assert_eq!(
+            "tests/tally-task.json: warning: State 'scn:///Tally-Task/Stopped' isn't final, but can't be left.\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_simulate() {
+        let output = run_command(Command::Simulate {
+            chart: "tests/tally-task.json".into(),
+            events: vec!["click".into(), "stop".into()],
+        })
+        .unwrap();
+        // The variables are qualified by the random id of the state machine.
+        let settings: Vec<&str> = output.lines().filter(|line| line.starts_with("  variable-setting")).collect();
+        assert_eq!(2, settings.len());
+        assert!(settings[0].ends_with("/Tally-Task/entries -> 1"));
+        assert!(settings[1].ends_with("/Tally-Task/clicks -> 1"));
+        let lines: Vec<&str> = output.lines().filter(|line| !line.starts_with("  variable-setting")).collect();
+        assert_eq!(
+            vec![
+                "  action: increment(variable=smv:///Tally-Task/entries) in scn:///Tally-Task/Counting",
+                "configuration: scn:///Tally-Task, scn:///Tally-Task/Counting",
+                "event: sme:///click",
+                "  action: increment(variable=smv:///Tally-Task/clicks) in scn:///Tally-Task/Counting",
+                "configuration: scn:///Tally-Task, scn:///Tally-Task/Counting",
+                "event: sme:///stop",
+                "  transaction: scn:///Tally-Task/Counting -> scn:///Tally-Task/Stopped",
+                "configuration: scn:///Tally-Task, scn:///Tally-Task/Stopped",
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_scenario() {
+        let scenarios = vec!["tests/scenarios/tally-task.json".into(), "tests/scenarios/media-player.json".into()];
+        let output = run_command(Command::Scenario { scenarios, coverage: None, format: "dot".into() }).unwrap();
+        assert_eq!("tests/scenarios/tally-task.json: ok\ntests/scenarios/media-player.json: ok\n", output);
+
+        let mut out = Vec::new();
+        let scenarios = vec!["tests/scenarios/approval.json".into(), "tests/scenarios/missing.json".into()];
+        let command = Command::Scenario { scenarios, coverage: None, format: "dot".into() };
+        assert!(matches!(run(command, &mut out), Err(CliError::ScenariosFailed(1, 2))));
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.starts_with("tests/scenarios/approval.json: ok\ntests/scenarios/missing.json: failed: "));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let dir = std::env::temp_dir().join(format!("state-machines-coverage-{}", std::process::id()));
+        let scenarios = vec!["tests/scenarios/approval.json".into(), "tests/scenarios/tally-task.json".into()];
+        let command = Command::Scenario { scenarios, coverage: Some(dir.clone()), format: "mermaid".into() };
+        let output = run_command(command).unwrap();
+        assert!(output.contains("Approval: 6/6 nodes, 4/5 transitions, 2/4 guards covered"));
+        let report: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("Approval.coverage.json")).unwrap()).unwrap();
+        assert_eq!(5, report["transitions"]["total"]);
+        assert!(std::fs::read_to_string(dir.join("Tally-Task.coverage.mmd")).unwrap().starts_with("stateDiagram-v2"));
+
+        let command = Command::Coverage {
+            chart: "tests/approval.json".into(),
+            database: dir.join("missing.db"),
+            out: dir.clone(),
+            format: "dot".into(),
+        };
+        assert!(matches!(run_command(command), Err(CliError::Io(_))));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_check() {
+        let check = |properties: &[&str]| Command::Check {
+            chart: "tests/approval.json".into(),
+            properties: properties.iter().map(|property| property.to_string()).collect(),
+            depth: 20,
+            states: 1000,
+        };
+        let output = run_command(check(&["!Approved W In-Approval", "AG EF Approved"])).unwrap();
+        assert!(output.ends_with("states explored (all)\n!Approved W In-Approval: holds\nAG EF Approved: holds\n"), "{output}");
+
+        let mut out = Vec::new();
+        assert!(matches!(run(check(&["AG EF Approved", "AG !Escalated"]), &mut out), Err(CliError::PropertiesFailed(1, 2))));
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("AG !Escalated: violated\n  0. start: Approval/Draft\n  1. set Approval/amount = 1001: Approval/In-Approval/Escalated\n"));
+        assert!(matches!(run_command(check(&["AG Unknown"])), Err(CliError::StateChart(StateChartError::InvalidProperty(_)))));
+    }
+
+    #[test]
+    fn test_fuzz() {
+        let fuzz = |chart: &str, seed: Option<u64>| Command::Fuzz {
+            chart: chart.into(),
+            seed,
+            runs: 2,
+            steps: 100,
+        };
+        let output = run_command(fuzz("tests/approval.json", Some(3))).unwrap();
+        assert!(output.starts_with("scn:///Approval: 200 inputs in 2 runs from seed 3, "), "{output}");
+        let output = run_command(fuzz("tests/media-player.scxml", Some(7))).unwrap();
+        assert!(output.contains(": 200 inputs in 2 runs from seed 7, "), "{output}");
+    }
+
+    #[test]
+    fn test_clock_seed() {
+        let seed = clock_seed();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        assert_ne!(seed, clock_seed());
+    }
+}
@@ -0,0 +1,431 @@
+//! Generates a typed state machine as Rust code from a state chart. The generated machine has no
+//! dependency on this crate, so it can be used in embedded code.
+//!
+//! The generator is run by the build script of the embedding crate:
+//!
+//! ```no_run
+//! // build.rs
+//! state_machines::codegen::build("charts/simple-task.json", "simple_task.rs").unwrap();
+//! ```
+//!
+//! The generated enums `State` and `Event` and the `Machine` are included into a module with
+//! `include!(concat!(env!("OUT_DIR"), "/simple_task.rs"));`. An invalid state chart fails the
+//! build.
+use std::collections::VecDeque;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::error::{Error, StateChartError};
+use crate::ids::{EventId, NodeId};
+use crate::node::{Node, NodeKind};
+use crate::state_charts::{Guard, Transition};
+use crate::state_machine::done_state;
+use crate::validation;
+
+/// The maximal number of eventless transitions and done events after a transition. More steps
+/// are a loop, which never comes to an end.
+const MAX_MICROSTEPS: usize = 100;
+
+/// The generation in a build script fails, if the state chart can't be read or generated.
+#[derive(Error, Debug)]
+pub enum CodegenError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Read(#[from] Error),
+    #[error("{0}")]
+    StateChart(#[from] StateChartError),
+    #[error("OUT_DIR isn't set, as the generator isn't run by a build script.")]
+    NoOutDir,
+}
+
+/// Reads the state chart as JSON or as SCXML document (*.scxml), validates it and writes the
+/// generated code into the file in OUT_DIR.
+pub fn build(chart: impl AsRef<Path>, file_name: &str) -> Result<(), CodegenError> {
+    let chart = chart.as_ref();
+    println!("cargo:rerun-if-changed={}", chart.display());
+    let state_chart = Node::read(chart)?;
+    let out_dir = std::env::var_os("OUT_DIR").ok_or(CodegenError::NoOutDir)?;
+    std::fs::write(Path::new(&out_dir).join(file_name), generate(&state_chart)?)?;
+    Ok(())
+}
+
+/// Generates the code of the state machine. The state chart is validated first.
+///
+/// Only the structure of the chart can be generated: states, final nodes and their hierarchy,
+/// transitions triggered by events, eventless transitions and the done events of final nodes.
+/// The state of the machine is the active leaf, as the transitions between the leaves are
+/// resolved while the code is generated.
+pub fn generate(state_chart: &Node) -> Result<String, StateChartError> {
+    validation::validate(state_chart)?;
+    check_supported(state_chart)?;
+    let chart = Chart::new(state_chart)?;
+    chart.code()
+}
+
+/// Rejects the constructs, which need the runtime of the state machine.
+fn check_supported(node: &Node) -> Result<(), StateChartError> {
+    let unsupported = |construct: &str| Err(StateChartError::UnsupportedCodegen(format!("{construct} of {}", node.id())));
+    if !matches!(node.kind(), NodeKind::State | NodeKind::Final) {
+        return unsupported(&format!("{} node", node.kind().as_str()));
+    }
+    if !node.attributes().is_empty() {
+        return unsupported("attributes");
+    }
+    if node.on_entry().is_some() || node.on_exit().is_some() {
+        return unsupported("entry or exit action");
+    }
+    if !node.deferred_events().is_empty() {
+        return unsupported("deferred events");
+    }
+    for transition in node.out_transitions() {
+        if transition.action().is_some() {
+            return unsupported("transition action");
+        }
+        if matches!(transition.guard(), Guard::Predicate(_) | Guard::Else) {
+            return unsupported("predicate guard");
+        }
+        if transition.condition().is_some() {
+            return unsupported("transition condition");
+        }
+    }
+    node.nodes().iter().try_for_each(check_supported)
+}
+
+/// The leaves and events of the state chart together with the resolved transitions.
+struct Chart<'a> {
+    root: &'a Node,
+    leaves: Vec<(&'a Node, String)>,
+    events: Vec<(EventId, String)>,
+}
+impl<'a> Chart<'a> {
+    fn new(root: &'a Node) -> Result<Self, StateChartError> {
+        let mut leaves = Vec::new();
+        collect_leaves(root, &mut leaves);
+        let leaves = leaves
+            .into_iter()
+            .map(|leaf| {
+                let path = leaf.id().path()?;
+                Ok((leaf, identifier(path.split_once('/').map_or(path, |(_, path)| path))))
+            })
+            .collect::<Result<Vec<_>, StateChartError>>()?;
+        let mut events: Vec<EventId> = Vec::new();
+        collect_events(root, &mut events);
+        let events = events
+            .into_iter()
+            .map(|event| {
+                let name = identifier(event.name());
+                (event, name)
+            })
+            .collect();
+        let chart = Chart { root, leaves, events };
+        chart.check_identifiers()?;
+        Ok(chart)
+    }
+
+    /// Different names may be the same identifier after the conversion.
+    fn check_identifiers(&self) -> Result<(), StateChartError> {
+        let states: Vec<&String> = self.leaves.iter().map(|(_, name)| name).collect();
+        let events: Vec<&String> = self.events.iter().map(|(_, name)| name).collect();
+        for (kind, names) in [("state", states), ("event", events)] {
+            if let Some(name) = names.iter().enumerate().find_map(|(i, name)| names[..i].contains(name).then_some(name)) {
+                return Err(StateChartError::UnsupportedCodegen(format!("ambiguous {kind} name {name}")));
+            }
+        }
+        Ok(())
+    }
+
+    fn node(&self, node_id: &NodeId) -> Result<&'a Node, StateChartError> {
+        self.root.find(node_id).ok_or_else(|| StateChartError::UnknownNode(node_id.clone()))
+    }
+
+    fn leaf_name(&self, leaf: &Node) -> &str {
+        self.leaves
+            .iter()
+            .find(|(other, _)| other.id() == leaf.id())
+            .map_or("", |(_, name)| name.as_str())
+    }
+
+    /// The leaf, which is entered with the node: the start node or the first sub-node.
+    fn default_leaf(&self, node: &'a Node) -> Result<&'a Node, StateChartError> {
+        let start = match (node.start_node(), node.nodes().first()) {
+            (Some(start_node), _) => self.node(start_node)?,
+            (None, Some(first)) => first,
+            (None, None) => return Ok(node),
+        };
+        self.default_leaf(start)
+    }
+
+    /// The transition from the leaf up to the root, which matches the event or is eventless
+    /// without event. The transition with the highest priority is taken, between transitions of
+    /// the same priority the one of the innermost node and then the first one of the node.
+    fn enabled_transition(&self, leaf: &Node, event: Option<&EventId>) -> Result<Option<&'a Transition>, StateChartError> {
+        let path = self.root.path_to(leaf.id()).ok_or_else(|| StateChartError::UnknownNode(leaf.id().clone()))?;
+        let mut enabled: Option<&'a Transition> = None;
+        for node in path.iter().rev() {
+            for transition in node.out_transitions() {
+                let matches = match (transition.guard(), event) {
+                    (Guard::Event(guard), Some(event)) => guard == event,
+                    (Guard::Completion, None) => true,
+                    _ => false,
+                };
+                if matches && enabled.is_none_or(|enabled| transition.priority() > enabled.priority()) {
+                    enabled = Some(transition);
+                }
+            }
+        }
+        Ok(enabled)
+    }
+
+    /// Takes the transition and provides the new leaf. Entering a final node raises the done
+    /// event of its parent.
+    fn take(&self, leaf: &'a Node, transition: &Transition, raised: &mut VecDeque<EventId>) -> Result<&'a Node, StateChartError> {
+        let to = match transition.to() {
+            Some(to) => to,
+            None => return Ok(leaf),
+        };
+        let next = self.default_leaf(self.node(to)?)?;
+        if next.kind() == NodeKind::Final {
+            let path = self.root.path_to(next.id()).ok_or_else(|| StateChartError::UnknownNode(next.id().clone()))?;
+            if path.len() > 1 {
+                raised.push_back(done_state(path[path.len() - 2].id())?);
+            }
+        }
+        Ok(next)
+    }
+
+    /// Takes the eventless transitions and the transitions of the raised events, until the leaf
+    /// is stable.
+    fn settle(&self, mut leaf: &'a Node, mut raised: VecDeque<EventId>) -> Result<&'a Node, StateChartError> {
+        for _ in 0..MAX_MICROSTEPS {
+            if let Some(transition) = self.enabled_transition(leaf, None)? {
+                leaf = self.take(leaf, transition, &mut raised)?;
+            } else if let Some(event) = raised.pop_front() {
+                if let Some(transition) = self.enabled_transition(leaf, Some(&event))? {
+                    leaf = self.take(leaf, transition, &mut raised)?;
+                }
+            } else {
+                return Ok(leaf);
+            }
+        }
+        Err(StateChartError::UnsupportedCodegen(format!("eventless loop from {}", leaf.id())))
+    }
+
+    /// The leaf after the event, if an active node has a transition for it.
+    fn next(&self, leaf: &'a Node, event: &EventId) -> Result<Option<&'a Node>, StateChartError> {
+        match self.enabled_transition(leaf, Some(event))? {
+            Some(transition) => {
+                let mut raised = VecDeque::new();
+                let next = self.take(leaf, transition, &mut raised)?;
+                Ok(Some(self.settle(next, raised)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn code(&self) -> Result<String, StateChartError> {
+        let name = self.root.id().path()?;
+        let initial = self.leaf_name(self.settle(self.default_leaf(self.root)?, VecDeque::new())?);
+        let mut transitions = String::new();
+        for (leaf, leaf_name) in &self.leaves {
+            for (event, event_name) in &self.events {
+                if let Some(next) = self.next(leaf, event)? {
+                    let next = self.leaf_name(next);
+                    transitions += &format!("            (State::{leaf_name}, Event::{event_name}) => State::{next},\n");
+                }
+            }
+        }
+        let send = if transitions.is_empty() {
+            "        let _ = event;\n        false\n".to_string()
+        } else {
+            format!("        self.state = match (self.state, event) {{\n{transitions}            _ => return false,\n        }};\n        true\n")
+        };
+        let states: String = self.leaves.iter().map(|(_, leaf_name)| format!("    {leaf_name},\n")).collect();
+        let node_ids: String = self
+            .leaves
+            .iter()
+            .map(|(leaf, leaf_name)| format!("            State::{leaf_name} => {:?},\n", leaf.id().as_str()))
+            .collect();
+        let events: String = self.events.iter().map(|(_, event_name)| format!("    {event_name},\n")).collect();
+        let event_ids = if self.events.is_empty() {
+            "        match *self {}\n".to_string()
+        } else {
+            let arms: String = self
+                .events
+                .iter()
+                .map(|(event, event_name)| format!("            Event::{event_name} => {:?},\n", event.as_str()))
+                .collect();
+            format!("        match self {{\n{arms}        }}\n")
+        };
+        Ok(format!(
+            r#"// Generated from the state chart {id}. Don't edit it.
+
+/// The leaves of the state chart {name}.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum State {{
+{states}}}
+#[allow(dead_code)]
+impl State {{
+    /// The id of the node in the state chart.
+    pub fn node_id(&self) -> &'static str {{
+        match self {{
+{node_ids}        }}
+    }}
+}}
+
+/// The events of the state chart {name}.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Event {{
+{events}}}
+#[allow(dead_code)]
+impl Event {{
+    /// The id of the event in the state chart.
+    pub fn event_id(&self) -> &'static str {{
+{event_ids}    }}
+}}
+
+/// A state machine of the state chart {name}.
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Machine {{
+    state: State,
+}}
+#[allow(dead_code)]
+impl Machine {{
+    pub fn new() -> Self {{
+        Machine {{ state: State::{initial} }}
+    }}
+
+    pub fn state(&self) -> State {{
+        self.state
+    }}
+
+    /// Sends the event to the machine. Provides false, if no active node has a transition for
+    /// the event.
+    pub fn send(&mut self, event: Event) -> bool {{
+{send}    }}
+}}
+impl Default for Machine {{
+    fn default() -> Self {{
+        Self::new()
+    }}
+}}
+"#,
+            id = self.root.id()
+        ))
+    }
+}
+
+fn collect_leaves<'a>(node: &'a Node, leaves: &mut Vec<&'a Node>) {
+    if node.nodes().is_empty() {
+        leaves.push(node);
+    }
+    for sub_node in node.nodes() {
+        collect_leaves(sub_node, leaves);
+    }
+}
+
+fn collect_events(node: &Node, events: &mut Vec<EventId>) {
+    for transition in node.out_transitions() {
+        if let Guard::Event(event) = transition.guard() {
+            if !events.contains(event) {
+                events.push(event.clone());
+            }
+        }
+    }
+    for sub_node in node.nodes() {
+        collect_events(sub_node, events);
+    }
+}
+
+/// Converts a name or path into a Rust identifier in camel case, e.g. "In-Progress" into
+/// "InProgress" and "done.state.Playback" into "DoneStatePlayback".
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or(String::new(), |first| first.to_uppercase().chain(chars).collect())
+        })
+        .collect();
+    if !identifier.starts_with(|c: char| c.is_alphabetic()) {
+        identifier.insert(0, 'N');
+    }
+    identifier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_charts::ActionCall;
+
+    mod simple_task {
+        include!("../tests/generated/simple_task.rs");
+    }
+
+    #[test]
+    fn test_generate() {
+        let code = generate(&Node::read("tests/simple-task.json").unwrap()).unwrap();
+        assert_eq!(include_str!("../tests/generated/simple_task.rs"), code);
+
+        use simple_task::{Event, Machine, State};
+        let mut machine = Machine::new();
+        assert_eq!(State::New, machine.state());
+        assert!(!machine.send(Event::Done));
+        assert!(machine.send(Event::Start));
+        assert!(machine.send(Event::Done));
+        assert_eq!(State::Done, machine.state());
+        assert_eq!("scn:///Simple-Task/Done", machine.state().node_id());
+        assert_eq!("sme:///start", Event::Start.event_id());
+    }
+
+    #[test]
+    fn test_hierarchy_and_done_events() {
+        let order = Node::builder("Order")
+            .start_node("Open")
+            .state(
+                Node::builder("Open")
+                    .start_node("Editing")
+                    .transition(Transition::on("cancel").to("Cancelled"))
+                    .transition(Transition::on("done.state.Open").to("Shipped"))
+                    .state(Node::builder("Editing").transition(Transition::on("submit").to("Submitted")))
+                    .state(Node::builder("Submitted").kind(NodeKind::Final)),
+            )
+            .state(Node::builder("Cancelled"))
+            .state(Node::builder("Shipped").transition(Transition::completion().to("Archived")))
+            .state(Node::builder("Archived"))
+            .build()
+            .unwrap();
+        let code = generate(&order).unwrap();
+        assert!(code.contains("Machine { state: State::OpenEditing }"));
+        assert!(code.contains("(State::OpenEditing, Event::Submit) => State::Archived,"));
+        assert!(code.contains("(State::OpenEditing, Event::Cancel) => State::Cancelled,"));
+        assert!(code.contains("(State::OpenSubmitted, Event::Cancel) => State::Cancelled,"));
+        assert!(code.contains("    DoneStateOpen,\n"));
+        assert!(!code.contains("(State::Cancelled,"));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        match generate(&Node::read("tests/tally-task.json").unwrap()) {
+            Err(StateChartError::UnsupportedCodegen(construct)) => assert!(construct.starts_with("attributes")),
+            _ => panic!("Expected an unsupported construct!"),
+        }
+        let loop_chart = Node::builder("Loop")
+            .start_node("A")
+            .state(Node::builder("A").transition(Transition::completion().to("B")))
+            .state(Node::builder("B").transition(Transition::completion().to("A")))
+            .build()
+            .unwrap();
+        assert!(matches!(generate(&loop_chart), Err(StateChartError::UnsupportedCodegen(_))));
+        let action = Node::builder("Action")
+            .start_node("A")
+            .state(Node::builder("A").transition(Transition::on("go").action(ActionCall::builder("increment"))))
+            .build()
+            .unwrap();
+        assert!(matches!(generate(&action), Err(StateChartError::UnsupportedCodegen(_))));
+    }
+}
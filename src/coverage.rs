@@ -0,0 +1,343 @@
+//! The coverage of a state chart shows, which nodes were entered, which transitions fired and
+//! which predicates were evaluated both true and false. It is collected from the observations of
+//! state machines, e.g. while scenarios are run, or from the logs of the state machines in the
+//! database.
+//!
+//! The report is serialized as JSON and the missed transitions are marked in the diagram.
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::diagram::{self, Format};
+use crate::error::{Error, StateChartError};
+use crate::ids::{NodeId, StateId};
+use crate::node::{Node, NodeKind};
+use crate::sql::Crud;
+use crate::state_charts::Guard;
+use crate::state_machine::Observation;
+use crate::state_machine_log::{LogEntryType, StateMachineLog};
+
+/// The coverage of a state chart by any number of state machines.
+#[derive(Debug)]
+pub struct Coverage {
+    state_chart: Node,
+    entered: BTreeSet<NodeId>,
+    fired: BTreeSet<(NodeId, usize)>,
+    /// The results of the predicate of each transition.
+    outcomes: BTreeMap<(NodeId, usize), BTreeSet<bool>>,
+}
+
+/// The covered and the missed parts of the state chart.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct CoverageReport {
+    chart: NodeId,
+    nodes: Summary<NodeId>,
+    transitions: Summary<TransitionReference>,
+    guards: Summary<TransitionReference>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Summary<T> {
+    total: usize,
+    covered: Vec<T>,
+    missed: Vec<T>,
+}
+
+/// A transition, addressed by its source and its index in the out transitions of the source. The
+/// label and the target make the report readable. The outcomes are the observed results of the
+/// predicate.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct TransitionReference {
+    source: NodeId,
+    index: usize,
+    label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<NodeId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    outcomes: Vec<bool>,
+}
+
+impl Coverage {
+    pub fn new(state_chart: Node) -> Self {
+        Coverage { state_chart, entered: BTreeSet::new(), fired: BTreeSet::new(), outcomes: BTreeMap::new() }
+    }
+
+    pub fn state_chart(&self) -> &Node {
+        &self.state_chart
+    }
+
+    /// Records the observations of a state machine of the state chart.
+    pub fn record(&mut self, observations: impl IntoIterator<Item = Observation>) {
+        for observation in observations {
+            match observation {
+                Observation::Entered(node_id) => {
+                    self.entered.insert(node_id);
+                }
+                Observation::Fired(node_id, index) => {
+                    self.fired.insert((node_id, index));
+                }
+                Observation::Evaluated(node_id, index, result) => {
+                    self.outcomes.entry((node_id, index)).or_default().insert(result);
+                }
+            }
+        }
+    }
+
+    /// Records the log of a state machine of the state chart. The log only names the first state,
+    /// which was reached by a transition, and doesn't contain the results of the predicates. The
+    /// transition is the first one of the left state or its ancestors, which leads to the reached
+    /// state. So the coverage of a log is a lower bound of the real coverage. The nodes entered
+    /// by default, when the state machine was started, are always covered.
+    pub fn record_log(&mut self, log: &StateMachineLog) {
+        let mut entered: Vec<NodeId> = self.state_chart.default_entry().iter().map(|node| node.id().clone()).collect();
+        for entry in log.entries() {
+            if let LogEntryType::Transaction(from, to) = entry.entry() {
+                chain(&self.state_chart, to, &mut entered);
+                if let Some(fired) = self.fired_transition(from, to) {
+                    self.fired.insert(fired);
+                }
+            }
+        }
+        self.entered.extend(entered);
+    }
+
+    /// Records the logs of all state machines of the state chart, which are stored in the
+    /// database.
+    /// Provides the number of the recorded logs.
+    pub fn record_database(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<usize, Error> {
+        let sql = "SELECT id FROM StateMachine WHERE state_chart = ? ORDER BY rowid";
+        let mut statement = connection.prepare(sql)?;
+        let ids = statement
+            .query_map(params![self.state_chart.id()], |row| row.get(0))?
+            .collect::<Result<Vec<StateId>, rusqlite::Error>>()?;
+        for id in &ids {
+            if let Some(log) = StateMachineLog::select(connection, id)? {
+                self.record_log(&log);
+            }
+        }
+        Ok(ids.len())
+    }
+
+    /// Provides the covered and missed nodes, transitions and predicates. Pseudo nodes are never
+    /// entered, so they aren't part of the nodes. A predicate or condition is covered, if it was
+    /// both true and false.
+    pub fn report(&self) -> CoverageReport {
+        let mut nodes = Summary::new();
+        let mut transitions = Summary::new();
+        let mut guards = Summary::new();
+        let mut all = Vec::new();
+        collect(&self.state_chart, &mut all);
+        for node in all {
+            if !node.kind().is_pseudo() {
+                nodes.add(node.id().clone(), self.entered.contains(node.id()));
+            }
+            for (index, transition) in node.out_transitions().iter().enumerate() {
+                let key = (node.id().clone(), index);
+                let outcomes: Vec<bool> = self.outcomes.get(&key).map(|outcomes| outcomes.iter().copied().collect()).unwrap_or_default();
+                let reference = TransitionReference {
+                    source: node.id().clone(),
+                    index,
+                    label: diagram::label(transition),
+                    to: transition.to().cloned(),
+                    outcomes,
+                };
+                if matches!(transition.guard(), Guard::Predicate(_)) || transition.condition().is_some() {
+                    let covered = reference.outcomes.len() == 2;
+                    guards.add(reference.clone(), covered);
+                }
+                transitions.add(reference, self.fired.contains(&key));
+            }
+        }
+        CoverageReport { chart: self.state_chart.id().clone(), nodes, transitions, guards }
+    }
+
+    /// Renders the state chart with the entered nodes highlighted and the missed transitions
+    /// marked.
+    pub fn render(&self, format: Format) -> Result<String, StateChartError> {
+        let entered: Vec<NodeId> = self.entered.iter().cloned().collect();
+        let missed: Vec<(NodeId, usize)> = self
+            .report()
+            .transitions
+            .missed
+            .into_iter()
+            .map(|reference| (reference.source, reference.index))
+            .collect();
+        diagram::render_annotated(&self.state_chart, format, &entered, &missed)
+    }
+
+    /// Finds the transition from the left state or one of its ancestors to the reached state.
+    fn fired_transition(&self, from: &NodeId, to: &NodeId) -> Option<(NodeId, usize)> {
+        let mut sources = Vec::new();
+        chain(&self.state_chart, from, &mut sources);
+        for source in sources.iter().rev() {
+            let node = self.state_chart.find(source)?;
+            for (index, transition) in node.out_transitions().iter().enumerate() {
+                let leads_to = |target: &NodeId| match self.state_chart.find(target).map(Node::kind) {
+                    Some(NodeKind::History | NodeKind::DeepHistory) => parent(target).is_some_and(|parent| contains(&parent, to)),
+                    _ => contains(target, to),
+                };
+                if transition.to().is_some_and(leads_to) {
+                    return Some((source.clone(), index));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T> Summary<T> {
+    fn new() -> Self {
+        Summary { total: 0, covered: Vec::new(), missed: Vec::new() }
+    }
+
+    fn add(&mut self, item: T, covered: bool) {
+        self.total += 1;
+        if covered {
+            self.covered.push(item);
+        } else {
+            self.missed.push(item);
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn covered(&self) -> &Vec<T> {
+        &self.covered
+    }
+
+    pub fn missed(&self) -> &Vec<T> {
+        &self.missed
+    }
+}
+
+impl CoverageReport {
+    pub fn nodes(&self) -> &Summary<NodeId> {
+        &self.nodes
+    }
+
+    pub fn transitions(&self) -> &Summary<TransitionReference> {
+        &self.transitions
+    }
+
+    pub fn guards(&self) -> &Summary<TransitionReference> {
+        &self.guards
+    }
+}
+
+impl TransitionReference {
+    pub fn source(&self) -> &NodeId {
+        &self.source
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// Collects the node and all its descendants in the order of the state chart.
+fn collect<'a>(node: &'a Node, nodes: &mut Vec<&'a Node>) {
+    nodes.push(node);
+    for sub_node in node.nodes() {
+        collect(sub_node, nodes);
+    }
+}
+
+/// Collects the ids of the node and all its ancestors, starting with the root.
+fn chain(state_chart: &Node, node_id: &NodeId, chain: &mut Vec<NodeId>) {
+    let mut node = state_chart;
+    loop {
+        chain.push(node.id().clone());
+        match node.nodes().iter().find(|sub_node| contains(sub_node.id(), node_id)) {
+            Some(sub_node) => node = sub_node,
+            None => break,
+        }
+    }
+}
+
+/// Checks, if the node is the ancestor or the node itself.
+fn contains(ancestor: &NodeId, node_id: &NodeId) -> bool {
+    node_id == ancestor || node_id.as_str().starts_with(&format!("{}/", ancestor.as_str()))
+}
+
+fn parent(node_id: &NodeId) -> Option<NodeId> {
+    node_id.as_str().rsplit_once('/').map(|(parent, _)| NodeId::from(parent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario;
+    use crate::state_machine::StateMachine;
+    use r2d2::Pool;
+    use std::path::Path;
+
+    fn paths(node_ids: &[NodeId]) -> Vec<&str> {
+        node_ids.iter().map(|node_id| node_id.path().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_scenario_coverage() {
+        let mut state_machine = scenario::run_file(Path::new("tests/scenarios/approval.json")).unwrap().state_machine;
+        let mut coverage = Coverage::new(state_machine.state_chart().clone());
+        coverage.record(state_machine.take_observations());
+        let report = coverage.report();
+
+        assert_eq!(6, report.nodes().total());
+        assert!(report.nodes().missed().is_empty());
+        // The cancelled approval re-enters the checking, as the amount is still positive.
+        assert_eq!(5, report.transitions().total());
+        let missed: Vec<(&str, usize)> =
+            report.transitions().missed().iter().map(|reference| (reference.source().path().unwrap(), reference.index())).collect();
+        assert_eq!(vec![("Approval/In-Approval/Checking", 1)], missed);
+        let covered: Vec<&str> = report.guards().covered().iter().map(|reference| reference.source().path().unwrap()).collect();
+        assert_eq!(vec!["Approval/Draft", "Approval/In-Approval/Escalated"], covered);
+        assert_eq!(2, report.guards().missed().len());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!("scn:///Approval", json["chart"]);
+        assert_eq!(serde_json::json!([true]), json["guards"]["missed"][0]["outcomes"]);
+        assert!(json["guards"]["missed"][1].get("outcomes").is_none());
+
+        let dot = coverage.render(Format::Dot).unwrap();
+        assert!(dot.contains(
+            "\"Approval/In-Approval/Checking\" -> \"Approval/Approved\" [color=red, fontcolor=red, penwidth=2, label=\"[is-true(approved)]\"]"
+        ));
+        assert!(dot.contains("\"Approval/Approved\" [label=\"Approved\", style=\"rounded,filled\""));
+        let mermaid = coverage.render(Format::Mermaid).unwrap();
+        assert!(mermaid.contains("Approval_In_Approval_Checking --> Approval_Approved : [is-true(approved)] (missed)"));
+    }
+
+    #[test]
+    fn test_log_coverage() {
+        let state_chart = Node::read("tests/approval.json").unwrap();
+        let manager = SqliteConnectionManager::memory();
+        let pool = Pool::builder().max_size(1).build(manager).unwrap();
+        let connection = pool.get().unwrap();
+        StateMachine::create(&connection).unwrap();
+
+        let mut state_machine = StateMachine::new(state_chart.clone()).unwrap();
+        let amount = format!("smv:///{}/Approval/amount", state_machine.id().id().unwrap()).parse().unwrap();
+        state_machine.set_variable(&amount, &open_api_matcher::ValidatedValue::Number(10.0)).unwrap();
+        state_machine.send_event(&"sme:///cancel".parse().unwrap()).unwrap();
+        state_machine.insert(&connection).unwrap();
+
+        let mut coverage = Coverage::new(state_chart);
+        assert_eq!(1, coverage.record_database(&connection).unwrap());
+        let report = coverage.report();
+        assert_eq!(
+            vec!["Approval/In-Approval/Escalated", "Approval/Approved"],
+            paths(report.nodes().missed())
+        );
+        let fired: Vec<(&str, usize)> =
+            report.transitions().covered().iter().map(|reference| (reference.source().path().unwrap(), reference.index())).collect();
+        assert_eq!(vec![("Approval/Draft", 0), ("Approval/In-Approval", 0)], fired);
+        assert!(report.guards().covered().is_empty());
+    }
+}
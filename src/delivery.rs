@@ -0,0 +1,347 @@
+use log::debug;
+use open_api_matcher::{ValidatedValue, Value};
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::error::{Error, StateChartError};
+use crate::ids::{EventId, NodeId, StateId, VariableId};
+use crate::node::Node;
+use crate::sql::Crud;
+use crate::state_machine::{Message, StateMachine};
+use crate::state_machine_log::LogEntryType;
+
+/// The maximal number of messages, delivered for a single request. State machines, which exceed
+/// the limit, most likely send events to each other in a loop.
+const MAX_MESSAGES: usize = 1000;
+
+/// The reaction of a state machine to an event or a changed variable. The chain contains all
+/// entries, which were logged in the meantime, including the events of other state machines.
+#[derive(Debug)]
+pub struct Reaction {
+    chain: Vec<LogEntryType>,
+    enabled_events: Vec<EventId>,
+}
+impl Reaction {
+    pub fn chain(&self) -> &Vec<LogEntryType> {
+        &self.chain
+    }
+
+    pub fn enabled_events(&self) -> &Vec<EventId> {
+        &self.enabled_events
+    }
+}
+
+/// Provides the reaction as object with the chain and the enabled events.
+impl From<&Reaction> for Value {
+    fn from(reaction: &Reaction) -> Self {
+        let mut content: BTreeMap<String, Value> = BTreeMap::new();
+        content.insert("chain".into(), reaction.chain.iter().map(Value::from).collect::<Vec<Value>>().into());
+        content.insert(
+            "enabled-events".into(),
+            reaction.enabled_events.iter().map(Value::from).collect::<Vec<Value>>().into(),
+        );
+        Value::Object(content)
+    }
+}
+
+/// Starts a new state machine from the state chart with the given id. The state machine and all
+/// state machines, it invoked, are saved, before its id is returned.
+pub fn start(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    state_chart_id: &NodeId,
+) -> Result<StateId, Error> {
+    debug!("[delivery::start({state_chart_id})]");
+    let mut delivery = Delivery::new(connection);
+    let state_machine = StateMachine::new(select_state_chart(connection, state_chart_id)?)?;
+    let id = state_machine.id().clone();
+    delivery.add(state_machine);
+    delivery.deliver(&id)?;
+    delivery.save()?;
+    Ok(id)
+}
+
+/// Sends the event to the state machine and delivers all messages to other state machines,
+/// which follow from it.
+pub fn send(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    state_machine_id: &StateId,
+    event: &EventId,
+) -> Result<Reaction, Error> {
+    debug!("[delivery::send({state_machine_id}, {event})]");
+    let mut delivery = Delivery::new(connection);
+    delivery.machine(state_machine_id)?.send_event(event)?;
+    delivery.deliver(state_machine_id)?;
+    delivery.reaction(state_machine_id)
+}
+
+/// Sets the variable of the state machine and delivers all messages to other state machines,
+/// which follow from it.
+pub fn set_variable(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    state_machine_id: &StateId,
+    variable_id: &VariableId,
+    value: &ValidatedValue,
+) -> Result<Reaction, Error> {
+    debug!("[delivery::set_variable({state_machine_id}, {variable_id})]");
+    let mut delivery = Delivery::new(connection);
+    delivery.machine(state_machine_id)?.set_variable(variable_id, value)?;
+    delivery.deliver(state_machine_id)?;
+    delivery.reaction(state_machine_id)
+}
+
+fn select_state_chart(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    state_chart_id: &NodeId,
+) -> Result<Node, Error> {
+    match Node::select(connection, state_chart_id)? {
+        Some(state_chart) => Ok(state_chart),
+        None => Err(StateChartError::UnknownStateChart(state_chart_id.clone()).into()),
+    }
+}
+
+/// The state machines, which take part in the processing of a single request. All messages are
+/// delivered, before any state machine is saved. Nothing is saved, if a message can't be
+/// delivered.
+struct Delivery<'a> {
+    connection: &'a PooledConnection<SqliteConnectionManager>,
+    /// The state machines in the order, they joined the delivery.
+    machines: Vec<StateMachine>,
+    /// The state machines, which were started by this delivery and must be inserted.
+    started: Vec<StateId>,
+    /// The state machines, which were cancelled by this delivery and must be deleted.
+    cancelled: Vec<StateMachine>,
+}
+impl<'a> Delivery<'a> {
+    fn new(connection: &'a PooledConnection<SqliteConnectionManager>) -> Self {
+        Delivery { connection, machines: Vec::new(), started: Vec::new(), cancelled: Vec::new() }
+    }
+
+    fn add(&mut self, state_machine: StateMachine) {
+        self.started.push(state_machine.id().clone());
+        self.machines.push(state_machine);
+    }
+
+    /// Provides the state machine with the given id. It is selected from the database, if it
+    /// doesn't take part in the delivery yet.
+    fn machine(&mut self, id: &StateId) -> Result<&mut StateMachine, Error> {
+        let index = match self.machines.iter().position(|state_machine| state_machine.id() == id) {
+            Some(index) => index,
+            None => match StateMachine::select(self.connection, id)? {
+                Some(state_machine) => {
+                    self.machines.push(state_machine);
+                    self.machines.len() - 1
+                }
+                None => return Err(StateChartError::UnknownStateMachine(id.clone()).into()),
+            },
+        };
+        Ok(&mut self.machines[index])
+    }
+
+    fn is_cancelled(&self, id: &StateId) -> bool {
+        self.cancelled.iter().any(|state_machine| state_machine.id() == id)
+    }
+
+    /// Delivers the messages of all state machines, until no state machine has a message left.
+    /// Messages to a state machine, which was cancelled in the meantime, are dropped.
+    fn deliver(&mut self, origin: &StateId) -> Result<(), Error> {
+        let mut messages = self.take_messages();
+        for _ in 0..=MAX_MESSAGES {
+            let message = match messages.pop_front() {
+                Some(message) => message,
+                None => return Ok(()),
+            };
+            debug!("[delivery::deliver({message:?})]");
+            match message {
+                Message::Start { state_chart, child, parent } => {
+                    let state_chart = select_state_chart(self.connection, &state_chart)?;
+                    self.add(StateMachine::invoked(state_chart, child, parent)?);
+                }
+                Message::Send(to, _) | Message::Done { parent: to, .. } if self.is_cancelled(&to) => {}
+                Message::Send(to, event) => {
+                    self.machine(&to)?.send_event(&event)?;
+                }
+                Message::Done { parent, child } => {
+                    self.machine(&parent)?.child_done(&child)?;
+                }
+                Message::Cancel(child) => {
+                    let index = self.machines.iter().position(|state_machine| state_machine.id() == &child);
+                    let mut state_machine = match index {
+                        Some(index) => self.machines.remove(index),
+                        None => match StateMachine::select(self.connection, &child)? {
+                            Some(state_machine) => state_machine,
+                            None => continue,
+                        },
+                    };
+                    state_machine.cancel();
+                    self.cancelled.push(state_machine);
+                }
+            }
+            messages.extend(self.take_messages());
+        }
+        Err(StateChartError::MessageLoop(origin.clone()).into())
+    }
+
+    /// Takes the messages of all state machines.
+    fn take_messages(&mut self) -> VecDeque<Message> {
+        self.machines
+            .iter_mut()
+            .chain(self.cancelled.iter_mut())
+            .flat_map(|state_machine| state_machine.take_messages())
+            .collect()
+    }
+
+    /// Provides the reaction of the state machine and saves all state machines of the delivery.
+    fn reaction(mut self, id: &StateId) -> Result<Reaction, Error> {
+        let state_machine = match self.cancelled.iter().find(|state_machine| state_machine.id() == id) {
+            Some(state_machine) => state_machine,
+            None => self.machine(id)?,
+        };
+        let reaction = Reaction {
+            chain: state_machine.log().new_entries().map(|entry| entry.entry().clone()).collect(),
+            enabled_events: state_machine.enabled_events()?,
+        };
+        self.save()?;
+        Ok(reaction)
+    }
+
+    /// Inserts the started, updates the changed and deletes the cancelled state machines. All of
+    /// them are saved in one transaction, so that a failure doesn't leave the state machines
+    /// partially saved.
+    fn save(&mut self) -> Result<(), Error> {
+        let transaction = self.connection.unchecked_transaction()?;
+        for state_machine in self.machines.iter_mut() {
+            if self.started.contains(state_machine.id()) {
+                state_machine.insert(self.connection)?;
+            } else {
+                state_machine.save(self.connection)?;
+            }
+        }
+        for state_machine in &self.cancelled {
+            if !self.started.contains(state_machine.id()) {
+                state_machine.delete(self.connection)?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::NodeKind;
+    use crate::state_charts::{ActionCall, Transition, VariableValue};
+    use open_api_matcher::OpenApi;
+    use r2d2::Pool;
+
+    fn create_db_connection() -> PooledConnection<SqliteConnectionManager> {
+        let manager = r2d2_sqlite::SqliteConnectionManager::memory();
+        let pool = Pool::builder().max_size(1).build(manager).unwrap();
+        let connection = pool.get().unwrap();
+        Node::create(&connection).unwrap();
+        StateMachine::create(&connection).unwrap();
+        let open_api_file = std::fs::File::open("StateMachines.yml").unwrap();
+        let open_api = OpenApi::new(&open_api_file).unwrap();
+        let sc_schema = open_api.get_schema("#/components/schemas/Node").unwrap();
+        for file_name in ["tests/case-task.json", "tests/review-step.json"] {
+            let sc = std::fs::read_to_string(file_name).unwrap();
+            let vvsc = ValidatedValue::new(&sc, &sc_schema, &open_api).unwrap();
+            let mut state_chart: Node = (&vvsc).try_into().unwrap();
+            state_chart.insert(&connection).unwrap();
+        }
+        connection
+    }
+
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, id: &StateId) -> Option<StateMachine> {
+        StateMachine::select(connection, id).unwrap()
+    }
+
+    /// Starts the case and provides the ids of the case and of the invoked review step.
+    fn start_case(connection: &PooledConnection<SqliteConnectionManager>) -> (StateId, StateId) {
+        let case = start(connection, &NodeId::new("Case-Task")).unwrap();
+        let state_machine = select(connection, &case).unwrap();
+        let review = match state_machine.variable(&NodeId::new("Case-Task"), "review").unwrap() {
+            Some(VariableValue::String(review)) => StateId::from(review.as_str()),
+            _ => panic!("Expected the id of the review step!"),
+        };
+        assert_eq!(&vec![(NodeId::new("Case-Task/Open"), review.clone())], state_machine.children());
+        (case, review)
+    }
+
+    #[test]
+    fn test_invoke_send_and_done() {
+        let connection = create_db_connection();
+        let (case, review) = start_case(&connection);
+        let review_step = select(&connection, &review).unwrap();
+        assert_eq!(Some(&case), review_step.parent());
+        assert_eq!(&NodeId::new("Review-Step/Pending"), review_step.current_state().node_id().unwrap());
+
+        // The case forwards the approval to the review step, which sends done.invoke.Open, when its
+        // final node is reached.
+        let reaction = send(&connection, &case, &"sme:///approve-all".parse().unwrap()).unwrap();
+        assert!(reaction.enabled_events().is_empty());
+        assert!(reaction.chain().contains(&LogEntryType::Event("sme:///done.invoke.Open".parse().unwrap())));
+        let review_step = select(&connection, &review).unwrap();
+        assert_eq!(&NodeId::new("Review-Step/Approved"), review_step.current_state().node_id().unwrap());
+        let state_machine = select(&connection, &case).unwrap();
+        assert_eq!(&NodeId::new("Case-Task/Closed"), state_machine.current_state().node_id().unwrap());
+        assert!(state_machine.children().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_on_exit() {
+        let connection = create_db_connection();
+        let (case, review) = start_case(&connection);
+        send(&connection, &case, &"sme:///abort".parse().unwrap()).unwrap();
+        let state_machine = select(&connection, &case).unwrap();
+        assert_eq!(&NodeId::new("Case-Task/Aborted"), state_machine.current_state().node_id().unwrap());
+        assert!(select(&connection, &review).is_none());
+        match send(&connection, &review, &"sme:///approve".parse().unwrap()) {
+            Err(Error::StateChart(StateChartError::UnknownStateMachine(_))) => {}
+            _ => panic!("Expected an unknown state machine!"),
+        }
+    }
+
+    #[test]
+    fn test_done_of_two_children() {
+        let connection = create_db_connection();
+        let worker = |name: &str, last: NodeKind| {
+            Node::builder(name)
+                .start_node("Pending")
+                .state(Node::builder("Pending").transition(Transition::on("finish").to("Last")))
+                .state(Node::builder("Last").kind(last))
+                .build()
+                .unwrap()
+        };
+        let region = |name: &str, child: &str| {
+            Node::builder(name)
+                .on_entry(ActionCall::builder("invoke").parameter("state-chart", child))
+                .start_node("Waiting")
+                .state(Node::builder("Waiting").transition(Transition::on(&format!("done.invoke.{name}")).to("Finished")))
+                .state(Node::builder("Finished").kind(NodeKind::Final))
+        };
+        let pair = Node::builder("Pair")
+            .start_node("Both")
+            .state(Node::builder("Both").kind(NodeKind::Parallel).state(region("Left", "scn:///Worker")).state(region("Right", "scn:///Stuck")))
+            .build()
+            .unwrap();
+        for mut state_chart in [worker("Worker", NodeKind::Final), worker("Stuck", NodeKind::State), pair] {
+            state_chart.insert(&connection).unwrap();
+        }
+        let pair = start(&connection, &NodeId::new("Pair")).unwrap();
+        let children = select(&connection, &pair).unwrap().children().clone();
+        let child = |node: &str| children.iter().find(|(node_id, _)| node_id == &NodeId::new(node)).unwrap().1.clone();
+        let finished = |region: &str| {
+            let configuration = select(&connection, &pair).unwrap().configuration().unwrap();
+            configuration.contains(&NodeId::new(&format!("Pair/Both/{region}/Finished")))
+        };
+
+        // The stuck child has no transition left, but it didn't reach a final node.
+        send(&connection, &child("Pair/Both/Right"), &"sme:///finish".parse().unwrap()).unwrap();
+        assert!(!finished("Right"));
+        send(&connection, &child("Pair/Both/Left"), &"sme:///finish".parse().unwrap()).unwrap();
+        assert!(finished("Left"));
+        assert!(!finished("Right"));
+    }
+}
@@ -0,0 +1,365 @@
+use crate::error::StateChartError;
+use crate::ids::NodeId;
+use crate::node::{Node, NodeKind};
+use crate::state_charts::{Guard, PredicateCall, Transition, VariableValue};
+
+/// The text formats, a state chart can be rendered to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Dot,
+    Mermaid,
+    PlantUml,
+}
+impl Format {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Dot => "text/vnd.graphviz",
+            Self::Mermaid | Self::PlantUml => "text/plain",
+        }
+    }
+
+    /// The usual extension of a file in the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Dot => "dot",
+            Self::Mermaid => "mmd",
+            Self::PlantUml => "puml",
+        }
+    }
+}
+
+impl TryFrom<&str> for Format {
+    type Error = StateChartError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            "plantuml" => Ok(Self::PlantUml),
+            _ => Err(StateChartError::UnknownDiagramFormat(value.into())),
+        }
+    }
+}
+
+/// Renders the state chart as diagram. Nodes with sub-nodes become clusters, the regions of a
+/// parallel node are separated. Each transition is labelled with its event or predicate and the
+/// name of its action. The start node of every cluster is marked by an initial pseudo state.
+/// The active nodes are highlighted, which shows the configuration of a running state machine.
+pub fn render(state_chart: &Node, format: Format, active: &[NodeId]) -> Result<String, StateChartError> {
+    render_annotated(state_chart, format, active, &[])
+}
+
+/// Renders the state chart with the highlighted nodes and marks the missed transitions, which are
+/// addressed by their source and their index in the out transitions of the source. This shows
+/// the coverage of a state chart.
+pub fn render_annotated(
+    state_chart: &Node,
+    format: Format,
+    active: &[NodeId],
+    missed: &[(NodeId, usize)],
+) -> Result<String, StateChartError> {
+    let annotations = Annotations { active, missed };
+    let mut lines = Vec::new();
+    match format {
+        Format::Dot => {
+            lines.push(format!("digraph \"{}\" {{", dot_escape(state_chart.id().name()?)));
+            lines.push("    compound=true;".into());
+            lines.push("    node [shape=box, style=rounded];".into());
+            let mut edges = Vec::new();
+            dot_node(state_chart, state_chart, 1, &annotations, &mut lines, &mut edges)?;
+            lines.extend(edges);
+            lines.push("}".into());
+        }
+        Format::Mermaid => {
+            lines.push("stateDiagram-v2".into());
+            let mut edges = Vec::new();
+            uml_node(state_chart, Format::Mermaid, 1, &annotations, &mut lines, &mut edges)?;
+            lines.extend(edges);
+            let highlighted = active.iter().map(alias).collect::<Result<Vec<String>, StateChartError>>()?;
+            if !highlighted.is_empty() {
+                lines.push("    classDef active fill:#cce5ff,stroke:#004085,stroke-width:2px".into());
+                lines.push(format!("    class {} active", highlighted.join(",")));
+            }
+        }
+        Format::PlantUml => {
+            lines.push("@startuml".into());
+            let mut edges = Vec::new();
+            uml_node(state_chart, Format::PlantUml, 0, &annotations, &mut lines, &mut edges)?;
+            lines.extend(edges);
+            lines.push("@enduml".into());
+        }
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
+/// The highlighted nodes and the missed transitions of the diagram.
+struct Annotations<'a> {
+    active: &'a [NodeId],
+    missed: &'a [(NodeId, usize)],
+}
+impl Annotations<'_> {
+    fn is_missed(&self, node: &Node, index: usize) -> bool {
+        self.missed.iter().any(|(source, missed)| source == node.id() && *missed == index)
+    }
+}
+
+/// The suffix of the label of a missed transition in Mermaid, which can't style single edges.
+const MISSED: &str = " (missed)";
+
+/// Provides an identifier of the node, which is valid in Mermaid and PlantUML.
+fn alias(node_id: &NodeId) -> Result<String, StateChartError> {
+    Ok(node_id.path()?.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect())
+}
+
+/// Provides the label of the transition: the event with its condition or the predicate, followed
+/// by the action.
+pub(crate) fn label(transition: &Transition) -> String {
+    let guard = match transition.guard() {
+        Guard::Event(event) => match transition.condition() {
+            Some(condition) => format!("{} {}", event.name(), predicate_label(condition)),
+            None => event.name().to_string(),
+        },
+        Guard::Predicate(predicate_call) => predicate_label(predicate_call),
+        Guard::Else => "[else]".into(),
+        Guard::Completion => String::new(),
+    };
+    match transition.action() {
+        Some(action) => format!("{guard} / {}", action.name().as_str()).trim_start().to_string(),
+        None => guard,
+    }
+}
+
+fn predicate_label(predicate_call: &PredicateCall) -> String {
+    let arguments: Vec<String> = predicate_call.parameters().iter().map(|parameter| value_label(parameter.value())).collect();
+    format!("[{}({})]", predicate_call.name().as_str(), arguments.join(", "))
+}
+
+/// Shows references by the name of the variable and ids by their name.
+fn value_label(value: &VariableValue) -> String {
+    match value {
+        VariableValue::Reference(reference) => reference.name().to_string(),
+        VariableValue::String(s) if s.starts_with("sme:///") => s.rsplit_once('/').map_or(s.as_str(), |(_, name)| name).to_string(),
+        VariableValue::String(s) => format!("'{s}'"),
+        VariableValue::Boolean(b) => b.to_string(),
+        value => value.to_string(),
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Writes the node as DOT. A node with sub-nodes is a cluster with an invisible anchor, which is
+/// the end of the transitions from and to the cluster. The edges are collected and written after
+/// all nodes.
+fn dot_node(
+    state_chart: &Node,
+    node: &Node,
+    depth: usize,
+    annotations: &Annotations,
+    lines: &mut Vec<String>,
+    edges: &mut Vec<String>,
+) -> Result<(), StateChartError> {
+    let indent = "    ".repeat(depth);
+    let id = dot_escape(node.id().path()?);
+    let is_active = annotations.active.contains(node.id());
+    if node.nodes().is_empty() {
+        let shape = match node.kind() {
+            NodeKind::Final => format!("label=\"{}\", peripheries=2", dot_escape(node.id().name()?)),
+            NodeKind::Choice => format!("label=\"{}\", shape=diamond", dot_escape(node.id().name()?)),
+            NodeKind::Junction => "label=\"\", shape=circle, style=filled, fillcolor=black, width=0.15".into(),
+            NodeKind::History => "label=\"H\", shape=circle".into(),
+            NodeKind::DeepHistory => "label=\"H*\", shape=circle".into(),
+            NodeKind::State | NodeKind::Parallel => format!("label=\"{}\"", dot_escape(node.id().name()?)),
+        };
+        let highlight = if is_active { ", style=\"rounded,filled\", fillcolor=lightblue, penwidth=2" } else { "" };
+        lines.push(format!("{indent}\"{id}\" [{shape}{highlight}];"));
+    } else {
+        lines.push(format!("{indent}subgraph \"cluster_{id}\" {{"));
+        lines.push(format!("{indent}    label=\"{}\";", dot_escape(node.id().name()?)));
+        let style = if node.kind() == NodeKind::Parallel { "dashed" } else { "rounded" };
+        let highlight = if is_active { " color=blue; penwidth=2;" } else { "" };
+        lines.push(format!("{indent}    style={style};{highlight}"));
+        lines.push(format!("{indent}    \"{id}\" [shape=point, style=invis, width=0];"));
+        if let Some(start_node) = node.start_node() {
+            lines.push(format!("{indent}    \"{id}/[*]\" [shape=point, width=0.15];"));
+            edges.push(dot_edge(state_chart, &format!("{id}/[*]"), None, start_node, "")?);
+        }
+        for sub_node in node.nodes() {
+            dot_node(state_chart, sub_node, depth + 1, annotations, lines, edges)?;
+        }
+        lines.push(format!("{indent}}}"));
+    }
+    for (index, transition) in node.out_transitions().iter().enumerate() {
+        let mut edge = match transition.to() {
+            Some(to) => dot_edge(state_chart, &id, Some(node), to, &label(transition))?,
+            None => format!("    \"{id}\" -> \"{id}\" [label=\"{}\", style=dashed];", dot_escape(&label(transition))),
+        };
+        if annotations.is_missed(node, index) {
+            edge = edge.replacen("[label=", "[color=red, fontcolor=red, penwidth=2, label=", 1);
+        }
+        edges.push(edge);
+    }
+    Ok(())
+}
+
+/// Writes an edge to the target. Clusters are addressed by their anchor and the edge is clipped
+/// at the border of the cluster.
+fn dot_edge(state_chart: &Node, from: &str, source: Option<&Node>, to: &NodeId, label: &str) -> Result<String, StateChartError> {
+    let is_cluster = |node: &Node| !node.nodes().is_empty();
+    let target = dot_escape(to.path()?);
+    let mut attributes = vec![format!("label=\"{}\"", dot_escape(label))];
+    // Graphviz can't clip a loop of a cluster at its border.
+    let is_loop = source.is_some_and(|source| source.id() == to);
+    if let Some(source) = source.filter(|source| is_cluster(source) && !is_loop) {
+        attributes.push(format!("ltail=\"cluster_{}\"", dot_escape(source.id().path()?)));
+    }
+    if state_chart.find(to).is_some_and(is_cluster) && !is_loop {
+        attributes.push(format!("lhead=\"cluster_{target}\""));
+    }
+    Ok(format!("    \"{from}\" -> \"{target}\" [{}];", attributes.join(", ")))
+}
+
+/// Writes the node as Mermaid or PlantUML state. Both use composite states for the nodes with
+/// sub-nodes and separate the regions of a parallel node. The transitions are collected and
+/// written after all states.
+fn uml_node(
+    node: &Node,
+    format: Format,
+    depth: usize,
+    annotations: &Annotations,
+    lines: &mut Vec<String>,
+    edges: &mut Vec<String>,
+) -> Result<(), StateChartError> {
+    let indent = "    ".repeat(depth);
+    let id = alias(node.id())?;
+    let is_active = annotations.active.contains(node.id());
+    let edge_indent = if format == Format::Mermaid { "    " } else { "" };
+    let declaration = match (node.kind(), format) {
+        (NodeKind::Choice | NodeKind::Junction, _) => format!("state {id} <<choice>>"),
+        (NodeKind::History, Format::PlantUml) => format!("state {id} <<history>>"),
+        (NodeKind::DeepHistory, Format::PlantUml) => format!("state {id} <<history*>>"),
+        (NodeKind::History, _) => format!("state \"H\" as {id}"),
+        (NodeKind::DeepHistory, _) => format!("state \"H*\" as {id}"),
+        (NodeKind::Final, Format::PlantUml) => format!("state \"{}\" as {id} <<end>>", node.id().name()?),
+        _ => format!("state \"{}\" as {id}", node.id().name()?),
+    };
+    let highlight = match (format, is_active, node.nodes().is_empty()) {
+        (Format::PlantUml, true, true) => " #LightBlue",
+        (Format::PlantUml, true, false) => " ##[bold]Blue",
+        _ => "",
+    };
+    if node.nodes().is_empty() {
+        lines.push(format!("{indent}{declaration}{highlight}"));
+        if node.kind() == NodeKind::Final && format == Format::Mermaid {
+            lines.push(format!("{indent}{id} --> [*]"));
+        }
+    } else {
+        lines.push(format!("{indent}{declaration}{highlight} {{"));
+        if let Some(start_node) = node.start_node() {
+            lines.push(format!("{indent}    [*] --> {}", alias(start_node)?));
+        }
+        let separator = if format == Format::Mermaid { "--" } else { "||" };
+        for (index, sub_node) in node.nodes().iter().enumerate() {
+            if node.kind() == NodeKind::Parallel && index > 0 {
+                lines.push(format!("{indent}    {separator}"));
+            }
+            uml_node(sub_node, format, depth + 1, annotations, lines, edges)?;
+        }
+        lines.push(format!("{indent}}}"));
+    }
+    for (index, transition) in node.out_transitions().iter().enumerate() {
+        let target = match transition.to() {
+            Some(to) => alias(to)?,
+            None => id.clone(),
+        };
+        let mut label = label(transition).replace(':', " ");
+        let mut arrow = "-->";
+        if annotations.is_missed(node, index) {
+            match format {
+                Format::PlantUml => arrow = "-[#red,bold]->",
+                _ => label = format!("{label}{MISSED}").trim_start().to_string(),
+            }
+        }
+        if label.is_empty() {
+            edges.push(format!("{edge_indent}{id} {arrow} {target}"));
+        } else {
+            edges.push(format!("{edge_indent}{id} {arrow} {target} : {label}"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_machine::StateMachine;
+
+    fn media_player() -> Node {
+        Node::read("tests/media-player.scxml").unwrap()
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = render(&media_player(), Format::Dot, &[]).unwrap();
+        assert!(dot.starts_with("digraph \"Media-Player\" {"));
+        assert!(dot.contains("subgraph \"cluster_Media-Player/On\" {"));
+        assert!(dot.contains("style=dashed;"));
+        assert!(dot.contains("\"Media-Player/[*]\" -> \"Media-Player/Off\" [label=\"\"];"));
+        assert!(dot.contains(
+            "\"Media-Player/Off\" -> \"Media-Player/On\" [label=\"power\", lhead=\"cluster_Media-Player/On\"];"
+        ));
+        assert!(dot.contains("\"Media-Player/On/Playback/Last-Playback\" [label=\"H\", shape=circle];"));
+        assert!(dot.contains("\"Media-Player/On/Playback/Finished\" [label=\"Finished\", peripheries=2];"));
+
+        let dot = render(&Node::read("tests/order-task.json").unwrap(), Format::Dot, &[]).unwrap();
+        assert!(dot.contains("[label=\"[greater(amount, 1000)]\"]"));
+        assert!(dot.contains("[label=\"[is-true(submitted)] / increment\"]"));
+        assert!(dot.contains("[label=\"[else]\"]"));
+    }
+
+    #[test]
+    fn test_mermaid_and_plantuml() {
+        let mermaid = render(&media_player(), Format::Mermaid, &[]).unwrap();
+        assert!(mermaid.starts_with("stateDiagram-v2\n"));
+        assert!(mermaid.contains("        [*] --> Media_Player_Off\n"));
+        assert!(mermaid.contains("            --\n"));
+        assert!(mermaid.contains("    Media_Player_Off --> Media_Player_On : power\n"));
+        assert!(mermaid.contains("Media_Player_On_Playback_Finished --> [*]"));
+        assert!(!mermaid.contains("classDef"));
+
+        let plantuml = render(&media_player(), Format::PlantUml, &[]).unwrap();
+        assert!(plantuml.starts_with("@startuml\n"));
+        assert!(plantuml.ends_with("@enduml\n"));
+        assert!(plantuml.contains("state Media_Player_On_Playback_Last_Playback <<history>>"));
+        assert!(plantuml.contains("        ||\n"));
+        assert!(plantuml.contains("Media_Player_Off --> Media_Player_On_Playback_Last_Playback : resume\n"));
+    }
+
+    #[test]
+    fn test_highlight_configuration() {
+        let mut state_machine = StateMachine::new(media_player()).unwrap();
+        state_machine.send_event(&"sme:///power".parse().unwrap()).unwrap();
+        let configuration = state_machine.configuration().unwrap();
+        let mermaid = render(state_machine.state_chart(), Format::Mermaid, &configuration).unwrap();
+        assert!(mermaid.contains(
+            "class Media_Player,Media_Player_On,Media_Player_On_Playback,Media_Player_On_Playback_Stopped,\
+             Media_Player_On_Volume,Media_Player_On_Volume_Normal active"
+        ));
+        let dot = render(state_machine.state_chart(), Format::Dot, &configuration).unwrap();
+        assert!(dot.contains("\"Media-Player/On/Volume/Normal\" [label=\"Normal\", style=\"rounded,filled\""));
+        assert!(!dot.contains("\"Media-Player/Off\" [label=\"Off\", style"));
+        let plantuml = render(state_machine.state_chart(), Format::PlantUml, &configuration).unwrap();
+        assert!(plantuml.contains("state \"Stopped\" as Media_Player_On_Playback_Stopped #LightBlue"));
+        assert!(plantuml.contains("state \"On\" as Media_Player_On ##[bold]Blue {"));
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Format::PlantUml, Format::try_from("plantuml").unwrap());
+        match Format::try_from("svg") {
+            Err(StateChartError::UnknownDiagramFormat(format)) => assert_eq!("svg", format),
+            _ => panic!("Expected an unknown format!"),
+        }
+    }
+}
@@ -17,11 +17,79 @@ pub enum StateChartError {
     InvalidStateId(StateId),
     #[error("State chart is no root.")]
     NoRoot,
+    #[error("VariableId '{0}' isn't valid.")]
+    InvalidVariableId(String),
+    #[error("Variable '{0}' isn't declared.")]
+    UnknownVariable(String),
+    #[error("Variable '{0}' isn't in an active scope.")]
+    VariableNotInScope(String),
+    #[error("Value of variable '{0}' doesn't match the declared type '{1}'.")]
+    TypeMismatch(String, String),
+    #[error("Predicate '{0}' isn't known.")]
+    UnknownPredicate(String),
+    #[error("Predicate '{0}' called with unexpected arguments.")]
+    InvalidPredicateArguments(String),
+    #[error("Node '{0}' isn't part of the state chart.")]
+    UnknownNode(NodeId),
+    #[error("State machine '{0}' isn't known.")]
+    UnknownStateMachine(StateId),
+    #[error("State chart '{0}' isn't known.")]
+    UnknownStateChart(NodeId),
+    #[error("EventId '{0}' isn't valid.")]
+    InvalidEventId(String),
+    #[error("ActionId '{0}' isn't valid.")]
+    InvalidActionId(String),
+    #[error("PredicateId '{0}' isn't valid.")]
+    InvalidPredicateId(String),
+    #[error("Reference '{0}' can't be resolved.")]
+    UnresolvedReference(String),
+    #[error("Action '{0}' isn't known.")]
+    UnknownAction(String),
+    #[error("Action '{0}' called with unexpected arguments.")]
+    InvalidActionArguments(String),
+    #[error("Choice '{0}' needs an else branch or guards, which cover all cases.")]
+    IncompleteChoice(NodeId),
+    #[error("Pseudo node '{0}' must only have predicate or else transitions, no sub-nodes and can't be a start node.")]
+    InvalidPseudoNode(NodeId),
+    #[error("Node '{0}' isn't a choice or junction and can't have an else branch.")]
+    MisplacedElse(NodeId),
+    #[error("No branch of '{0}' is enabled.")]
+    NoEnabledBranch(NodeId),
+    #[error("Eventless transitions starting in '{0}' don't reach a stable state.")]
+    CompletionLoop(StateId),
+    #[error("Internal transition of '{0}' must target the node itself or one of its descendants.")]
+    InvalidInternalTransition(NodeId),
+    #[error("Messages between state machines starting with '{0}' don't come to an end.")]
+    MessageLoop(StateId),
+    #[error("Parallel node '{0}' must have sub-nodes, which are states, parallel or history nodes, and no start node.")]
+    InvalidParallelNode(NodeId),
+    #[error("Final node '{0}' can't have out transitions or sub-nodes.")]
+    InvalidFinalNode(NodeId),
+    #[error("SCXML construct '{0}' can't be mapped.")]
+    UnsupportedScxml(String),
+    #[error("SCXML document isn't valid: {0}")]
+    InvalidScxml(String),
+    #[error("Diagram format '{0}' isn't known.")]
+    UnknownDiagramFormat(String),
+    #[error("State chart construct '{0}' can't be generated as code.")]
+    UnsupportedCodegen(String),
+    #[error("Transition {1} of '{0}' and transition {3} of '{2}' can be enabled together and need different priorities.")]
+    ConflictingTransitions(NodeId, usize, NodeId, usize),
+    #[error("Property isn't valid: {0}")]
+    InvalidProperty(String),
+    #[error("Value of variable '{0}' overflows.")]
+    Overflow(String),
+    #[error("Internal events raised starting in '{0}' don't come to an end.")]
+    RaiseLoop(StateId),
+    #[error("State chart '{0}' already exists.")]
+    StateChartExists(NodeId),
+    #[error("Transition of '{0}' can only have a condition, if it is triggered by an event.")]
+    MisplacedCondition(NodeId),
 }
 impl StateChartError {
     /// Assigns an error number to every known error. This is mandatory to support international
     /// service and support teams.
-    fn error_id(&self) -> i64 {
+    pub fn error_id(&self) -> i64 {
         match self {
             Self::MandatoryAttributeMissing(_) => 0,
             Self::UnexpectedType => 1,
@@ -29,6 +97,40 @@ impl StateChartError {
             Self::InvalidNodeId(_) => 3,
             Self::InvalidStateId(_) => 4,
             Self::NoRoot => 5,
+            Self::InvalidVariableId(_) => 6,
+            Self::UnknownVariable(_) => 7,
+            Self::VariableNotInScope(_) => 8,
+            Self::TypeMismatch(_, _) => 9,
+            Self::UnknownPredicate(_) => 10,
+            Self::InvalidPredicateArguments(_) => 11,
+            Self::UnknownNode(_) => 12,
+            Self::UnknownStateMachine(_) => 13,
+            Self::UnknownStateChart(_) => 14,
+            Self::InvalidEventId(_) => 15,
+            Self::InvalidActionId(_) => 16,
+            Self::InvalidPredicateId(_) => 17,
+            Self::UnresolvedReference(_) => 18,
+            Self::UnknownAction(_) => 19,
+            Self::InvalidActionArguments(_) => 20,
+            Self::IncompleteChoice(_) => 21,
+            Self::InvalidPseudoNode(_) => 22,
+            Self::MisplacedElse(_) => 23,
+            Self::NoEnabledBranch(_) => 24,
+            Self::CompletionLoop(_) => 25,
+            Self::InvalidInternalTransition(_) => 26,
+            Self::MessageLoop(_) => 27,
+            Self::InvalidParallelNode(_) => 28,
+            Self::InvalidFinalNode(_) => 29,
+            Self::UnsupportedScxml(_) => 30,
+            Self::InvalidScxml(_) => 31,
+            Self::UnknownDiagramFormat(_) => 32,
+            Self::UnsupportedCodegen(_) => 33,
+            Self::ConflictingTransitions(..) => 34,
+            Self::InvalidProperty(_) => 35,
+            Self::Overflow(_) => 36,
+            Self::RaiseLoop(_) => 37,
+            Self::StateChartExists(_) => 38,
+            Self::MisplacedCondition(_) => 39,
         }
     }
 }
@@ -42,3 +144,93 @@ impl From<StateChartError> for Value {
         Value::Object(content)
     }
 }
+
+/// The error of the crate. Besides the errors of the state charts, the state charts and state
+/// machines can't be stored or read, if the database fails. The errors of the database are
+/// numbered from 100 on.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    StateChart(#[from] StateChartError),
+    #[error("Database operation failed: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("Database connection isn't available: {0}")]
+    Connection(#[from] r2d2::Error),
+    #[error("Value type '{0}' isn't known.")]
+    UnknownValueType(String),
+    #[error("{0} isn't stored in the database.")]
+    NotStored(&'static str),
+    #[error("JSON can't be read or written: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("File can't be read: {0}")]
+    Io(#[from] std::io::Error),
+}
+impl Error {
+    /// The errors of the state charts keep their numbers.
+    pub fn error_id(&self) -> i64 {
+        match self {
+            Self::StateChart(err) => err.error_id(),
+            Self::Database(_) => 100,
+            Self::Connection(_) => 101,
+            Self::UnknownValueType(_) => 102,
+            Self::NotStored(_) => 103,
+            Self::Serialization(_) => 104,
+            Self::Io(_) => 105,
+        }
+    }
+
+    /// Only the errors of the state charts are caused by the request, all others are failures
+    /// of the server.
+    pub fn is_internal(&self) -> bool {
+        !matches!(self, Self::StateChart(_))
+    }
+
+    /// Distinguishes the errors of the request, which address something unknown.
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            Self::StateChart(
+                StateChartError::UnknownNode(_)
+                    | StateChartError::UnknownStateChart(_)
+                    | StateChartError::UnknownStateMachine(_)
+                    | StateChartError::UnknownVariable(_)
+            )
+        )
+    }
+
+    /// Distinguishes the errors of the request, which conflict with the stored data.
+    pub fn is_conflict(&self) -> bool {
+        matches!(self, Self::StateChart(StateChartError::StateChartExists(_)))
+    }
+}
+
+/// Converts an error into a return value.
+impl From<Error> for Value {
+    fn from(error: Error) -> Self {
+        let mut content: BTreeMap<String, Value> = BTreeMap::new();
+        content.insert("id".into(), Value::Integer(error.error_id()));
+        content.insert("message".into(), Value::String(format!("{}", error)));
+        Value::Object(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kinds() {
+        let unknown: Error = StateChartError::UnknownStateChart(NodeId::new("Task")).into();
+        assert_eq!(14, unknown.error_id());
+        assert!(unknown.is_not_found() && !unknown.is_internal());
+        let invalid: Error = StateChartError::NoRoot.into();
+        assert!(!invalid.is_not_found() && !invalid.is_internal());
+        let database: Error = rusqlite::Error::QueryReturnedNoRows.into();
+        assert_eq!(100, database.error_id());
+        assert!(database.is_internal());
+        match Value::from(database) {
+            Value::Object(content) => assert!(matches!(content.get("id"), Some(Value::Integer(100)))),
+            _ => panic!("Expected an object!"),
+        }
+    }
+}
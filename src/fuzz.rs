@@ -0,0 +1,328 @@
+//! Tests a state chart by random walks. Every run starts a state machine and sends random inputs
+//! for a number of steps: the events of the state chart, unknown events, the values of the
+//! domains of the variables, values of the wrong type and unknown variables. Invalid inputs must
+//! be rejected with an error. After every step the invariants are checked:
+//!
+//! - The state machine doesn't panic.
+//! - The root is active, the parent of every active node is active, a state with sub-nodes has
+//!   exactly one active sub-node, a parallel node has all its regions active and a node is active
+//!   only once.
+//! - The log only grows. The source of every logged transition was active before the step or was
+//!   entered during the step, its target was entered or stays active. If the active states
+//!   change, a transition is logged.
+//!
+//! The inputs of a failed run are shrunk to a shorter sequence, which still violates an
+//! invariant. The runs are reproducible by their seed.
+use std::collections::BTreeSet;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::ids::{EventId, NodeId};
+use crate::model_checker::{self, Input};
+use crate::node::{Node, NodeKind};
+use crate::state_charts::VariableValue;
+use crate::state_machine::{Observation, StateMachine};
+use crate::state_machine_log::LogEntryType;
+
+/// The number of runs and steps and the seed of the first run. Every further run increments the
+/// seed.
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+    pub seed: u64,
+    pub runs: usize,
+    pub steps: usize,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { seed: 0, runs: 10, steps: 1000 }
+    }
+}
+
+/// The number of inputs sent by all runs and the number of inputs, which were rejected.
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub inputs: usize,
+    pub rejected: usize,
+}
+
+/// The violated invariant together with the seed of the run and the shrunk inputs, which lead to
+/// the violation.
+#[derive(Debug)]
+pub struct Failure {
+    pub seed: u64,
+    pub inputs: Vec<Input>,
+    pub message: String,
+}
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Run with seed {} failed: {}", self.seed, self.message)?;
+        for (index, input) in self.inputs.iter().enumerate() {
+            writeln!(f, "  {}. {input}", index + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the random walks on the state chart, which must be valid. The first failed run stops the
+/// test.
+pub fn fuzz(state_chart: &Node, settings: &Settings) -> Result<Summary, Failure> {
+    let valid = model_checker::inputs(state_chart);
+    let mut summary = Summary { inputs: 0, rejected: 0 };
+    for run in 0..settings.runs {
+        let seed = settings.seed.wrapping_add(run as u64);
+        let mut random = Random(seed);
+        let inputs: Vec<Input> = (0..settings.steps).map(|_| random_input(&mut random, &valid, state_chart)).collect();
+        match walk(state_chart, &inputs) {
+            Ok(rejected) => {
+                summary.inputs += inputs.len();
+                summary.rejected += rejected;
+            }
+            Err((step, _)) => {
+                let inputs = shrink(inputs[..step].to_vec(), |inputs| walk(state_chart, inputs).is_err());
+                let message = match walk(state_chart, &inputs) {
+                    Err((_, message)) => message,
+                    Ok(_) => unreachable!("The shrunk inputs still fail."),
+                };
+                return Err(Failure { seed, inputs, message });
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Sends the inputs to a new state machine and checks the invariants after every step. Provides
+/// the number of rejected inputs, or the number of inputs up to the violation and the violated
+/// invariant.
+pub fn walk(state_chart: &Node, inputs: &[Input]) -> Result<usize, (usize, String)> {
+    let started = panic::catch_unwind(AssertUnwindSafe(|| StateMachine::new(state_chart.clone())));
+    let mut state_machine = match started {
+        Ok(Ok(state_machine)) => state_machine,
+        Ok(Err(err)) => return Err((0, format!("The state machine doesn't start: {err}"))),
+        Err(_) => return Err((0, "The state machine panics on start.".into())),
+    };
+    entered(&mut state_machine);
+    let mut leaves = check_configuration(&state_machine).map_err(|message| (0, message))?;
+    let mut rejected = 0;
+    for (index, input) in inputs.iter().enumerate() {
+        let step = index + 1;
+        let logged = state_machine.log().entries().len();
+        match panic::catch_unwind(AssertUnwindSafe(|| model_checker::apply(&mut state_machine, input))) {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => rejected += 1,
+            Err(_) => return Err((step, format!("The state machine panics on '{input}'."))),
+        }
+        let entered = entered(&mut state_machine);
+        let after = check_configuration(&state_machine).map_err(|message| (step, format!("After '{input}': {message}")))?;
+        check_log(&state_machine, logged, &leaves, &after, &entered).map_err(|message| (step, format!("After '{input}': {message}")))?;
+        leaves = after;
+    }
+    Ok(rejected)
+}
+
+/// Removes chunks of inputs, as long as the remaining inputs still fail. The chunks get smaller,
+/// until single inputs are removed.
+pub fn shrink(mut inputs: Vec<Input>, fails: impl Fn(&[Input]) -> bool) -> Vec<Input> {
+    let mut size = inputs.len() / 2;
+    while size > 0 {
+        let mut start = 0;
+        while start < inputs.len() {
+            let end = (start + size).min(inputs.len());
+            let candidate: Vec<Input> = inputs[..start].iter().chain(&inputs[end..]).cloned().collect();
+            if fails(&candidate) {
+                inputs = candidate;
+            } else {
+                start = end;
+            }
+        }
+        size /= 2;
+    }
+    inputs
+}
+
+/// Takes the nodes, which were entered during the last step. The messages and actions are taken
+/// too, as they aren't checked.
+fn entered(state_machine: &mut StateMachine) -> BTreeSet<NodeId> {
+    state_machine.take_messages();
+    state_machine.take_executed_actions();
+    state_machine
+        .take_observations()
+        .into_iter()
+        .filter_map(|observation| match observation {
+            Observation::Entered(node_id) => Some(node_id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks the active nodes against the structure of the state chart and provides the active
+/// leaves.
+fn check_configuration(state_machine: &StateMachine) -> Result<BTreeSet<NodeId>, String> {
+    let state_chart = state_machine.state_chart();
+    let configuration = state_machine.configuration().map_err(|err| err.to_string())?;
+    let active: BTreeSet<&NodeId> = configuration.iter().collect();
+    if active.len() != configuration.len() {
+        return Err(format!("A node is active more than once: {configuration:?}"));
+    }
+    if !active.contains(state_chart.id()) {
+        return Err("The root isn't active.".into());
+    }
+    let mut leaves = BTreeSet::new();
+    for node_id in &configuration {
+        let path = state_chart.path_to(node_id).ok_or_else(|| format!("The active node '{node_id}' is unknown."))?;
+        let node = path[path.len() - 1];
+        if path.len() > 1 && !active.contains(path[path.len() - 2].id()) {
+            return Err(format!("The parent of the active node '{node_id}' isn't active."));
+        }
+        let sub_nodes: Vec<&Node> = node.nodes().iter().filter(|sub_node| !sub_node.kind().is_pseudo()).collect();
+        let active_sub_nodes = sub_nodes.iter().filter(|sub_node| active.contains(sub_node.id())).count();
+        match node.kind() {
+            NodeKind::Parallel if active_sub_nodes != sub_nodes.len() => {
+                return Err(format!("Only {active_sub_nodes} of {} regions of '{node_id}' are active.", sub_nodes.len()))
+            }
+            NodeKind::State if !sub_nodes.is_empty() && active_sub_nodes != 1 => {
+                return Err(format!("{active_sub_nodes} sub-nodes of '{node_id}' are active."))
+            }
+            _ if active_sub_nodes == 0 => {
+                leaves.insert(node_id.clone());
+            }
+            _ => {}
+        }
+    }
+    Ok(leaves)
+}
+
+/// Checks the entries, which were logged during the step, against the active leaves before and
+/// after the step and the nodes entered during the step.
+fn check_log(
+    state_machine: &StateMachine,
+    logged: usize,
+    before: &BTreeSet<NodeId>,
+    after: &BTreeSet<NodeId>,
+    entered: &BTreeSet<NodeId>,
+) -> Result<(), String> {
+    let entries = state_machine.log().entries();
+    if entries.len() < logged {
+        return Err(format!("The log shrank from {logged} to {} entries.", entries.len()));
+    }
+    let mut transitions = 0;
+    for entry in &entries[logged..] {
+        if let LogEntryType::Transaction(from, to) = entry.entry() {
+            transitions += 1;
+            if !before.contains(from) && !entered.contains(from) {
+                return Err(format!("The transition from '{from}' starts in a node, which wasn't active."));
+            }
+            if !entered.contains(to) && !before.contains(to) {
+                return Err(format!("The transition to '{to}' ends in a node, which wasn't entered."));
+            }
+        }
+    }
+    if transitions == 0 && before != after {
+        return Err(format!("The active states changed from {before:?} to {after:?} without a logged transition."));
+    }
+    Ok(())
+}
+
+/// Provides mostly valid inputs, but also unknown events, values of the wrong type and unknown
+/// variables.
+fn random_input(random: &mut Random, valid: &[Input], state_chart: &Node) -> Input {
+    let variables: Vec<&String> = valid
+        .iter()
+        .filter_map(|input| match input {
+            Input::Set(variable, _) => Some(variable),
+            Input::Send(_) => None,
+        })
+        .collect();
+    match random.below(10) {
+        0 => Input::Send(unknown_event()),
+        1 if !variables.is_empty() => {
+            let variable = variables[random.below(variables.len())].clone();
+            let value = match random.below(4) {
+                0 => VariableValue::String("fuzz".into()),
+                1 => VariableValue::Integer(random.next() as i64),
+                2 => VariableValue::Number(random.next() as f64 / 3.0),
+                _ => VariableValue::Boolean(random.below(2) == 0),
+            };
+            Input::Set(variable, value)
+        }
+        2 => Input::Set(format!("{}/fuzz", state_chart.id().path().unwrap_or_default()), VariableValue::Integer(1)),
+        _ if !valid.is_empty() => valid[random.below(valid.len())].clone(),
+        _ => Input::Send(unknown_event()),
+    }
+}
+
+fn unknown_event() -> EventId {
+    "sme:///fuzz".parse().expect("The event id is valid.")
+}
+
+/// A small generator of pseudo random numbers (SplitMix64), so that the runs are reproducible by
+/// their seed.
+struct Random(u64);
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures() {
+        let settings = Settings { seed: 42, runs: 3, steps: 500 };
+        for file_name in ["tests/approval.json", "tests/session-task.json", "tests/tally-task.json", "tests/relay-task.json"] {
+            let summary = fuzz(&Node::read(file_name).unwrap(), &settings).unwrap_or_else(|failure| panic!("{file_name}: {failure}"));
+            assert_eq!(1500, summary.inputs);
+            assert!(summary.rejected > 0);
+        }
+        let state_chart = crate::Node::read("tests/media-player.scxml").unwrap();
+        fuzz(&state_chart, &settings).unwrap();
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let state_chart = Node::read("tests/approval.json").unwrap();
+        let settings = Settings { seed: 7, runs: 2, steps: 200 };
+        assert_eq!(fuzz(&state_chart, &settings).unwrap(), fuzz(&state_chart, &settings).unwrap());
+    }
+
+    #[test]
+    fn test_shrink() {
+        let send = |event: &str| Input::Send(format!("sme:///{event}").parse().unwrap());
+        let inputs: Vec<Input> = ["start", "click", "pause", "click", "stop", "click", "stop"].iter().map(|event| send(event)).collect();
+        // Fails, if a click is followed by a stop later on.
+        let fails = |inputs: &[Input]| {
+            inputs
+                .iter()
+                .position(|input| input == &send("click"))
+                .is_some_and(|click| inputs[click..].contains(&send("stop")))
+        };
+        assert_eq!(vec![send("click"), send("stop")], shrink(inputs, fails));
+    }
+
+    #[test]
+    fn test_violation() {
+        // A state chart without a start node can't be started, which fails without any input.
+        let state_chart: Node = serde_json::from_value(serde_json::json!({
+            "id": "scn:///Task",
+            "nodes": [{ "id": "scn:///Task/Work" }]
+        }))
+        .unwrap();
+        match fuzz(&state_chart, &Settings::default()) {
+            Err(failure) => {
+                assert!(failure.inputs.is_empty());
+                assert_eq!(0, failure.seed);
+                assert!(failure.to_string().starts_with("Run with seed 0 failed: The state machine doesn't start"));
+            }
+            Ok(summary) => panic!("Expected a failure, but got {summary:?}!"),
+        }
+    }
+}
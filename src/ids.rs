@@ -1,46 +1,141 @@
 use crate::error::StateChartError;
 use rusqlite::ToSql;
-use rusqlite::types::ToSqlOutput;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
 use open_api_matcher::{ValidatedValue, Value};
 use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
 use uuid::Uuid;
 
+/// Compiles the pattern once and keeps the compiled regex for the lifetime of the process.
+macro_rules! regex {
+    ($pattern:expr) => {{
+        static REGEX: OnceLock<Regex> = OnceLock::new();
+        REGEX.get_or_init(|| Regex::new($pattern).unwrap())
+    }};
+}
+
 /// A system wide unique Id for a node.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct NodeId(String);
+/// The id is parsed once, when it is constructed. The bounds of the segments are kept, which are
+/// the offsets of the slash in front of each segment and the end of the id. Ids, which don't
+/// match the pattern, like a relative reference before it is resolved, have no path.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId {
+    id: String,
+    bounds: Option<Vec<usize>>,
+}
 impl NodeId {
-    const REGEX: &'static str = r"^scn:///(?P<path>\p{L}[\w\.\-]*(/\w[\w\.\-]*)*)$";
+    const REGEX: &'static str = r"^scn:///\p{L}[\w\.\-]*(/\w[\w\.\-]*)*$";
+    const PREFIX: &'static str = "scn:///";
 
     /// Creates an default node id.
     pub fn default() -> Self {
-        NodeId("scn:///de4ult".into())
+        NodeId::parse("scn:///de4ult".into())
     }
 
     /// Creates a new node id from a given node path.
     pub fn new(node_path: &str) -> Self {
-        Self(format!("scn:///{node_path}"))
+        NodeId::parse(format!("{}{node_path}", NodeId::PREFIX))
+    }
+
+    fn parse(id: String) -> Self {
+        let bounds = if regex!(NodeId::REGEX).is_match(&id) {
+            let slashes = id.match_indices('/').map(|(offset, _)| offset).skip(2);
+            Some(slashes.chain(std::iter::once(id.len())).collect())
+        } else {
+            None
+        };
+        NodeId { id, bounds }
+    }
+
+    fn bounds(&self) -> Result<&Vec<usize>, StateChartError> {
+        self.bounds.as_ref().ok_or_else(|| StateChartError::InvalidNodeId(self.clone()))
     }
 
     pub fn path(&self) -> Result<&str, StateChartError> {
-        let r = Regex::new(NodeId::REGEX).unwrap();
-        let NodeId(id) = self;
-        if let Some(captures) = r.captures(id.as_str()) {
-            if let Some(path) = captures.name("path") {
-                Ok(path.as_str().into())
-            } else {
-                Err(StateChartError::InvalidNodeId(self.clone()))
+        self.bounds()?;
+        Ok(&self.id[NodeId::PREFIX.len()..])
+    }
+
+    /// Provides the segments of the path, starting with the name of the state chart.
+    pub fn segments(&self) -> Result<impl DoubleEndedIterator<Item = &str> + ExactSizeIterator, StateChartError> {
+        Ok(self.bounds()?.windows(2).map(|bound| &self.id[bound[0] + 1..bound[1]]))
+    }
+
+    /// Provides the name of the node, which is the last segment of the path.
+    pub fn name(&self) -> Result<&str, StateChartError> {
+        let bounds = self.bounds()?;
+        Ok(&self.id[bounds[bounds.len() - 2] + 1..])
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    /// Resolves a reference relative to this node. Absolute ids are kept as they are. The path
+    /// segment "." addresses this node and ".." its parent, so "./Checking" and a bare "Checking"
+    /// are sub-nodes, while "../Done" is a sibling of this node.
+    pub fn resolve(&self, reference: &str) -> Result<NodeId, StateChartError> {
+        if reference.starts_with(NodeId::PREFIX) {
+            return Ok(reference.into());
+        }
+        let mut segments: Vec<&str> = self.segments()?.collect();
+        for segment in reference.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop();
+                    if segments.is_empty() {
+                        return Err(StateChartError::UnresolvedReference(reference.into()));
+                    }
+                }
+                name => segments.push(name),
             }
+        }
+        Ok(NodeId::new(&segments.join("/")))
+    }
+
+    /// Resolves the target of a transition. Other than with resolve(), a bare name addresses a
+    /// sibling of this node.
+    pub fn resolve_sibling(&self, reference: &str) -> Result<NodeId, StateChartError> {
+        if reference.starts_with(NodeId::PREFIX) || reference.starts_with("./") || reference.starts_with("../") {
+            self.resolve(reference)
         } else {
-            Err(StateChartError::InvalidNodeId(self.clone()))
+            self.resolve(&format!("../{reference}"))
         }
     }
 }
 
+/// A node id is written as it is, relative references included.
+impl Serialize for NodeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+impl<'de> Deserialize<'de> for NodeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(String::deserialize(deserializer)?.as_str().into())
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
 impl ToSql for NodeId {
-    fn to_sql(&self) -> Result<ToSqlOutput, rusqlite::Error>
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error>
     {
-        let NodeId(id) = &self;
-        id.to_sql()
+        self.id.to_sql()
+    }
+}
+
+impl FromSql for NodeId {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        String::column_result(value).map(NodeId::parse)
     }
 }
 
@@ -49,7 +144,7 @@ impl TryFrom<&ValidatedValue> for NodeId {
 
     fn try_from(value: &ValidatedValue) -> Result<Self, Self::Error> {
         if let ValidatedValue::String(id) = value {
-            Ok(NodeId(id.into()))
+            Ok(NodeId::parse(id.into()))
         } else {
             Err(StateChartError::UnexpectedType)
         }
@@ -59,36 +154,33 @@ impl TryFrom<&ValidatedValue> for NodeId {
 /// Convenience trait to transform Strings to NodeIds.
 impl From<String> for NodeId {
     fn from(value: String) -> Self {
-        NodeId(value)
+        NodeId::parse(value)
     }
 }
 
 /// Convenience trait to transform &str to NodeIds.
 impl From<&str> for NodeId {
     fn from(value: &str) -> Self {
-        NodeId(value.into())
+        NodeId::parse(value.into())
     }
 }
 
 /// Convenience trait to transform to String.
 impl From<NodeId> for String {
     fn from(value: NodeId) -> Self {
-        let NodeId(content) = value;
-        content
+        value.id
     }
 }
 
 impl<'a> From<&'a NodeId> for &'a String {
     fn from(value: &'a NodeId) -> &'a String {
-        let NodeId(content) = value;
-        content
+        &value.id
     }
 }
 
 impl From<&NodeId> for String {
     fn from(value: &NodeId) -> String {
-        let NodeId(content) = value;
-        content.clone()
+        value.id.clone()
     }
 }
 
@@ -99,48 +191,379 @@ impl From<&NodeId> for Value {
 }
 
 /// The state holds a reference to the root and the current state of a state chart.
+/// The id is parsed once, when it is constructed. The end of the machine id and the id of the
+/// addressed node are kept, to provide them without parsing the id again.
 /// @see StateMachines.yml
 /// pattern: '^sms:///\w[\w\.\-]*(/\w[\w\.\-]*)*$'
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct StateId(String);
+pub struct StateId {
+    id: String,
+    machine_end: Option<usize>,
+    node_id: Option<NodeId>,
+}
 impl StateId {
-    const REGEX: &'static str = r"^sms:///(?P<id>\w[\w\.-]*)(/\w[\w\.-]*)*$";
+    const REGEX: &'static str = r"^sms:///\w[\w\.\-]*(/\w[\w\.\-]*)*$";
+    const PREFIX: &'static str = "sms:///";
 
     pub fn default() -> Self {
-        StateId("sms:///de4ult".into())
+        StateId::parse("sms:///de4ult".into())
     }
 
     /// Creates a new state id from the provided node id. The assumption is, that the node id is
     /// the id of the state chart.
     pub fn new(node_id: &NodeId) -> Result<Self, StateChartError> {
-        let id = format!("sms:///{}/{}", Uuid::new_v4(), node_id.path()?);
-        Ok(StateId(id))
+        StateId::with_machine(&Uuid::new_v4().to_string(), node_id)
     }
 
     /// Creates a new state id from a given state id and a node id. The assumption is, that a
     /// substate of the state machine will be addressed.
     pub fn new_with_node(machine: &StateId, node_id: &NodeId) -> Result<Self, StateChartError> {
-        Ok(StateId(format!(
-            "sms:///{}/{}",
-            machine.id()?,
-            node_id.path()?
-        )))
-    }
-
-    fn id(&self) -> Result<String, StateChartError> {
-        let r = Regex::new(StateId::REGEX).unwrap();
-        let StateId(id) = self;
-        if let Some(captures) = r.captures(id.as_str()) {
-            if let Some(path) = captures.name("id") {
-                Ok(path.as_str().into())
-            } else {
-                Err(StateChartError::InvalidStateId(self.clone()))
+        StateId::with_machine(machine.id()?, node_id)
+    }
+
+    /// Composes the state id of the ids of the machine and the node, which are valid already.
+    fn with_machine(machine: &str, node_id: &NodeId) -> Result<Self, StateChartError> {
+        let id = format!("{}{machine}/{}", StateId::PREFIX, node_id.path()?);
+        let machine_end = Some(StateId::PREFIX.len() + machine.len());
+        Ok(StateId { id, machine_end, node_id: Some(node_id.clone()) })
+    }
+
+    fn parse(id: String) -> Self {
+        let machine_end = if regex!(StateId::REGEX).is_match(&id) {
+            let prefix = StateId::PREFIX.len();
+            Some(id[prefix..].find('/').map_or(id.len(), |end| prefix + end))
+        } else {
+            None
+        };
+        let node_id = match machine_end {
+            Some(end) if end < id.len() => Some(NodeId::new(&id[end + 1..])).filter(|node_id| node_id.bounds.is_some()),
+            _ => None,
+        };
+        StateId { id, machine_end, node_id }
+    }
+
+    /// Provides the unique id of the state machine, this state belongs to.
+    pub fn id(&self) -> Result<&str, StateChartError> {
+        match self.machine_end {
+            Some(end) => Ok(&self.id[StateId::PREFIX.len()..end]),
+            None => Err(StateChartError::InvalidStateId(self.clone())),
+        }
+    }
+
+    /// Provides the path of the node, this state is addressing. The path doesn't contain the id
+    /// of the state machine.
+    pub fn path(&self) -> Result<&str, StateChartError> {
+        match self.machine_end {
+            Some(end) if end < self.id.len() => Ok(&self.id[end + 1..]),
+            _ => Err(StateChartError::InvalidStateId(self.clone())),
+        }
+    }
+
+    /// Provides the id of the node, this state is addressing.
+    pub fn node_id(&self) -> Result<&NodeId, StateChartError> {
+        self.node_id.as_ref().ok_or_else(|| StateChartError::InvalidStateId(self.clone()))
+    }
+}
+
+impl fmt::Display for StateId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl ToSql for StateId {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error>
+    {
+        self.id.to_sql()
+    }
+}
+
+impl FromSql for StateId {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        String::column_result(value).map(StateId::parse)
+    }
+}
+
+/// Convenience trait to transform Strings to StateIds.
+impl From<String> for StateId {
+    fn from(value: String) -> Self {
+        StateId::parse(value)
+    }
+}
+
+/// Convenience trait to transform &str to StateIds.
+impl From<&str> for StateId {
+    fn from(value: &str) -> Self {
+        StateId::parse(value.into())
+    }
+}
+
+impl From<&StateId> for String {
+    fn from(value: &StateId) -> String {
+        value.id.clone()
+    }
+}
+
+impl From<&StateId> for Value {
+    fn from(state_id: &StateId) -> Self {
+        Value::String(state_id.id.clone())
+    }
+}
+
+/// Implements the conversions, shared by all ids, which are validated by a pattern of the
+/// specification. The id type must implement FromStr and as_str().
+macro_rules! validated_id {
+    ($id_type:ident) => {
+        impl fmt::Display for $id_type {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+
+        impl ToSql for $id_type {
+            fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error> {
+                self.as_str().to_sql()
+            }
+        }
+
+        impl Serialize for $id_type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        /// Deserialized ids are validated like parsed ones.
+        impl<'de> Deserialize<'de> for $id_type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+            }
+        }
+
+        /// Ids read from the database are validated again.
+        impl FromSql for $id_type {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                String::column_result(value)?
+                    .parse()
+                    .map_err(|_| FromSqlError::InvalidType)
+            }
+        }
+
+        impl TryFrom<&ValidatedValue> for $id_type {
+            type Error = StateChartError;
+
+            fn try_from(value: &ValidatedValue) -> Result<Self, Self::Error> {
+                if let ValidatedValue::String(id) = value {
+                    id.parse()
+                } else {
+                    Err(StateChartError::UnexpectedType)
+                }
             }
+        }
+
+        impl From<&$id_type> for ValidatedValue {
+            fn from(id: &$id_type) -> Self {
+                ValidatedValue::String(id.as_str().into())
+            }
+        }
+
+        impl From<&$id_type> for Value {
+            fn from(id: &$id_type) -> Self {
+                Value::String(id.as_str().into())
+            }
+        }
+    };
+}
+
+/// The id of a variable, constructed from the id of the state machine, the path of the declaring
+/// node and the name of the variable. The positions of the separators are kept, when the id is
+/// parsed, so that the accessors only borrow from the id.
+/// @see StateMachines.yml
+/// pattern: '^smv:///\w[\w\.\-]*(/\w[\w\.\-]*)*$'
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VariableId {
+    id: String,
+    machine_end: usize,
+    name_start: usize,
+}
+impl VariableId {
+    const REGEX: &'static str = r"^smv:///\w[\w\.\-]*/\p{L}[\w\.\-]*(/\w[\w\.\-]*)*/\w[\w\-]*$";
+    const PREFIX: &'static str = "smv:///";
+
+    /// Creates the id of a variable, declared by the given node of the state machine.
+    pub fn new(state_machine: &StateId, node_id: &NodeId, name: &str) -> Result<Self, StateChartError> {
+        format!("{}{}/{}/{name}", VariableId::PREFIX, state_machine.id()?, node_id.path()?).parse()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    /// The id of the state machine, the variable belongs to.
+    pub fn machine_id(&self) -> &str {
+        &self.id[VariableId::PREFIX.len()..self.machine_end]
+    }
+
+    /// The path of the node, which declares the variable.
+    pub fn node_path(&self) -> &str {
+        &self.id[self.machine_end + 1..self.name_start - 1]
+    }
+
+    /// The id of the node, which declares the variable.
+    pub fn node_id(&self) -> NodeId {
+        NodeId::new(self.node_path())
+    }
+
+    /// The name of the variable inside of the declaring node.
+    pub fn name(&self) -> &str {
+        &self.id[self.name_start..]
+    }
+}
+impl FromStr for VariableId {
+    type Err = StateChartError;
+
+    /// The pattern guarantees the prefix and at least two separators after the machine id.
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        let invalid = || StateChartError::InvalidVariableId(id.into());
+        if !regex!(VariableId::REGEX).is_match(id) {
+            return Err(invalid());
+        }
+        let prefix = VariableId::PREFIX.len();
+        let machine_end = id[prefix..].find('/').ok_or_else(invalid)? + prefix;
+        let name_start = id.rfind('/').ok_or_else(invalid)? + 1;
+        Ok(VariableId { id: id.into(), machine_end, name_start })
+    }
+}
+validated_id!(VariableId);
+
+/// The reference of a state chart to one of its variables, given by the id of the declaring node
+/// and the name of the variable. Other than the id of a variable, it belongs to no state machine.
+/// It is written as "smv:///{node-path}/{name}".
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VariableReference {
+    node_id: NodeId,
+    name: String,
+}
+impl VariableReference {
+    const REGEX: &'static str = r"^smv:///\p{L}[\w\.\-]*(/\w[\w\.\-]*)*/\w[\w\-]*$";
+    const PREFIX: &'static str = "smv:///";
+
+    /// Creates the reference to the variable, declared by the node.
+    pub fn new(node_id: &NodeId, name: &str) -> Result<Self, StateChartError> {
+        format!("{}{}/{name}", VariableReference::PREFIX, node_id.path()?).parse()
+    }
+
+    /// The id of the node, which declares the variable.
+    pub fn node_id(&self) -> &NodeId {
+        &self.node_id
+    }
+
+    /// The name of the variable inside of the declaring node.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+impl FromStr for VariableReference {
+    type Err = StateChartError;
+
+    /// The pattern guarantees the prefix and a separator between the node path and the name.
+    fn from_str(reference: &str) -> Result<Self, Self::Err> {
+        let invalid = || StateChartError::InvalidVariableId(reference.into());
+        if !regex!(VariableReference::REGEX).is_match(reference) {
+            return Err(invalid());
+        }
+        let (node_path, name) = reference[VariableReference::PREFIX.len()..].rsplit_once('/').ok_or_else(invalid)?;
+        Ok(VariableReference { node_id: NodeId::new(node_path), name: name.into() })
+    }
+}
+impl fmt::Display for VariableReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}/{}", VariableReference::PREFIX, &self.node_id.as_str()[NodeId::PREFIX.len()..], self.name)
+    }
+}
+
+/// The id of an event. It isn't declared, but derived from the transitions in the state chart.
+/// @see StateMachines.yml
+/// pattern: '^sme:///\p{L}[\w\.\-]*$'
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId(String);
+impl EventId {
+    const REGEX: &'static str = r"^sme:///\p{L}[\w\.\-]*$";
+    const PREFIX: &'static str = "sme:///";
+
+    pub fn as_str(&self) -> &str {
+        let EventId(id) = self;
+        id
+    }
+
+    /// The name of the event without the scheme.
+    pub fn name(&self) -> &str {
+        &self.as_str()[EventId::PREFIX.len()..]
+    }
+}
+impl FromStr for EventId {
+    type Err = StateChartError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        if regex!(EventId::REGEX).is_match(id) {
+            Ok(EventId(id.into()))
         } else {
-            Err(StateChartError::InvalidStateId(self.clone()))
+            Err(StateChartError::InvalidEventId(id.into()))
         }
     }
 }
+validated_id!(EventId);
+
+/// The name of an action, provided by this service.
+/// @see StateMachines.yml
+/// pattern: '^\p{L}[\w\-]*$'
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ActionId(String);
+impl ActionId {
+    const REGEX: &'static str = r"^\p{L}[\w\-]*$";
+
+    pub fn as_str(&self) -> &str {
+        let ActionId(id) = self;
+        id
+    }
+}
+impl FromStr for ActionId {
+    type Err = StateChartError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        if regex!(ActionId::REGEX).is_match(id) {
+            Ok(ActionId(id.into()))
+        } else {
+            Err(StateChartError::InvalidActionId(id.into()))
+        }
+    }
+}
+validated_id!(ActionId);
+
+/// The name of a predicate, provided by this service.
+/// @see StateMachines.yml
+/// pattern: '^\p{L}[\w\-]*$'
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PredicateId(String);
+impl PredicateId {
+    const REGEX: &'static str = r"^\p{L}[\w\-]*$";
+
+    pub fn as_str(&self) -> &str {
+        let PredicateId(id) = self;
+        id
+    }
+}
+impl FromStr for PredicateId {
+    type Err = StateChartError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        if regex!(PredicateId::REGEX).is_match(id) {
+            Ok(PredicateId(id.into()))
+        } else {
+            Err(StateChartError::InvalidPredicateId(id.into()))
+        }
+    }
+}
+validated_id!(PredicateId);
 
 #[cfg(test)]
 mod tests {
@@ -152,6 +575,27 @@ mod tests {
         assert_eq!("Simple-Task", node_id.path().unwrap());
         let node_id = NodeId::new("Complex-Task");
         assert_eq!("Complex-Task", node_id.path().unwrap());
+        let node_id = NodeId::new("Complex-Task/Checking");
+        let segments: Vec<&str> = node_id.segments().unwrap().collect();
+        assert_eq!(vec!["Complex-Task", "Checking"], segments);
+        assert_eq!(Some("Checking"), node_id.segments().unwrap().next_back());
+        assert_eq!("Checking", node_id.name().unwrap());
+        assert_eq!("Complex-Task", NodeId::new("Complex-Task").name().unwrap());
+        let relative: NodeId = "../Done".into();
+        relative.path().unwrap_err();
+        assert!(relative.segments().is_err());
+    }
+
+    #[test]
+    fn test_node_id_resolve() {
+        let node_id = NodeId::new("Approval/In-Approval");
+        assert_eq!(NodeId::new("Approval/In-Approval/Checking"), node_id.resolve("./Checking").unwrap());
+        assert_eq!(NodeId::new("Approval/In-Approval/Checking"), node_id.resolve("Checking").unwrap());
+        assert_eq!(NodeId::new("Approval/Done"), node_id.resolve("../Done").unwrap());
+        assert_eq!(NodeId::new("Approval/Done"), node_id.resolve_sibling("Done").unwrap());
+        assert_eq!(NodeId::new("Approval/In-Approval/Checking"), node_id.resolve_sibling("./Checking").unwrap());
+        assert_eq!(NodeId::new("Other/Done"), node_id.resolve_sibling("scn:///Other/Done").unwrap());
+        node_id.resolve("../../Done").unwrap_err();
     }
 
     #[test]
@@ -159,5 +603,77 @@ mod tests {
         let node_id = NodeId::new("Complex-Task");
         let state_id = StateId::new(&node_id).unwrap();
         let _uuid = state_id.id().unwrap();
+        assert_eq!("Complex-Task", state_id.path().unwrap());
+        let sub_state = StateId::new_with_node(&state_id, &NodeId::new("Complex-Task/Checking")).unwrap();
+        assert_eq!(&NodeId::new("Complex-Task/Checking"), sub_state.node_id().unwrap());
+        // The composed id equals the parsed one.
+        assert_eq!(StateId::from(sub_state.to_string()), sub_state);
+        let numeric: StateId = "sms:///4711/1-Task".into();
+        assert_eq!("1-Task", numeric.path().unwrap());
+        numeric.node_id().unwrap_err();
+        let machine: StateId = "sms:///4711".into();
+        assert_eq!("4711", machine.id().unwrap());
+        machine.path().unwrap_err();
+        let invalid: StateId = "sms:///".into();
+        invalid.id().unwrap_err();
+    }
+
+    #[test]
+    fn test_variable_id() {
+        let variable_id: VariableId = "smv:///4711/Simple-Task/In-Progress/since".parse().unwrap();
+        assert_eq!("4711", variable_id.machine_id());
+        assert_eq!("Simple-Task/In-Progress", variable_id.node_path());
+        assert_eq!(NodeId::new("Simple-Task/In-Progress"), variable_id.node_id());
+        assert_eq!("since", variable_id.name());
+        let state_id: StateId = "sms:///4711/Simple-Task".into();
+        let created = VariableId::new(&state_id, &NodeId::new("Simple-Task/In-Progress"), "since").unwrap();
+        assert_eq!(variable_id, created);
+
+        match "smv:///4711/since".parse::<VariableId>() {
+            Err(StateChartError::InvalidVariableId(_)) => {}
+            _ => panic!("A variable id needs a node path!"),
+        }
+        "sms:///4711/Simple-Task/since".parse::<VariableId>().unwrap_err();
+        "smv:///4711/1st-Task/since".parse::<VariableId>().unwrap_err();
+        let validated: VariableId = (&ValidatedValue::from(&variable_id)).try_into().unwrap();
+        assert_eq!(variable_id, validated);
+    }
+
+    #[test]
+    fn test_variable_reference() {
+        let reference: VariableReference = "smv:///Approval/In-Approval/approved".parse().unwrap();
+        assert_eq!(&NodeId::new("Approval/In-Approval"), reference.node_id());
+        assert_eq!("approved", reference.name());
+        assert_eq!("smv:///Approval/In-Approval/approved", reference.to_string());
+        assert_eq!(reference, VariableReference::new(&NodeId::new("Approval/In-Approval"), "approved").unwrap());
+        "smv:///approved".parse::<VariableReference>().unwrap_err();
+        "smv:approved".parse::<VariableReference>().unwrap_err();
+        "smv:///1st/approved".parse::<VariableReference>().unwrap_err();
+    }
+
+    #[test]
+    fn test_event_id() {
+        let event_id: EventId = "sme:///start".parse().unwrap();
+        assert_eq!("start", event_id.name());
+        assert_eq!("sme:///start", event_id.to_string());
+        match "sme:///1st".parse::<EventId>() {
+            Err(StateChartError::InvalidEventId(_)) => {}
+            _ => panic!("An event name must start with a letter!"),
+        }
+        "start".parse::<EventId>().unwrap_err();
+        EventId::try_from(&ValidatedValue::Integer(1)).unwrap_err();
+    }
+
+    #[test]
+    fn test_action_and_predicate_id() {
+        let action_id: ActionId = "send-mail".parse().unwrap();
+        assert_eq!("send-mail", action_id.as_str());
+        "send mail".parse::<ActionId>().unwrap_err();
+        let predicate_id: PredicateId = "is_true".parse().unwrap();
+        assert_eq!("is_true", predicate_id.as_str());
+        match "-equals".parse::<PredicateId>() {
+            Err(StateChartError::InvalidPredicateId(_)) => {}
+            _ => panic!("A predicate name must start with a letter!"),
+        }
     }
 }
@@ -0,0 +1,42 @@
+//! State charts and the state machines, which run them. A state chart is loaded from JSON or SCXML,
+//! validated and started as state machine, which reacts on events and changed variables. State
+//! charts and state machines are persisted through the `Crud` trait.
+//!
+//! ```no_run
+//! use state_machines::{validation, Node, StateMachine};
+//!
+//! let state_chart = Node::read("task.json")?;
+//! validation::validate(&state_chart)?;
+//! let mut state_machine = StateMachine::new(state_chart)?;
+//! state_machine.send_event(&"sme:///start".parse()?)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The HTTP layer is part of the http feature, which is enabled by default.
+pub mod actions;
+pub mod codegen;
+pub mod coverage;
+pub mod delivery;
+pub mod diagram;
+pub mod error;
+pub mod fuzz;
+pub mod ids;
+pub mod lint;
+pub mod model_checker;
+pub mod node;
+pub mod predicates;
+pub mod scenario;
+pub mod scxml;
+#[cfg(feature = "http")]
+pub mod server;
+pub mod sql;
+pub mod state_charts;
+pub mod state_machine;
+pub mod state_machine_log;
+pub mod validation;
+
+pub use crate::error::{Error, StateChartError};
+pub use crate::ids::{EventId, NodeId, StateId, VariableId, VariableReference};
+pub use crate::node::Node;
+pub use crate::sql::Crud;
+pub use crate::state_machine::StateMachine;
@@ -0,0 +1,354 @@
+//! The static analysis of a valid state chart. It finds constructs, which are allowed, but most
+//! likely not intended: nodes, which can't be reached, states, which can't be left, transitions,
+//! which are never taken, and events of the state machine, which are never raised.
+use open_api_matcher::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::ids::{EventId, NodeId};
+use crate::node::{Node, NodeKind};
+use crate::state_charts::{ActionCall, Guard, Transition, VariableValue};
+use crate::state_machine::{done_invoke, done_state};
+
+/// A finding of the analysis. The state chart can be saved and run anyway.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// No transition leads to the node or one of its descendants.
+    UnreachableNode(NodeId),
+    /// The state isn't final and neither the state nor its ancestors have a transition.
+    Deadlock(NodeId),
+    /// The transition of the source with the index is never taken, as an earlier transition of
+    /// the source with the same event and without condition is always selected instead. Without index, the transitions
+    /// of the sub-nodes on the same event are selected in every state below the source.
+    ShadowedTransition { source: NodeId, index: usize, event: EventId, by: Option<usize> },
+    /// The transition waits for an event of the state machine, which is never raised.
+    UnraisedEvent { source: NodeId, index: usize, event: EventId },
+}
+impl Warning {
+    /// Assigns a number to every warning, like the error ids.
+    fn warning_id(&self) -> i64 {
+        match self {
+            Self::UnreachableNode(_) => 1000,
+            Self::Deadlock(_) => 1001,
+            Self::ShadowedTransition { .. } => 1002,
+            Self::UnraisedEvent { .. } => 1003,
+        }
+    }
+
+    /// The node, the warning is about.
+    pub fn node(&self) -> &NodeId {
+        match self {
+            Self::UnreachableNode(node_id) | Self::Deadlock(node_id) => node_id,
+            Self::ShadowedTransition { source, .. } | Self::UnraisedEvent { source, .. } => source,
+        }
+    }
+}
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnreachableNode(node_id) => write!(f, "Node '{node_id}' can't be reached."),
+            Self::Deadlock(node_id) => write!(f, "State '{node_id}' isn't final, but can't be left."),
+            Self::ShadowedTransition { source, index, event, by: Some(by) } => write!(
+                f,
+                "Transition {index} of '{source}' on '{event}' is never taken, as transition {by} of the node takes precedence."
+            ),
+            Self::ShadowedTransition { source, index, event, by: None } => write!(
+                f,
+                "Transition {index} of '{source}' on '{event}' is never taken, as the transitions of its sub-nodes take precedence."
+            ),
+            Self::UnraisedEvent { source, index, event } => {
+                write!(f, "Transition {index} of '{source}' waits for '{event}', which is never raised.")
+            }
+        }
+    }
+}
+
+/// Converts a warning into a return value, which has the structure of an error and the node.
+impl From<&Warning> for Value {
+    fn from(warning: &Warning) -> Self {
+        let mut content: BTreeMap<String, Value> = BTreeMap::new();
+        content.insert("id".into(), Value::Integer(warning.warning_id()));
+        content.insert("message".into(), Value::String(warning.to_string()));
+        content.insert("node".into(), warning.node().into());
+        Value::Object(content)
+    }
+}
+
+/// Analyses the state chart, which must be valid. The warnings are in the order of the state
+/// chart.
+pub fn lint(state_chart: &Node) -> Vec<Warning> {
+    let mut nodes = Vec::new();
+    collect(state_chart, &mut nodes);
+    let reached = reachable(state_chart);
+    let raised = raised_events(state_chart, &nodes);
+    let mut warnings = Vec::new();
+    for node in nodes {
+        if !reached.contains(node.id()) {
+            warnings.push(Warning::UnreachableNode(node.id().clone()));
+        }
+        if is_deadlock(state_chart, node) {
+            warnings.push(Warning::Deadlock(node.id().clone()));
+        }
+        for (index, transition) in node.out_transitions().iter().enumerate() {
+            let event = match transition.guard() {
+                Guard::Event(event) => event,
+                _ => continue,
+            };
+            let preferred = node.out_transitions().iter().enumerate().position(|(other_index, other)| {
+                matches!(other.guard(), Guard::Event(other_event) if other_event == event)
+                    && other.condition().is_none()
+                    && (other.priority() > transition.priority() || (other.priority() == transition.priority() && other_index < index))
+            });
+            if preferred.is_some() || handled_below(state_chart, node, event, transition.priority()) {
+                warnings.push(Warning::ShadowedTransition { source: node.id().clone(), index, event: event.clone(), by: preferred });
+            }
+            if event.name().starts_with("done.") && !raised.contains(event) {
+                warnings.push(Warning::UnraisedEvent { source: node.id().clone(), index, event: event.clone() });
+            }
+        }
+    }
+    warnings
+}
+
+/// Collects the node and all its descendants in the order of the state chart.
+fn collect<'a>(node: &'a Node, nodes: &mut Vec<&'a Node>) {
+    nodes.push(node);
+    for sub_node in node.nodes() {
+        collect(sub_node, nodes);
+    }
+}
+
+/// Provides the ids of all nodes, which can be active, starting with the default entry of the
+/// state chart. Every transition of a reached node is assumed to be taken. The guards aren't
+/// evaluated, so some reached nodes may never be active.
+fn reachable(state_chart: &Node) -> BTreeSet<NodeId> {
+    let mut reached = BTreeSet::new();
+    let mut queue = Vec::new();
+    for node in state_chart.default_entry() {
+        reach(node, &mut reached, &mut queue);
+    }
+    while let Some(node) = queue.pop() {
+        for target in node.out_transitions().iter().filter_map(Transition::to) {
+            enter(state_chart, target, &mut reached, &mut queue);
+        }
+    }
+    reached
+}
+
+fn reach<'a>(node: &'a Node, reached: &mut BTreeSet<NodeId>, queue: &mut Vec<&'a Node>) {
+    if reached.insert(node.id().clone()) {
+        queue.push(node);
+    }
+}
+
+/// Enters the target with its ancestors and the nodes, which are entered by default. A history
+/// node without transition enters its parent by default, otherwise the recorded nodes, which were
+/// reached before.
+fn enter<'a>(state_chart: &'a Node, target: &NodeId, reached: &mut BTreeSet<NodeId>, queue: &mut Vec<&'a Node>) {
+    let chain = match state_chart.path_to(target) {
+        Some(chain) => chain,
+        None => return,
+    };
+    for (index, node) in chain.iter().enumerate() {
+        reach(node, reached, queue);
+        if let (NodeKind::Parallel, Some(next)) = (node.kind(), chain.get(index + 1)) {
+            for region in node.nodes().iter().filter(|region| region.id() != next.id() && !region.kind().is_pseudo()) {
+                region.default_entry().into_iter().for_each(|node| reach(node, reached, queue));
+            }
+        }
+    }
+    let target = chain[chain.len() - 1];
+    let entered = match (target.kind().is_history(), chain.len()) {
+        (true, length) if target.out_transitions().is_empty() && length > 1 => chain[length - 2].default_entry(),
+        (true, _) => Vec::new(),
+        (false, _) => target.default_entry(),
+    };
+    entered.into_iter().for_each(|node| reach(node, reached, queue));
+}
+
+/// A state without sub-states is a deadlock, if neither the state nor its ancestors have a
+/// transition.
+fn is_deadlock(state_chart: &Node, node: &Node) -> bool {
+    node.kind() == NodeKind::State
+        && node.nodes().iter().all(|sub_node| sub_node.kind().is_pseudo())
+        && state_chart
+            .path_to(node.id())
+            .is_some_and(|chain| chain.iter().all(|node| node.out_transitions().is_empty()))
+}
+
+/// Checks, if the event is always handled by a transition of a sub-node with at least the given
+/// priority, whichever sub-nodes are active. Between transitions of the same priority the
+/// innermost transition is preferred, so the transition of the node is never taken on this event.
+fn handled_below(state_chart: &Node, node: &Node, event: &EventId, priority: i32) -> bool {
+    let sub_nodes: Vec<&Node> = node.nodes().iter().filter(|sub_node| !sub_node.kind().is_pseudo()).collect();
+    match node.kind() {
+        // A transition without target doesn't conflict with the transitions of the other regions.
+        NodeKind::Parallel => sub_nodes.iter().any(|region| handles(state_chart, region, event, priority, true)),
+        _ => !sub_nodes.is_empty() && sub_nodes.iter().all(|sub_node| handles(state_chart, sub_node, event, priority, false)),
+    }
+}
+
+/// Checks, if the node or its sub-nodes always have an enabled transition on the event with at
+/// least the given priority. A transition to a junction or with a condition may be disabled.
+fn handles(state_chart: &Node, node: &Node, event: &EventId, priority: i32, with_target: bool) -> bool {
+    let handled = node.out_transitions().iter().any(|transition| {
+        matches!(transition.guard(), Guard::Event(other) if other == event)
+            && transition.condition().is_none()
+            && transition.priority() >= priority
+            && match transition.to() {
+                Some(to) => state_chart.find(to).is_some_and(|target| target.kind() != NodeKind::Junction),
+                None => !with_target,
+            }
+    });
+    let sub_nodes: Vec<&Node> = node.nodes().iter().filter(|sub_node| !sub_node.kind().is_pseudo()).collect();
+    handled
+        || match node.kind() {
+            NodeKind::Parallel => sub_nodes.iter().any(|region| handles(state_chart, region, event, priority, true)),
+            _ => !sub_nodes.is_empty() && sub_nodes.iter().all(|sub_node| handles(state_chart, sub_node, event, priority, with_target)),
+        }
+}
+
+/// Provides the events, which are raised by the state machine itself: the done events of the
+/// nodes with final sub-nodes and of the parallel nodes with final nodes in all regions, the done
+/// events of invoked state machines and the events raised by actions.
+fn raised_events(state_chart: &Node, nodes: &[&Node]) -> BTreeSet<EventId> {
+    let mut raised = BTreeSet::new();
+    let has_final = |node: &Node| node.nodes().iter().any(|sub_node| sub_node.kind() == NodeKind::Final);
+    for node in nodes {
+        let regions = node.nodes().iter().filter(|region| !region.kind().is_pseudo());
+        if has_final(node) || (node.kind() == NodeKind::Parallel && regions.clone().count() > 0 && regions.clone().all(has_final)) {
+            raised.extend(done_state(node.id()).ok());
+        }
+        for action in node.on_entry().into_iter().chain(node.on_exit()) {
+            raised.extend(raised_event(action, &[node]));
+        }
+        // A state machine, invoked by a transition, is bound to its domain, one of the ancestors of
+        // the source.
+        let ancestors = state_chart.path_to(node.id()).unwrap_or_default();
+        for action in node.out_transitions().iter().filter_map(Transition::action) {
+            raised.extend(raised_event(action, &ancestors));
+        }
+    }
+    raised
+}
+
+/// Provides the events raised by the action. The done event of an invoked state machine depends on
+/// the node, it is bound to.
+fn raised_event(action: &ActionCall, bound_to: &[&Node]) -> Vec<EventId> {
+    match action.name().as_str() {
+        "invoke" => bound_to.iter().filter_map(|node| done_invoke(node.id()).ok()).collect(),
+        "raise" => match action.parameters().first().map(|parameter| parameter.value()) {
+            Some(VariableValue::String(event)) => event.parse().into_iter().collect(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures() {
+        for file_name in ["tests/media-player.scxml", "tests/session-task.json", "tests/ping-pong.json"] {
+            assert_eq!(Vec::<Warning>::new(), lint(&Node::read(file_name).unwrap()), "{file_name}");
+        }
+        // The stopped tally can't be left, but isn't final.
+        let warnings = lint(&Node::read("tests/tally-task.json").unwrap());
+        assert_eq!(vec![Warning::Deadlock(NodeId::new("Tally-Task/Stopped"))], warnings);
+        assert_eq!("State 'scn:///Tally-Task/Stopped' isn't final, but can't be left.", warnings[0].to_string());
+        let warnings = lint(&Node::read("tests/approval.json").unwrap());
+        assert_eq!(vec![Warning::Deadlock(NodeId::new("Approval/Approved"))], warnings);
+    }
+
+    #[test]
+    fn test_unreachable_and_deadlock() {
+        let state_chart = Node::builder("Task")
+            .start_node("Open")
+            .state(Node::builder("Open").transition(Transition::on("close").to("Closed")))
+            .state(Node::builder("Closed").kind(NodeKind::Final))
+            .state(Node::builder("Lost"))
+            .state(Node::builder("Parked").transition(Transition::on("resume").to("Open")))
+            .build()
+            .unwrap();
+        assert_eq!(
+            vec![
+                Warning::UnreachableNode(NodeId::new("Task/Lost")),
+                Warning::Deadlock(NodeId::new("Task/Lost")),
+                Warning::UnreachableNode(NodeId::new("Task/Parked")),
+            ],
+            lint(&state_chart)
+        );
+    }
+
+    #[test]
+    fn test_shadowed_transitions() {
+        let event: EventId = "sme:///cancel".parse().unwrap();
+        let state_chart = Node::builder("Task")
+            .start_node("Work")
+            .state(
+                Node::builder("Work")
+                    .start_node("Doing")
+                    .transition(Transition::on("cancel").to("Cancelled"))
+                    .state(Node::builder("Doing").transition(Transition::on("cancel").to("../../Cancelled")))
+                    .state(
+                        Node::builder("Waiting")
+                            .transition(Transition::on("cancel").to("../../Cancelled"))
+                            .transition(Transition::on("cancel").internal().priority(1))
+                            .transition(Transition::on("wake").to("../Doing")),
+                    ),
+            )
+            .state(Node::builder("Cancelled").kind(NodeKind::Final))
+            .build()
+            .unwrap();
+        let warnings = lint(&state_chart);
+        assert_eq!(
+            vec![
+                Warning::ShadowedTransition { source: NodeId::new("Task/Work"), index: 0, event: event.clone(), by: None },
+                Warning::UnreachableNode(NodeId::new("Task/Work/Waiting")),
+                Warning::ShadowedTransition { source: NodeId::new("Task/Work/Waiting"), index: 0, event: event.clone(), by: Some(1) },
+            ],
+            warnings
+        );
+        assert!(warnings[2].to_string().contains("as transition 1 of the node takes precedence"));
+
+        let state_chart = Node::builder("Task")
+            .start_node("Work")
+            .state(
+                Node::builder("Work")
+                    .start_node("Doing")
+                    .transition(Transition::on("cancel").to("Cancelled").priority(1))
+                    .state(Node::builder("Doing").transition(Transition::on("cancel").to("../../Done"))),
+            )
+            .state(Node::builder("Cancelled").kind(NodeKind::Final))
+            .state(Node::builder("Done").kind(NodeKind::Final))
+            .build()
+            .unwrap();
+        assert!(!lint(&state_chart).iter().any(|warning| matches!(warning, Warning::ShadowedTransition { .. })));
+    }
+
+    #[test]
+    fn test_unraised_events() {
+        let state_chart = Node::builder("Case")
+            .start_node("Review")
+            .state(
+                Node::builder("Review")
+                    .on_entry(ActionCall::builder("raise").parameter("event", "sme:///done.review"))
+                    .transition(Transition::on("done.review").to("Waiting"))
+                    .transition(Transition::on("done.state.Review").to("Waiting")),
+            )
+            .state(Node::builder("Waiting").transition(Transition::on("done.invoke.Review").to("Review")))
+            .build()
+            .unwrap();
+        let warnings: Vec<Value> = lint(&state_chart).iter().map(Value::from).collect();
+        assert_eq!(2, warnings.len());
+        match &warnings[0] {
+            Value::Object(content) => {
+                assert!(matches!(content.get("id"), Some(Value::Integer(1003))));
+                assert!(matches!(content.get("node"), Some(Value::String(node)) if node == "scn:///Case/Review"));
+            }
+            _ => panic!("Expected an object!"),
+        }
+    }
+}
@@ -0,0 +1,848 @@
+//! Checks temporal properties of a state chart on its state space. The state space is explored
+//! from the start of a state machine by sending every event of the state chart and by setting
+//! every variable to the values of its finite domain, up to a bound on the number of steps and on
+//! the number of states. A state is the stable state of the machine after an input. Inputs, which
+//! don't change the state, aren't steps.
+//!
+//! The domain of a variable holds its initial value and the literals, it is compared with by the
+//! predicates of the state chart, together with their neighbours for integers and numbers. A
+//! boolean has both values. So the properties are checked for these values only.
+//!
+//! The properties are written in a CTL-style syntax with the names or paths of the nodes as
+//! atoms, which are true, if the node is active:
+//!
+//! - `!f`, `f & g`, `f | g`, `f -> g` and `true` or `false`,
+//! - `AX f`, `AF f`, `AG f`, `A[f U g]`, `A[f W g]` hold on all paths, `EX f`, `EF f`, `EG f`,
+//!   `E[f U g]`, `E[f W g]` on some path,
+//! - `X f`, `F f`, `G f`, `f U g` and `f W g` are the LTL-style forms, which hold on all paths.
+//!
+//! For example, `!Done W Approved` holds, if Done is never reached without passing Approved,
+//! while `AG EF Cancelled` holds, if Cancelled is reachable from every state. A path ends in a
+//! state without steps, where `AF f` fails unless f holds, while `AG f` holds.
+//!
+//! The states at the bound aren't explored further. A property holds, if it holds whatever their
+//! successors are, and it is violated, if it fails whatever their successors are. Otherwise it is
+//! undecided within the bound. A violated property comes with a counter-example, which leads
+//! from the start to the violation, if a single path shows it.
+use open_api_matcher::ValidatedValue;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use crate::error::StateChartError;
+use crate::ids::{EventId, NodeId, VariableId, VariableReference};
+use crate::node::Node;
+use crate::state_charts::{Guard, VariableDeclaration, VariableValue};
+use crate::state_machine::StateMachine;
+
+/// The bounds of the exploration of the state space.
+#[derive(Clone, Copy, Debug)]
+pub struct Bounds {
+    /// The maximal number of steps from the start.
+    pub depth: usize,
+    /// The maximal number of states.
+    pub states: usize,
+}
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds { depth: 20, states: 10_000 }
+    }
+}
+
+/// An input of the environment, which leads from one state to another.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    Send(EventId),
+    /// Sets the variable, given by the path of the declaring node and its name, to the value.
+    Set(String, VariableValue),
+}
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Send(event) if event.as_str().starts_with("sme:///") => write!(f, "send {}", event.name()),
+            Self::Send(event) => write!(f, "send {event}"),
+            Self::Set(variable, VariableValue::Boolean(value)) => write!(f, "set {variable} = {value}"),
+            Self::Set(variable, value) => write!(f, "set {variable} = {value}"),
+        }
+    }
+}
+
+/// A state of the state space with the active nodes and the steps to the successors. The states
+/// at the bound aren't expanded, so their successors are unknown.
+struct State {
+    configuration: Vec<NodeId>,
+    leaves: Vec<String>,
+    steps: Vec<(Input, usize)>,
+    expanded: bool,
+}
+impl State {
+    fn new(state_machine: &StateMachine) -> Result<Self, StateChartError> {
+        let leaves = state_machine
+            .active_states()?
+            .iter()
+            .map(|state_id| state_id.path().map(String::from))
+            .collect::<Result<Vec<String>, StateChartError>>()?;
+        Ok(State { configuration: state_machine.configuration()?, leaves, steps: Vec::new(), expanded: false })
+    }
+
+    /// A state without steps, which was expanded, ends every path.
+    fn is_end(&self) -> bool {
+        self.expanded && self.steps.is_empty()
+    }
+}
+
+/// The explored states of a state chart. The first state is the start.
+pub struct StateSpace {
+    states: Vec<State>,
+}
+impl StateSpace {
+    /// Explores the state space of the state chart, which must be valid, breadth first within the
+    /// bounds. Inputs, which fail, e.g. as the variable isn't in scope, are skipped.
+    pub fn explore(state_chart: &Node, bounds: &Bounds) -> Result<StateSpace, StateChartError> {
+        let mut state_machine = StateMachine::new(state_chart.clone())?;
+        drain(&mut state_machine);
+        let inputs = inputs(state_chart);
+        let mut keys = BTreeMap::new();
+        keys.insert(state_machine.fingerprint(), 0);
+        let mut states = vec![State::new(&state_machine)?];
+        let mut queue = VecDeque::from([(0, 0, state_machine)]);
+        while let Some((index, depth, state_machine)) = queue.pop_front() {
+            if depth >= bounds.depth {
+                continue;
+            }
+            let key = state_machine.fingerprint();
+            let mut successors: Vec<(Input, StateMachine, String)> = Vec::new();
+            for input in &inputs {
+                let mut successor = state_machine.clone();
+                if apply(&mut successor, input).is_err() {
+                    continue;
+                }
+                drain(&mut successor);
+                let successor_key = successor.fingerprint();
+                if successor_key != key {
+                    successors.push((input.clone(), successor, successor_key));
+                }
+            }
+            let unknown = successors.iter().filter(|(_, _, key)| !keys.contains_key(key)).count();
+            if states.len() + unknown > bounds.states {
+                continue;
+            }
+            for (input, successor, successor_key) in successors {
+                let target = match keys.get(&successor_key) {
+                    Some(target) => *target,
+                    None => {
+                        keys.insert(successor_key, states.len());
+                        states.push(State::new(&successor)?);
+                        queue.push_back((states.len() - 1, depth + 1, successor));
+                        states.len() - 1
+                    }
+                };
+                states[index].steps.push((input, target));
+            }
+            states[index].expanded = true;
+        }
+        Ok(StateSpace { states })
+    }
+
+    /// The number of explored states.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// The state space is complete, if all states were expanded within the bounds.
+    pub fn is_complete(&self) -> bool {
+        self.states.iter().all(|state| state.expanded)
+    }
+
+    /// Checks the property in the start state.
+    pub fn check(&self, property: &Property) -> Verdict {
+        if self.evaluate(&property.formula, Approximation::Must)[0] {
+            Verdict::Holds
+        } else if !self.evaluate(&property.formula, Approximation::May)[0] {
+            let mut trace = Trace { states: vec![0], cycle: None };
+            self.explain(&property.formula, false, &mut trace);
+            Verdict::Violated(self.counter_example(&trace))
+        } else {
+            Verdict::Undecided
+        }
+    }
+
+    /// Evaluates the formula in all states. The successors of the states, which weren't expanded,
+    /// are assumed to violate the formula for the must approximation and to satisfy it for the
+    /// may approximation.
+    fn evaluate(&self, formula: &Formula, approximation: Approximation) -> Vec<bool> {
+        match formula {
+            Formula::Constant(value) => vec![*value; self.states.len()],
+            Formula::Active(node_id) => self.states.iter().map(|state| state.configuration.contains(node_id)).collect(),
+            Formula::Not(formula) => self.evaluate(formula, approximation.dual()).into_iter().map(|value| !value).collect(),
+            Formula::And(left, right) => {
+                let right = self.evaluate(right, approximation);
+                self.evaluate(left, approximation).into_iter().zip(right).map(|(left, right)| left && right).collect()
+            }
+            Formula::Or(left, right) => {
+                let right = self.evaluate(right, approximation);
+                self.evaluate(left, approximation).into_iter().zip(right).map(|(left, right)| left || right).collect()
+            }
+            Formula::Next(quantifier, formula) => {
+                let values = self.evaluate(formula, approximation);
+                (0..self.states.len()).map(|index| self.next(index, *quantifier, &values, approximation, true)).collect()
+            }
+            Formula::Until(quantifier, left, right, weak) => {
+                let left = self.evaluate(left, approximation);
+                let right = self.evaluate(right, approximation);
+                // The weak until is the greatest and the strong until the least fixpoint.
+                let mut values = vec![*weak; self.states.len()];
+                loop {
+                    let next: Vec<bool> = (0..self.states.len())
+                        .map(|index| right[index] || (left[index] && self.next(index, *quantifier, &values, approximation, *weak)))
+                        .collect();
+                    if next == values {
+                        return values;
+                    }
+                    values = next;
+                }
+            }
+        }
+    }
+
+    /// Checks, if all or some successors of the state have the value. A state without steps has
+    /// the value at the end of a path.
+    fn next(&self, index: usize, quantifier: Quantifier, values: &[bool], approximation: Approximation, at_end: bool) -> bool {
+        let state = &self.states[index];
+        if !state.expanded {
+            return approximation == Approximation::May;
+        }
+        if state.steps.is_empty() {
+            return at_end;
+        }
+        match quantifier {
+            Quantifier::All => state.steps.iter().all(|(_, target)| values[*target]),
+            Quantifier::Exists => state.steps.iter().any(|(_, target)| values[*target]),
+        }
+    }
+
+    /// Extends the trace by a path, which shows that the formula has the value in the last state
+    /// of the trace. Only the universal formulas are shown to be false and the existential ones to
+    /// be true, as a single path can't show the other cases.
+    fn explain(&self, formula: &Formula, value: bool, trace: &mut Trace) {
+        let approximation = if value { Approximation::Must } else { Approximation::May };
+        let index = *trace.states.last().unwrap();
+        match (formula, value) {
+            (Formula::Not(formula), _) => self.explain(formula, !value, trace),
+            (Formula::And(left, right), false) | (Formula::Or(left, right), true) => {
+                if self.evaluate(left, approximation)[index] == value {
+                    self.explain(left, value, trace)
+                } else {
+                    self.explain(right, value, trace)
+                }
+            }
+            // Both sides have the value, the right side is the consequent of an implication.
+            (Formula::Or(_, right), false) | (Formula::And(_, right), true) => self.explain(right, value, trace),
+            (Formula::Next(Quantifier::All, formula), false) | (Formula::Next(Quantifier::Exists, formula), true) => {
+                let values = self.evaluate(formula, approximation);
+                if let Some((_, target)) = self.states[index].steps.iter().find(|(_, target)| values[*target] == value) {
+                    trace.states.push(*target);
+                    self.explain(formula, value, trace);
+                }
+            }
+            (Formula::Until(Quantifier::All, left, _, weak), false) | (Formula::Until(Quantifier::Exists, _, left, weak), true) => {
+                // A universal until fails, if the left side fails before the right side holds, or
+                // if the right side never holds. An existential until holds, if the right side
+                // holds, or if the left side always holds.
+                let region = self.evaluate(formula, approximation);
+                let goal = self.evaluate(left, approximation);
+                let strong_end = value == *weak;
+                match self.path(index, |index| region[index] == value, |index| goal[index] == value) {
+                    Some(path) => {
+                        trace.states.extend(path);
+                        self.explain(left, value, trace);
+                    }
+                    None if strong_end => self.lasso(trace, |index| region[index] == value),
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Provides the shortest path from the state to a goal, which stays in the region. The path
+    /// starts after the state.
+    fn path(&self, start: usize, region: impl Fn(usize) -> bool, goal: impl Fn(usize) -> bool) -> Option<Vec<usize>> {
+        let mut previous: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(index) = queue.pop_front() {
+            if goal(index) {
+                let mut path = vec![index];
+                while let Some(before) = previous.get(path.last().unwrap()) {
+                    path.push(*before);
+                }
+                path.pop();
+                path.reverse();
+                return Some(path);
+            }
+            for (_, target) in &self.states[index].steps {
+                if region(*target) && *target != start && !previous.contains_key(target) {
+                    previous.insert(*target, index);
+                    queue.push_back(*target);
+                }
+            }
+        }
+        None
+    }
+
+    /// Extends the trace in the region, until it reaches the end of a path or a state of the
+    /// trace again.
+    fn lasso(&self, trace: &mut Trace, region: impl Fn(usize) -> bool) {
+        let start = trace.states.len() - 1;
+        loop {
+            let index = *trace.states.last().unwrap();
+            match self.states[index].steps.iter().find(|(_, target)| region(*target)) {
+                Some((_, target)) => {
+                    if let Some(position) = trace.states[start..].iter().position(|index| index == target) {
+                        trace.cycle = Some(start + position);
+                        return;
+                    }
+                    trace.states.push(*target);
+                }
+                None => return,
+            }
+        }
+    }
+
+    fn counter_example(&self, trace: &Trace) -> CounterExample {
+        let mut steps = Vec::new();
+        for pair in trace.states.windows(2) {
+            let (input, _) = self.states[pair[0]].steps.iter().find(|(_, target)| *target == pair[1]).unwrap();
+            steps.push((input.clone(), self.states[pair[1]].leaves.clone()));
+        }
+        let last = &self.states[*trace.states.last().unwrap()];
+        let cycle = match trace.cycle {
+            Some(cycle) => last
+                .steps
+                .iter()
+                .find(|(_, target)| *target == trace.states[cycle])
+                .map(|(input, _)| (cycle, input.clone())),
+            None => None,
+        };
+        CounterExample { start: self.states[0].leaves.clone(), steps, cycle, end: last.is_end() }
+    }
+}
+
+/// The states of a path through the state space, which returns to the state at the position of
+/// the cycle.
+struct Trace {
+    states: Vec<usize>,
+    cycle: Option<usize>,
+}
+
+/// The result of checking a property.
+#[derive(Debug)]
+pub enum Verdict {
+    Holds,
+    Violated(CounterExample),
+    /// The property depends on the states beyond the bounds.
+    Undecided,
+}
+
+/// The inputs from the start to the violation of a property, each with the active states after
+/// it. A path, which never fulfils the property, ends with the input back to an earlier state of
+/// the path or in a state without steps.
+#[derive(Debug)]
+pub struct CounterExample {
+    start: Vec<String>,
+    steps: Vec<(Input, Vec<String>)>,
+    /// The position of the state in the path, which is reached again, and the input to it.
+    cycle: Option<(usize, Input)>,
+    end: bool,
+}
+impl CounterExample {
+    pub fn steps(&self) -> &Vec<(Input, Vec<String>)> {
+        &self.steps
+    }
+
+    pub fn cycle(&self) -> Option<&(usize, Input)> {
+        self.cycle.as_ref()
+    }
+}
+impl fmt::Display for CounterExample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  0. start: {}", self.start.join(", "))?;
+        for (index, (input, leaves)) in self.steps.iter().enumerate() {
+            writeln!(f, "  {}. {input}: {}", index + 1, leaves.join(", "))?;
+        }
+        match &self.cycle {
+            Some((position, input)) => writeln!(f, "  {input}: back to step {position}."),
+            None if self.end => writeln!(f, "  No further steps."),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A property of the state chart.
+#[derive(Debug)]
+pub struct Property {
+    formula: Formula,
+}
+impl Property {
+    /// Parses the property. The atoms are the paths of the nodes of the state chart, a path may
+    /// be shortened to its end, as long as it is unique. Names, which are operators, are written
+    /// in double quotes.
+    pub fn parse(property: &str, state_chart: &Node) -> Result<Property, StateChartError> {
+        let mut nodes = Vec::new();
+        collect_nodes(state_chart, &mut nodes);
+        let paths = nodes.iter().map(|node| node.id().clone()).collect();
+        let mut parser = Parser { tokens: tokenize(property)?, position: 0, paths };
+        let formula = parser.implication()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(Property { formula }),
+            Some(token) => Err(invalid(&format!("unexpected '{token}'"))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Approximation {
+    Must,
+    May,
+}
+impl Approximation {
+    fn dual(self) -> Self {
+        match self {
+            Self::Must => Self::May,
+            Self::May => Self::Must,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Quantifier {
+    All,
+    Exists,
+}
+
+/// The formulas of the properties. The eventually and always operators are expressed by the
+/// until operators: `F f` is `true U f` and `G f` is `f W false`.
+#[derive(Debug)]
+enum Formula {
+    Constant(bool),
+    Active(NodeId),
+    Not(Box<Formula>),
+    And(Box<Formula>, Box<Formula>),
+    Or(Box<Formula>, Box<Formula>),
+    Next(Quantifier, Box<Formula>),
+    /// The left side holds until the right side holds. A weak until also holds, if the left side
+    /// holds forever.
+    Until(Quantifier, Box<Formula>, Box<Formula>, bool),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    paths: Vec<NodeId>,
+}
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), StateChartError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(invalid(&format!("expected '{expected}', but found '{token}'"))),
+            None => Err(invalid(&format!("expected '{expected}'"))),
+        }
+    }
+
+    fn implication(&mut self) -> Result<Formula, StateChartError> {
+        let left = self.disjunction()?;
+        if self.peek() == Some(&Token::Implies) {
+            self.position += 1;
+            return Ok(Formula::Or(Box::new(Formula::Not(Box::new(left))), Box::new(self.implication()?)));
+        }
+        Ok(left)
+    }
+
+    fn disjunction(&mut self) -> Result<Formula, StateChartError> {
+        let mut left = self.conjunction()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            left = Formula::Or(Box::new(left), Box::new(self.conjunction()?));
+        }
+        Ok(left)
+    }
+
+    fn conjunction(&mut self) -> Result<Formula, StateChartError> {
+        let mut left = self.until(Quantifier::All)?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            left = Formula::And(Box::new(left), Box::new(self.until(Quantifier::All)?));
+        }
+        Ok(left)
+    }
+
+    fn until(&mut self, quantifier: Quantifier) -> Result<Formula, StateChartError> {
+        let left = self.unary()?;
+        let weak = match self.peek() {
+            Some(Token::Name(name)) if name == "U" => false,
+            Some(Token::Name(name)) if name == "W" => true,
+            _ => return Ok(left),
+        };
+        self.position += 1;
+        Ok(Formula::Until(quantifier, Box::new(left), Box::new(self.unary()?), weak))
+    }
+
+    fn unary(&mut self) -> Result<Formula, StateChartError> {
+        let name = match self.next() {
+            Some(Token::Not) => return Ok(Formula::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let formula = self.implication()?;
+                self.expect(Token::Close)?;
+                return Ok(formula);
+            }
+            Some(Token::Quoted(name)) => return self.atom(&name),
+            Some(Token::Name(name)) => name,
+            Some(token) => return Err(invalid(&format!("unexpected '{token}'"))),
+            None => return Err(invalid("the formula ends unexpectedly")),
+        };
+        let (quantifier, operator) = match name.as_str() {
+            "true" => return Ok(Formula::Constant(true)),
+            "false" => return Ok(Formula::Constant(false)),
+            "A" | "E" if self.peek() == Some(&Token::OpenBracket) => {
+                self.position += 1;
+                let quantifier = if name == "A" { Quantifier::All } else { Quantifier::Exists };
+                let formula = self.until(quantifier)?;
+                self.expect(Token::CloseBracket)?;
+                return match formula {
+                    Formula::Until(..) => Ok(formula),
+                    _ => Err(invalid(&format!("expected an until in {name}[...]"))),
+                };
+            }
+            "AX" | "AF" | "AG" => (Quantifier::All, &name[1..]),
+            "EX" | "EF" | "EG" => (Quantifier::Exists, &name[1..]),
+            "X" | "F" | "G" => (Quantifier::All, name.as_str()),
+            _ => return self.atom(&name),
+        };
+        let formula = Box::new(self.unary()?);
+        Ok(match operator {
+            "X" => Formula::Next(quantifier, formula),
+            "F" => Formula::Until(quantifier, Box::new(Formula::Constant(true)), formula, false),
+            _ => Formula::Until(quantifier, formula, Box::new(Formula::Constant(false)), true),
+        })
+    }
+
+    /// Provides the node with the path or the unique node, whose path ends with it.
+    fn atom(&self, name: &str) -> Result<Formula, StateChartError> {
+        let suffix = format!("/{name}");
+        let matches: Vec<&NodeId> = self
+            .paths
+            .iter()
+            .filter(|node_id| node_id.path().is_ok_and(|path| path == name || path.ends_with(&suffix)))
+            .collect();
+        match matches.as_slice() {
+            [node_id] => Ok(Formula::Active((*node_id).clone())),
+            [] => Err(invalid(&format!("'{name}' isn't a node of the state chart"))),
+            _ => match matches.iter().find(|node_id| node_id.path().is_ok_and(|path| path == name)) {
+                Some(node_id) => Ok(Formula::Active((*node_id).clone())),
+                None => Err(invalid(&format!("'{name}' is ambiguous"))),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Quoted(String),
+    Not,
+    And,
+    Or,
+    Implies,
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+}
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "{name}"),
+            Self::Quoted(name) => write!(f, "\"{name}\""),
+            Self::Not => write!(f, "!"),
+            Self::And => write!(f, "&"),
+            Self::Or => write!(f, "|"),
+            Self::Implies => write!(f, "->"),
+            Self::Open => write!(f, "("),
+            Self::Close => write!(f, ")"),
+            Self::OpenBracket => write!(f, "["),
+            Self::CloseBracket => write!(f, "]"),
+        }
+    }
+}
+
+/// Splits the property into tokens. Names consist of letters, digits and "_", "-", "." or "/",
+/// but a "-" followed by ">" is an implication.
+fn tokenize(property: &str) -> Result<Vec<Token>, StateChartError> {
+    let mut tokens = Vec::new();
+    let mut chars = property.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '!' => Token::Not,
+            '&' => Token::And,
+            '|' => Token::Or,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                Token::Implies
+            }
+            '"' => {
+                let name: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                Token::Quoted(name)
+            }
+            _ if is_name_char(c) => {
+                let mut name = String::from(c);
+                while let Some(c) = chars.peek().copied() {
+                    let mut lookahead = chars.clone();
+                    lookahead.next();
+                    if !is_name_char(c) || (c == '-' && lookahead.peek() == Some(&'>')) {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                Token::Name(name)
+            }
+            _ => return Err(invalid(&format!("unexpected '{c}'"))),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/')
+}
+
+fn invalid(reason: &str) -> StateChartError {
+    StateChartError::InvalidProperty(reason.into())
+}
+
+/// Takes the messages, actions and observations of the last step, which aren't part of the state.
+fn drain(state_machine: &mut StateMachine) {
+    state_machine.take_messages();
+    state_machine.take_executed_actions();
+    state_machine.take_observations();
+}
+
+pub(crate) fn apply(state_machine: &mut StateMachine, input: &Input) -> Result<(), StateChartError> {
+    match input {
+        Input::Send(event) => {
+            state_machine.send_event(event)?;
+        }
+        Input::Set(variable, value) => {
+            let (node_path, name) = variable.rsplit_once('/').ok_or_else(|| StateChartError::UnknownVariable(variable.clone()))?;
+            let variable_id = VariableId::new(state_machine.id(), &NodeId::new(node_path), name)?;
+            state_machine.set_variable(&variable_id, &ValidatedValue::from(value))?;
+        }
+    }
+    Ok(())
+}
+
+/// Provides the events of the state chart and the settings of its variables to all values of
+/// their domains.
+pub(crate) fn inputs(state_chart: &Node) -> Vec<Input> {
+    let mut nodes = Vec::new();
+    collect_nodes(state_chart, &mut nodes);
+    let mut events: Vec<EventId> = Vec::new();
+    let mut inputs = Vec::new();
+    for node in &nodes {
+        for transition in node.out_transitions() {
+            if let Guard::Event(event) = transition.guard() {
+                if !events.contains(event) {
+                    events.push(event.clone());
+                }
+            }
+        }
+    }
+    inputs.extend(events.into_iter().map(Input::Send));
+    for node in &nodes {
+        let node_path = match node.id().path() {
+            Ok(node_path) => node_path,
+            Err(_) => continue,
+        };
+        for declaration in node.attributes() {
+            let variable = format!("{node_path}/{}", declaration.name());
+            let Ok(reference) = VariableReference::new(node.id(), declaration.name()) else {
+                continue;
+            };
+            inputs.extend(domain(&nodes, &reference, declaration).into_iter().map(|value| Input::Set(variable.clone(), value)));
+        }
+    }
+    inputs
+}
+
+/// Provides the initial value of the variable and the literals, it is compared with, together
+/// with their neighbours for integers and numbers.
+fn domain(nodes: &[&Node], reference: &VariableReference, declaration: &VariableDeclaration) -> Vec<VariableValue> {
+    let reference = VariableValue::Reference(reference.clone());
+    let mut candidates = vec![declaration.value().clone()];
+    if declaration.value_type() == "boolean" {
+        candidates.extend([VariableValue::Boolean(false), VariableValue::Boolean(true)]);
+    }
+    let predicate_calls = nodes.iter().flat_map(|node| node.out_transitions()).flat_map(|transition| {
+        let guard = match transition.guard() {
+            Guard::Predicate(predicate_call) => Some(predicate_call),
+            _ => None,
+        };
+        guard.into_iter().chain(transition.condition())
+    });
+    for predicate_call in predicate_calls {
+        if !predicate_call.parameters().iter().any(|parameter| parameter.value() == &reference) {
+            continue;
+        }
+        for parameter in predicate_call.parameters() {
+            match parameter.value() {
+                VariableValue::Reference(_) => {}
+                VariableValue::Integer(value) => {
+                    let neighbours = [value.checked_sub(1), Some(*value), value.checked_add(1)];
+                    candidates.extend(neighbours.into_iter().flatten().map(VariableValue::Integer));
+                }
+                VariableValue::Number(value) => {
+                    candidates.extend([value - 1.0, *value, value + 1.0].map(VariableValue::Number));
+                }
+                value => candidates.push(value.clone()),
+            }
+        }
+    }
+    let mut values: Vec<VariableValue> = Vec::new();
+    for value in candidates.into_iter().filter_map(|value| declaration.coerce(value).ok()) {
+        if !values.iter().any(|other| other.to_string() == value.to_string()) {
+            values.push(value);
+        }
+    }
+    values
+}
+
+fn collect_nodes<'a>(node: &'a Node, nodes: &mut Vec<&'a Node>) {
+    nodes.push(node);
+    for sub_node in node.nodes() {
+        collect_nodes(sub_node, nodes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::NodeKind;
+    use crate::state_charts::{PredicateCall, Transition};
+
+    fn check(state_space: &StateSpace, state_chart: &Node, property: &str) -> Verdict {
+        state_space.check(&Property::parse(property, state_chart).unwrap())
+    }
+
+    #[test]
+    fn test_approval() {
+        let state_chart = Node::read("tests/approval.json").unwrap();
+        let state_space = StateSpace::explore(&state_chart, &Bounds::default()).unwrap();
+        assert!(state_space.is_complete());
+        assert!(matches!(check(&state_space, &state_chart, "!Approved W In-Approval"), Verdict::Holds));
+        assert!(matches!(check(&state_space, &state_chart, "AG EF Approved"), Verdict::Holds));
+        assert!(matches!(check(&state_space, &state_chart, "AG (Approved -> AG Approved)"), Verdict::Holds));
+
+        // The approval can't be withdrawn.
+        match check(&state_space, &state_chart, "AG EF Draft") {
+            Verdict::Violated(counter_example) => {
+                let steps: Vec<String> = counter_example.steps().iter().map(|(input, _)| input.to_string()).collect();
+                assert_eq!(vec!["set Approval/amount = 1", "set Approval/In-Approval/approved = true"], steps);
+                assert!(counter_example.to_string().ends_with("2. set Approval/In-Approval/approved = true: Approval/Approved\n"));
+            }
+            verdict => panic!("Expected a violation, but got {verdict:?}!"),
+        }
+        match check(&state_space, &state_chart, "G !Escalated") {
+            Verdict::Violated(counter_example) => {
+                assert_eq!(1, counter_example.steps().len());
+                assert_eq!("set Approval/amount = 1001", counter_example.steps()[0].0.to_string());
+            }
+            verdict => panic!("Expected a violation, but got {verdict:?}!"),
+        }
+        // Nobody has to approve, the approval may be cancelled forever.
+        match check(&state_space, &state_chart, "AF Approved") {
+            Verdict::Violated(counter_example) => assert!(counter_example.cycle().is_some()),
+            verdict => panic!("Expected a violation, but got {verdict:?}!"),
+        }
+
+        let state_space = StateSpace::explore(&state_chart, &Bounds { depth: 1, states: 100 }).unwrap();
+        assert!(!state_space.is_complete());
+        assert!(matches!(check(&state_space, &state_chart, "EF Approved"), Verdict::Undecided));
+        assert!(matches!(check(&state_space, &state_chart, "EX Checking"), Verdict::Holds));
+    }
+
+    #[test]
+    fn test_events() {
+        let state_chart = Node::builder("Task")
+            .start_node("Open")
+            .state(Node::builder("Open").transition(Transition::on("start").to("Doing")).transition(Transition::on("cancel").to("Cancelled")))
+            .state(Node::builder("Doing").transition(Transition::on("finish").to("Done")))
+            .state(Node::builder("Done").kind(NodeKind::Final))
+            .state(Node::builder("Cancelled").kind(NodeKind::Final))
+            .build()
+            .unwrap();
+        let state_space = StateSpace::explore(&state_chart, &Bounds::default()).unwrap();
+        assert_eq!(4, state_space.len());
+        assert!(matches!(check(&state_space, &state_chart, "!Done W Doing"), Verdict::Holds));
+        assert!(matches!(check(&state_space, &state_chart, "E[Open U Cancelled]"), Verdict::Holds));
+        match check(&state_space, &state_chart, "!(EF Done)") {
+            Verdict::Violated(counter_example) => {
+                let steps: Vec<String> = counter_example.steps().iter().map(|(input, _)| input.to_string()).collect();
+                assert_eq!(vec!["send start", "send finish"], steps);
+            }
+            verdict => panic!("Expected a violation, but got {verdict:?}!"),
+        }
+        match check(&state_space, &state_chart, "Open -> AX Doing") {
+            Verdict::Violated(counter_example) => assert_eq!("send cancel", counter_example.steps()[0].0.to_string()),
+            verdict => panic!("Expected a violation, but got {verdict:?}!"),
+        }
+    }
+
+    #[test]
+    fn test_domain_limits() {
+        // The neighbours of the compared values don't exist at the limits of the integers.
+        let state_chart = Node::builder("Limits")
+            .attribute("amount", 0)
+            .start_node("Open")
+            .state(Node::builder("Open").transition(
+                Transition::when(PredicateCall::builder("greater").parameter("left", "smv:amount").parameter("right", i64::MIN)).to("Done"),
+            ))
+            .state(Node::builder("Done").transition(
+                Transition::when(PredicateCall::builder("equals").parameter("left", "smv:amount").parameter("right", i64::MAX)).to("Open"),
+            ))
+            .build()
+            .unwrap();
+        let sets = inputs(&state_chart).iter().filter(|input| matches!(input, Input::Set(..))).count();
+        assert_eq!(5, sets);
+        StateSpace::explore(&state_chart, &Bounds::default()).unwrap();
+    }
+
+    #[test]
+    fn test_parse() {
+        let state_chart = Node::read("tests/approval.json").unwrap();
+        for property in ["AG EF Approved", "A[Draft W In-Approval]", "(Checking|Escalated) -> X !Draft", "\"Approval/Draft\" U Approved"] {
+            Property::parse(property, &state_chart).unwrap();
+        }
+        for property in ["AG", "Unknown", "A[Draft]", "Draft U", "(Draft", "Draft Approved", "Draft # Approved"] {
+            assert!(matches!(Property::parse(property, &state_chart), Err(StateChartError::InvalidProperty(_))), "{property}");
+        }
+    }
+}
@@ -1,28 +1,47 @@
 use open_api_matcher::ValidatedValue;
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
+use rusqlite::{params, ToSql};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
 use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 
-use crate::error::StateChartError;
-use crate::sql::Crud;
-use crate::ids::NodeId;
-use crate::state_charts::{ActionCall, Transition, VariableDeclaration, get_mandatory};
+use crate::error::{Error, StateChartError};
+use crate::sql::{savepoint, Crud};
+use crate::ids::{EventId, NodeId};
+use crate::scxml;
+use crate::state_charts::{get_mandatory, ActionCall, CallBuilder, Transition, TransitionBuilder, VariableDeclaration, VariableValue};
+use crate::validation;
 
 /// The node is the heart of the state chart definition. A node can be a single state or a state
 /// chart of its own.
 /// The node will be saved in all details to the database. The objective is to make it easier to
 /// address the nodes in the context of the state machines operations.
+/// The node is serialized in the wire format of StateMachines.yml. The references are resolved,
+/// when the node is deserialized.
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", try_from = "NodeDefinition")]
 pub struct Node {
     id: NodeId,
+    #[serde(skip_serializing_if = "NodeKind::is_state")]
+    kind: NodeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     on_entry: Option<ActionCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     on_exit: Option<ActionCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     start_node: Option<NodeId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     out_transitions: Vec<Transition>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    deferred_events: Vec<EventId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     attributes: Vec<VariableDeclaration>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     nodes: Vec<Node>,
 }
 impl Node {
@@ -30,14 +49,291 @@ impl Node {
         &self.id
     }
 
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn on_entry(&self) -> Option<&ActionCall> {
+        self.on_entry.as_ref()
+    }
+
+    pub fn on_exit(&self) -> Option<&ActionCall> {
+        self.on_exit.as_ref()
+    }
+
     /// Provides access to the optional start node of the state chart.
     #[allow(dead_code)]
     pub fn start_node(&self) -> Option<&NodeId> {
         self.start_node.as_ref()
     }
+
+    pub fn out_transitions(&self) -> &Vec<Transition> {
+        &self.out_transitions
+    }
+
+    /// Provides the events, which are kept by the state machine while the node is active, until a
+    /// node is entered, which doesn't defer them.
+    pub fn deferred_events(&self) -> &Vec<EventId> {
+        &self.deferred_events
+    }
+
+    pub fn defers(&self, event: &EventId) -> bool {
+        self.deferred_events.contains(event)
+    }
+
+    pub fn attributes(&self) -> &Vec<VariableDeclaration> {
+        &self.attributes
+    }
+
+    pub fn nodes(&self) -> &Vec<Node> {
+        &self.nodes
+    }
+
+    /// Provides the chain of nodes from the receiver down to the node with the given id. The
+    /// receiver is the first and the requested node the last element of the chain.
+    pub fn path_to(&self, id: &NodeId) -> Option<Vec<&Node>> {
+        if &self.id == id {
+            return Some(vec![self]);
+        }
+        for node in &self.nodes {
+            if let Some(mut path) = node.path_to(id) {
+                path.insert(0, self);
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    /// Resolves the relative node and variable references of the receiver and all its sub-nodes.
+    /// The scopes hold the ids and variable names of all enclosing nodes, the innermost last.
+    fn resolve_references(&mut self, scopes: &mut Vec<(NodeId, Vec<String>)>) -> Result<(), StateChartError> {
+        let names = self.attributes.iter().map(|attribute| attribute.name().to_string()).collect();
+        scopes.push((self.id.clone(), names));
+        if let Some(start_node) = self.start_node.take() {
+            self.start_node = Some(self.id.resolve(start_node.as_str())?);
+        }
+        if let Some(on_entry) = &mut self.on_entry {
+            on_entry.resolve_references(scopes)?;
+        }
+        if let Some(on_exit) = &mut self.on_exit {
+            on_exit.resolve_references(scopes)?;
+        }
+        for transition in &mut self.out_transitions {
+            transition.resolve_references(&self.id, scopes)?;
+        }
+        for node in &mut self.nodes {
+            node.resolve_references(scopes)?;
+        }
+        scopes.pop();
+        Ok(())
+    }
+
+    /// Provides the receiver and the sub-nodes, which are entered with it by default: all regions
+    /// of a parallel node, otherwise the start node or the first sub-node, which isn't a pseudo
+    /// node.
+    pub fn default_entry(&self) -> Vec<&Node> {
+        let mut entered = vec![self];
+        let mut sub_nodes = self.nodes.iter().filter(|sub_node| !sub_node.kind.is_pseudo());
+        if self.kind == NodeKind::Parallel {
+            entered.extend(sub_nodes.flat_map(Node::default_entry));
+        } else if let Some(start_node) = self.start_node.as_ref().and_then(|start_node| self.find(start_node)) {
+            entered.extend(start_node.default_entry());
+        } else if let Some(sub_node) = sub_nodes.next() {
+            entered.extend(sub_node.default_entry());
+        }
+        entered
+    }
+
+    /// Finds the node with the given id in the hierarchy of the receiver.
+    pub fn find(&self, id: &NodeId) -> Option<&Node> {
+        self.path_to(id).and_then(|path| path.last().copied())
+    }
+
+    /// Reads the state chart from a JSON file or from an SCXML document (*.scxml). The semantic
+    /// of the state chart isn't validated.
+    pub fn read(path: impl AsRef<Path>) -> Result<Node, Error> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        if path.extension().is_some_and(|extension| extension == "scxml") {
+            Ok(scxml::from_scxml(&content)?)
+        } else {
+            Ok(serde_json::from_str(&content)?)
+        }
+    }
+
+    /// Starts a state chart or a sub-node in code. The name of a sub-node is relative to its
+    /// parent.
+    pub fn builder(name: &str) -> NodeBuilder {
+        NodeBuilder {
+            name: name.into(),
+            kind: NodeKind::default(),
+            description: None,
+            on_entry: None,
+            on_exit: None,
+            start_node: None,
+            out_transitions: Vec::new(),
+            deferred_events: Vec::new(),
+            attributes: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+/// The node as it is written. The references of the sub-nodes can only be resolved from the
+/// root, so they are deserialized as definitions too.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct NodeDefinition {
+    id: NodeId,
+    #[serde(default)]
+    kind: NodeKind,
+    description: Option<String>,
+    on_entry: Option<ActionCall>,
+    on_exit: Option<ActionCall>,
+    start_node: Option<NodeId>,
+    #[serde(default)]
+    out_transitions: Vec<Transition>,
+    #[serde(default)]
+    deferred_events: Vec<EventId>,
+    #[serde(default)]
+    attributes: Vec<VariableDeclaration>,
+    #[serde(default)]
+    nodes: Vec<NodeDefinition>,
+}
+impl NodeDefinition {
+    /// Provides the node with the references as they are written.
+    fn into_node(self) -> Node {
+        Node {
+            id: self.id,
+            kind: self.kind,
+            description: self.description,
+            on_entry: self.on_entry,
+            on_exit: self.on_exit,
+            start_node: self.start_node,
+            out_transitions: self.out_transitions,
+            deferred_events: self.deferred_events,
+            attributes: self.attributes,
+            nodes: self.nodes.into_iter().map(NodeDefinition::into_node).collect(),
+        }
+    }
+}
+impl TryFrom<NodeDefinition> for Node {
+    type Error = StateChartError;
+
+    fn try_from(definition: NodeDefinition) -> Result<Self, Self::Error> {
+        let mut node = definition.into_node();
+        node.resolve_references(&mut Vec::new())?;
+        Ok(node)
+    }
+}
+
+/// Builds a state chart in code. References to nodes and variables are relative, like in JSON.
+/// They are resolved and the state chart is validated, when it is built.
+#[derive(Clone, Debug)]
+pub struct NodeBuilder {
+    name: String,
+    kind: NodeKind,
+    description: Option<String>,
+    on_entry: Option<CallBuilder>,
+    on_exit: Option<CallBuilder>,
+    start_node: Option<String>,
+    out_transitions: Vec<TransitionBuilder>,
+    deferred_events: Vec<String>,
+    attributes: Vec<VariableDeclaration>,
+    nodes: Vec<NodeBuilder>,
+}
+impl NodeBuilder {
+    pub fn kind(mut self, kind: NodeKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn on_entry(mut self, action: CallBuilder) -> Self {
+        self.on_entry = Some(action);
+        self
+    }
+
+    pub fn on_exit(mut self, action: CallBuilder) -> Self {
+        self.on_exit = Some(action);
+        self
+    }
+
+    pub fn start_node(mut self, start_node: &str) -> Self {
+        self.start_node = Some(start_node.into());
+        self
+    }
+
+    pub fn transition(mut self, transition: TransitionBuilder) -> Self {
+        self.out_transitions.push(transition);
+        self
+    }
+
+    /// Defers the event, while the node is active. A bare name is an event of the sme:/// scheme.
+    pub fn defer(mut self, event: &str) -> Self {
+        self.deferred_events.push(event.into());
+        self
+    }
+
+    /// Declares a variable of the node. The type is the type of the initial value.
+    pub fn attribute(mut self, name: &str, value: impl Into<VariableValue>) -> Self {
+        self.attributes.push(VariableDeclaration::new(name, value.into()));
+        self
+    }
+
+    /// Adds the sub-node.
+    pub fn state(mut self, node: NodeBuilder) -> Self {
+        self.nodes.push(node);
+        self
+    }
+
+    /// Builds the state chart, resolves its references and validates it.
+    pub fn build(self) -> Result<Node, StateChartError> {
+        let id = if self.name.contains(':') { NodeId::from(self.name.as_str()) } else { NodeId::new(&self.name) };
+        let mut node = self.build_node(id)?;
+        node.resolve_references(&mut Vec::new())?;
+        validation::validate(&node)?;
+        Ok(node)
+    }
+
+    fn build_node(self, id: NodeId) -> Result<Node, StateChartError> {
+        let nodes = self
+            .nodes
+            .into_iter()
+            .map(|node| {
+                let sub_node_id = id.resolve(&node.name)?;
+                node.build_node(sub_node_id)
+            })
+            .collect::<Result<_, _>>()?;
+        let deferred_events = self
+            .deferred_events
+            .iter()
+            .map(|event| if event.contains(':') { event.parse() } else { format!("sme:///{event}").parse() })
+            .collect::<Result<_, _>>()?;
+        Ok(Node {
+            id,
+            kind: self.kind,
+            description: self.description,
+            on_entry: self.on_entry.map(CallBuilder::build_action_call).transpose()?,
+            on_exit: self.on_exit.map(CallBuilder::build_action_call).transpose()?,
+            start_node: self.start_node.as_deref().map(NodeId::from),
+            out_transitions: self.out_transitions.into_iter().map(TransitionBuilder::build).collect::<Result<_, _>>()?,
+            deferred_events,
+            attributes: self.attributes,
+            nodes,
+        })
+    }
 }
 impl Crud<SqliteConnectionManager, NodeId> for Node {
-    type Error = rusqlite::Error;
+    type Error = Error;
 
     /// This id differs from the id of the other CRUD objects. While the id is normally provided
     /// only by the database, this id is provided by the user and already available from the start,
@@ -53,18 +349,26 @@ impl Crud<SqliteConnectionManager, NodeId> for Node {
         debug!("[node::Crud::create()]");
         let sql = "CREATE TABLE IF NOT EXISTS Node (
                 id TEXT NOT NULL UNIQUE,
+                kind TEXT NOT NULL,
                 description TEXT,
-                on_entry TEXT,
-                on_exit TEXT,
-                start_node TEXT,
-                out_transitions TEXT,
-                attributes TEXT,
-                nodes TEXT
+                on_entry INTEGER,
+                on_exit INTEGER,
+                start_node TEXT
+            )";
+        connection.execute(sql, [])?;
+        let sql = "CREATE TABLE IF NOT EXISTS NodeTransitions (
+                node_id TEXT NOT NULL,
+                transition_id INTEGER NOT NULL
+            )";
+        connection.execute(sql, [])?;
+        let sql = "CREATE TABLE IF NOT EXISTS NodeDeferredEvents (
+                node_id TEXT NOT NULL,
+                event TEXT NOT NULL
             )";
         connection.execute(sql, [])?;
         let sql = "CREATE TABLE IF NOT EXISTS NodeAttributes (
                 node_id TEXT NOT NULL,
-                attribute_id TEXT NOT NULL
+                attribute_id INTEGER NOT NULL
             )";
         connection.execute(sql, [])?;
         let sql = "CREATE TABLE IF NOT EXISTS SubNodes (
@@ -79,41 +383,282 @@ impl Crud<SqliteConnectionManager, NodeId> for Node {
 
         Ok(())
     }
+
+    /// Inserts the node with all dependent objects and all sub-nodes. The transitions, deferred
+    /// events, attributes and sub-nodes are linked by the NodeTransitions, NodeDeferredEvents,
+    /// NodeAttributes and SubNodes tables. Nothing is inserted, if a part can't be inserted.
     fn insert(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<&NodeId, Self::Error>
     {
         debug!("[node::Crud::insert()]");
-        let sql = "INSERT INTO Node (
-                   id, description, on_entry, on_exit, start_node, out_transitions, attributes, nodes 
-                ) VALUES ( 
-                    ?, ?, ?, ?, ?, ?, ?, ?
-                )";
-        let mut statement = connection.prepare(sql)?;
-        let _rowid = statement.insert(params![]);
+        savepoint(connection, || self.insert_parts(connection))?;
         Ok(&self.id)
     }
-    fn update(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+
+    /// Replaces the stored node with all dependent objects and all sub-nodes by the receiver. The
+    /// stored node is kept, if the receiver can't be inserted.
+    fn update(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
         debug!("[node::Crud::update()]");
-        todo!()
+        savepoint(connection, || {
+            self.delete(connection)?;
+            self.clone().insert(connection)?;
+            Ok(())
+        })
     }
-    fn delete(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+
+    /// Deletes the node with all dependent objects and all sub-nodes. The dependent objects are
+    /// taken from the stored node, as only they carry the ids of the database.
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
         debug!("[node::Crud::delete()]");
-        todo!()
+        let stored = Node::select(connection, &self.id)?.ok_or(Error::NotStored("Node"))?;
+        for action_call in stored.on_entry.iter().chain(&stored.on_exit) {
+            action_call.delete(connection)?;
+        }
+        for transition in &stored.out_transitions {
+            transition.delete(connection)?;
+        }
+        for attribute in &stored.attributes {
+            attribute.delete(connection)?;
+        }
+        for node in &stored.nodes {
+            node.delete(connection)?;
+        }
+        for (table, column) in [
+            ("NodeTransitions", "node_id"),
+            ("NodeDeferredEvents", "node_id"),
+            ("NodeAttributes", "node_id"),
+            ("SubNodes", "parent_node"),
+            ("SubNodes", "child_node"),
+            ("Node", "id"),
+        ] {
+            connection.execute(&format!("DELETE FROM {table} WHERE {column} = ?"), params![self.id])?;
+        }
+        Ok(())
     }
-    fn select(_connection: &PooledConnection<SqliteConnectionManager>, _key_value: &NodeId) -> Result<Option<Self>, Self::Error>
+
+    /// Selects the node with all dependent objects and all sub-nodes.
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, key_value: &NodeId) -> Result<Option<Self>, Self::Error>
     where
         Self: Sized
     {
         debug!("[node::Crud::select()]");
-        todo!()
+        let sql = "SELECT id, kind, description, on_entry, on_exit, start_node FROM Node WHERE id = ?";
+        let mut statement = connection.prepare(sql)?;
+        let mut rows = statement.query(params![key_value])?;
+        if let Some(row) = rows.next()? {
+            let on_entry_id: Option<i64> = row.get(3)?;
+            let on_entry = match on_entry_id {
+                Some(id) => ActionCall::select(connection, &id)?,
+                None => None,
+            };
+            let on_exit_id: Option<i64> = row.get(4)?;
+            let on_exit = match on_exit_id {
+                Some(id) => ActionCall::select(connection, &id)?,
+                None => None,
+            };
+
+            let sql = "SELECT transition_id FROM NodeTransitions WHERE node_id = ? ORDER BY rowid";
+            let mut out_transitions = Vec::new();
+            for transition_id in select_links::<i64>(connection, sql, key_value)? {
+                if let Some(transition) = Transition::select(connection, &transition_id)? {
+                    out_transitions.push(transition);
+                }
+            }
+            let sql = "SELECT event FROM NodeDeferredEvents WHERE node_id = ? ORDER BY rowid";
+            let deferred_events = select_links::<EventId>(connection, sql, key_value)?;
+            let sql = "SELECT attribute_id FROM NodeAttributes WHERE node_id = ? ORDER BY rowid";
+            let mut attributes = Vec::new();
+            for attribute_id in select_links::<i64>(connection, sql, key_value)? {
+                if let Some(attribute) = VariableDeclaration::select(connection, &attribute_id)? {
+                    attributes.push(attribute);
+                }
+            }
+            let sql = "SELECT child_node FROM SubNodes WHERE parent_node = ? ORDER BY rowid";
+            let mut nodes = Vec::new();
+            for child_id in select_links::<NodeId>(connection, sql, key_value)? {
+                if let Some(node) = Node::select(connection, &child_id)? {
+                    nodes.push(node);
+                }
+            }
+
+            Ok(Some(Node {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                description: row.get(2)?,
+                on_entry,
+                on_exit,
+                start_node: row.get(5)?,
+                out_transitions,
+                deferred_events,
+                attributes,
+                nodes,
+            }))
+        } else {
+            Ok(None)
+        }
     }
 }
+
+impl Node {
+    /// Inserts the node with its parts, which is done in the savepoint of insert.
+    fn insert_parts(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Error> {
+        let on_entry = match &mut self.on_entry {
+            Some(action_call) => Some(*action_call.insert(connection)?),
+            None => None,
+        };
+        let on_exit = match &mut self.on_exit {
+            Some(action_call) => Some(*action_call.insert(connection)?),
+            None => None,
+        };
+        let sql = "INSERT INTO Node (
+                   id, kind, description, on_entry, on_exit, start_node
+                ) VALUES (
+                    ?, ?, ?, ?, ?, ?
+                )";
+        let mut statement = connection.prepare(sql)?;
+        statement.insert(params![self.id, self.kind, self.description, on_entry, on_exit, self.start_node])?;
+
+        let sql = "INSERT INTO NodeTransitions ( node_id, transition_id ) VALUES ( ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for transition in &mut self.out_transitions {
+            let transition_id = transition.insert(connection)?;
+            statement.insert(params![self.id, transition_id])?;
+        }
+        let sql = "INSERT INTO NodeDeferredEvents ( node_id, event ) VALUES ( ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for event in &self.deferred_events {
+            statement.insert(params![self.id, event])?;
+        }
+        let sql = "INSERT INTO NodeAttributes ( node_id, attribute_id ) VALUES ( ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for attribute in &mut self.attributes {
+            let attribute_id = attribute.insert(connection)?;
+            statement.insert(params![self.id, attribute_id])?;
+        }
+        let sql = "INSERT INTO SubNodes ( parent_node, child_node ) VALUES ( ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for node in &mut self.nodes {
+            let child_id = node.insert(connection)?;
+            statement.insert(params![self.id, child_id])?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of a node. The sub-nodes of a state are alternatives, of which only one is active,
+/// while all sub-nodes of a parallel node are active at the same time as orthogonal regions. A
+/// final node completes its parent.
+/// Choices, junctions and history nodes are pseudo nodes, which are never active. They only
+/// select the target of a transition. A junction is evaluated before the transition is taken, a
+/// choice after the action of the transition ran. A history node selects the sub-nodes of its
+/// parent, which were active, when the parent was left the last time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeKind {
+    #[default]
+    State,
+    Parallel,
+    Final,
+    Choice,
+    Junction,
+    History,
+    DeepHistory,
+}
+impl NodeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::State => "state",
+            Self::Parallel => "parallel",
+            Self::Final => "final",
+            Self::Choice => "choice",
+            Self::Junction => "junction",
+            Self::History => "history",
+            Self::DeepHistory => "deep-history",
+        }
+    }
+
+    pub fn is_state(&self) -> bool {
+        *self == Self::State
+    }
+
+    pub fn is_pseudo(&self) -> bool {
+        matches!(self, Self::Choice | Self::Junction | Self::History | Self::DeepHistory)
+    }
+
+    pub fn is_history(&self) -> bool {
+        matches!(self, Self::History | Self::DeepHistory)
+    }
+}
+
+impl TryFrom<&str> for NodeKind {
+    type Error = StateChartError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "state" => Ok(Self::State),
+            "parallel" => Ok(Self::Parallel),
+            "final" => Ok(Self::Final),
+            "choice" => Ok(Self::Choice),
+            "junction" => Ok(Self::Junction),
+            "history" => Ok(Self::History),
+            "deep-history" => Ok(Self::DeepHistory),
+            _ => Err(StateChartError::UnexpectedType),
+        }
+    }
+}
+
+impl TryFrom<&ValidatedValue> for NodeKind {
+    type Error = StateChartError;
+
+    fn try_from(value: &ValidatedValue) -> Result<Self, Self::Error> {
+        if let ValidatedValue::String(kind) = value {
+            kind.as_str().try_into()
+        } else {
+            Err(StateChartError::UnexpectedType)
+        }
+    }
+}
+
+impl ToSql for NodeKind {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error> {
+        self.as_str().to_sql()
+    }
+}
+
+impl FromSql for NodeKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str()?.try_into().map_err(|_| FromSqlError::InvalidType)
+    }
+}
+
+/// Selects the ids of the objects linked to a node by one of the link tables.
+fn select_links<T: FromSql>(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    sql: &str,
+    node_id: &NodeId,
+) -> Result<Vec<T>, rusqlite::Error> {
+    let mut statement = connection.prepare(sql)?;
+    let links = statement.query_map(params![node_id], |row| row.get(0))?;
+    links.collect()
+}
+
 /// Constructs a node from the validated value.
 impl TryFrom<&ValidatedValue> for Node {
     type Error = StateChartError;
 
+    /// Parses the root node of a state chart and resolves all relative references in it.
     fn try_from(value: &ValidatedValue) -> Result<Self, Self::Error> {
+        let mut node = Node::parse(value)?;
+        node.resolve_references(&mut Vec::new())?;
+        Ok(node)
+    }
+}
+
+impl Node {
+    /// Parses the node and its sub-nodes. References are kept as they are written in the
+    /// definition, as the scopes of the enclosing nodes aren't known yet.
+    fn parse(value: &ValidatedValue) -> Result<Self, StateChartError> {
         if let ValidatedValue::Object(attributes) = value {
             let on_entry = match attributes.get("on-entry") {
                 Some(vac) => {
@@ -136,6 +681,10 @@ impl TryFrom<&ValidatedValue> for Node {
                 }
                 None => None,
             };
+            let kind = match attributes.get("kind") {
+                Some(kind) => kind.try_into()?,
+                None => NodeKind::default(),
+            };
             let start_node: Option<NodeId> = match attributes.get("start-node") {
                 Some(sn) => Some(sn.try_into()?),
                 None => None,
@@ -144,13 +693,19 @@ impl TryFrom<&ValidatedValue> for Node {
                 Some(ot) => transitions_from_validated_value(ot)?,
                 None => Vec::new(),
             };
+            let deferred_events = match attributes.get("deferred-events") {
+                Some(de) => events_from_validated_value(de)?,
+                None => Vec::new(),
+            };
             Ok(Node {
                 id: get_mandatory(attributes, "id")?.try_into()?,
+                kind,
                 description,
                 on_entry,
                 on_exit,
                 start_node,
                 out_transitions,
+                deferred_events,
                 attributes: attributes_from_validated_value(attributes.get("attributes"))?,
                 nodes: nodes_from_validated_value(attributes.get("nodes"))?,
             })
@@ -176,6 +731,15 @@ fn transitions_from_validated_value(
     }
 }
 
+/// Retrieves the deferred events of a node from the array.
+fn events_from_validated_value(value: &ValidatedValue) -> Result<Vec<EventId>, StateChartError> {
+    if let ValidatedValue::Array(events) = value {
+        events.iter().map(|event| event.try_into()).collect()
+    } else {
+        Err(StateChartError::UnexpectedType)
+    }
+}
+
 /// Retrieves the attributes/variables from the array.
 fn attributes_from_validated_value(
     value: Option<&ValidatedValue>,
@@ -203,7 +767,7 @@ fn nodes_from_validated_value(
         if let ValidatedValue::Array(nodes) = v_value {
             let mut result: Vec<Node> = Vec::new();
             for v_node in nodes {
-                let node = v_node.try_into()?;
+                let node = Node::parse(v_node)?;
                 result.push(node);
             }
             Ok(result)
@@ -219,7 +783,7 @@ fn nodes_from_validated_value(
 mod tests {
     use super::*;
     use std::collections::BTreeMap;
-    use crate::state_charts::VariableValue;
+    use crate::state_charts::{Guard, PredicateCall};
     use open_api_matcher::OpenApi;
     use r2d2::Pool;
 
@@ -236,6 +800,21 @@ mod tests {
         assert_eq!(3, node.nodes.len());
     }
 
+    #[test]
+    fn test_read_sc_from_json() {
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string("tests/approval.json").unwrap()).unwrap();
+        let node = serde_json::from_value::<Node>(json).unwrap();
+        assert_eq!(read_state_chart("tests/approval.json").nodes().len(), node.nodes().len());
+        assert_eq!(Some(&NodeId::new("Approval/Draft")), node.start_node());
+        assert!(serde_json::from_value::<Node>(serde_json::json!({ "id": 42 })).is_err());
+        let misspelled = serde_json::json!({
+            "id": "scn:///Misspelled",
+            "start-node": "Open",
+            "nodes": [ { "id": "scn:///Misspelled/Open", "transitions": [] } ]
+        });
+        assert!(serde_json::from_value::<Node>(misspelled).is_err());
+    }
+
     #[test]
     fn test_attributes_from_vv() {
         // Create a list of ValidatedValues with objects with the attributes of the
@@ -259,15 +838,270 @@ mod tests {
         // let _vd1 = VariableDeclaration::new("var1", "integer", VariableValue::Integer(1));
     }
 
+    #[test]
+    fn test_builder() {
+        let increment = |variable: &str| ActionCall::builder("increment").parameter("variable", variable);
+        let built = Node::builder("Tally-Task")
+            .description("A tally, which counts the clicks without leaving its state.")
+            .start_node("Counting")
+            .attribute("entries", 0)
+            .attribute("clicks", 0)
+            .state(
+                Node::builder("Counting")
+                    .on_entry(increment("smv:entries"))
+                    .transition(Transition::on("click").action(increment("smv:clicks")))
+                    .transition(Transition::on("sme:///stop").to("Stopped")),
+            )
+            .state(Node::builder("Stopped"))
+            .build()
+            .unwrap();
+        assert_eq!(format!("{:?}", read_state_chart("tests/tally-task.json")), format!("{built:?}"));
+
+        let approval = Node::builder("Approval")
+            .start_node("Draft")
+            .attribute("amount", 0.0)
+            .state(Node::builder("Draft").transition(
+                Transition::when(PredicateCall::builder("greater").parameter("left", "smv:amount").parameter("right", 0))
+                    .to("Approved"),
+            ))
+            .state(Node::builder("Approved"))
+            .build()
+            .unwrap();
+        let draft = approval.find(&NodeId::new("Approval/Draft")).unwrap();
+        assert_eq!(Some(&NodeId::new("Approval/Approved")), draft.out_transitions()[0].to());
+        match draft.out_transitions()[0].guard() {
+            Guard::Predicate(predicate_call) => assert_eq!(
+                &VariableValue::Reference("smv:///Approval/amount".parse().unwrap()),
+                predicate_call.parameters()[0].value()
+            ),
+            _ => panic!("Expected a predicate!"),
+        }
+
+        let unknown_target = Node::builder("Broken")
+            .start_node("Open")
+            .state(Node::builder("Open").transition(Transition::on("close").to("Closed")))
+            .build();
+        assert!(matches!(unknown_target, Err(StateChartError::UnknownNode(_))));
+        let invalid_action = Node::builder("Broken")
+            .start_node("Open")
+            .state(Node::builder("Open").on_entry(ActionCall::builder("no action")))
+            .build();
+        assert!(matches!(invalid_action, Err(StateChartError::InvalidActionId(_))));
+    }
+
+    #[test]
+    fn test_serde() {
+        // Relative references are resolved, like in the validated value.
+        let json = std::fs::read_to_string("tests/approval.json").unwrap();
+        let approval: Node = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", read_state_chart("tests/approval.json")), format!("{approval:?}"));
+        let serialized = serde_json::to_string(&approval).unwrap();
+        assert!(serialized.contains(r#""start-node":"scn:///Approval/Draft""#));
+        assert!(serialized.contains(r#""out-transitions":[{"guard":{"name":"greater""#));
+        assert!(!serialized.contains("kind"));
+
+        let tally = read_state_chart("tests/tally-task.json");
+        assert!(serde_json::to_string(&tally).unwrap().contains(r#""on-entry":{"name":"increment""#));
+
+        let document = std::fs::read_to_string("tests/media-player.scxml").unwrap();
+        let media_player = crate::scxml::from_scxml(&document).unwrap();
+        for node in [approval, tally, media_player] {
+            let expected = format!("{node:?}");
+            let json: Node = serde_json::from_str(&serde_json::to_string_pretty(&node).unwrap()).unwrap();
+            assert_eq!(expected, format!("{json:?}"));
+            let yaml: Node = serde_yaml::from_str(&serde_yaml::to_string(&node).unwrap()).unwrap();
+            assert_eq!(expected, format!("{yaml:?}"));
+            let toml: Node = toml::from_str(&toml::to_string(&node).unwrap()).unwrap();
+            assert_eq!(expected, format!("{toml:?}"));
+        }
+
+        let unknown_scope = r#"{ "id": "scn:///Broken", "start-node": "../Outside" }"#;
+        assert!(serde_json::from_str::<Node>(unknown_scope).is_err());
+
+        // A misspelled key is rejected instead of being dropped.
+        for misspelled in [
+            r#"{ "id": "scn:///Task", "transitions": [] }"#,
+            r#"{ "id": "scn:///Task", "out-transitions": [{ "guard": "sme:///start", "target": "Task" }] }"#,
+            r#"{ "id": "scn:///Task", "on-entry": { "name": "log", "params": [] } }"#,
+            r#"{ "id": "scn:///Task", "attributes": [{ "name": "a", "type": "integer", "value": 1, "scope": "x" }] }"#,
+        ] {
+            let err = serde_json::from_str::<Node>(misspelled).unwrap_err();
+            assert!(err.to_string().contains("unknown field"), "{misspelled}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_read() {
+        let approval = Node::read("tests/approval.json").unwrap();
+        assert_eq!(format!("{:?}", read_state_chart("tests/approval.json")), format!("{approval:?}"));
+        assert_eq!("scn:///Media-Player", Node::read("tests/media-player.scxml").unwrap().id().to_string());
+        assert!(matches!(Node::read("tests/missing.json"), Err(Error::Io(_))));
+        assert!(matches!(Node::read("tests/scenarios/approval.json"), Err(Error::Serialization(_))));
+    }
+
     fn create_db_connection() -> PooledConnection<SqliteConnectionManager> {
         let manager = r2d2_sqlite::SqliteConnectionManager::memory();
         let pool = Pool::builder().max_size(10).build(manager).unwrap();
         pool.get().unwrap()
     }
 
+    fn read_state_chart(file_name: &str) -> Node {
+        let open_api_file = std::fs::File::open("StateMachines.yml").unwrap();
+        let open_api = OpenApi::new(&open_api_file).unwrap();
+        let sc = std::fs::read_to_string(file_name).unwrap();
+        let sc_schema = open_api.get_schema("#/components/schemas/Node").unwrap();
+        let vvsc = ValidatedValue::new(&sc, &sc_schema, &open_api).unwrap();
+        (&vvsc).try_into().unwrap()
+    }
+
+    #[test]
+    fn test_path_to() {
+        let node = read_state_chart("tests/review-task.json");
+        let path = node.path_to(&NodeId::new("Review-Task/Escalated")).unwrap();
+        assert_eq!(2, path.len());
+        assert_eq!(&NodeId::new("Review-Task"), path[0].id());
+        assert_eq!(&NodeId::new("Review-Task/Escalated"), path[1].id());
+        assert!(node.find(&NodeId::new("Review-Task/Unknown")).is_none());
+    }
+
+    #[test]
+    fn test_resolve_references() {
+        let node = read_state_chart("tests/approval.json");
+        assert_eq!(Some(&NodeId::new("Approval/Draft")), node.start_node());
+        let draft = node.find(&NodeId::new("Approval/Draft")).unwrap();
+        assert_eq!(Some(&NodeId::new("Approval/In-Approval")), draft.out_transitions()[0].to());
+        let in_approval = node.find(&NodeId::new("Approval/In-Approval")).unwrap();
+        assert_eq!(Some(&NodeId::new("Approval/In-Approval/Checking")), in_approval.start_node());
+        assert_eq!(Some(&NodeId::new("Approval/Draft")), in_approval.out_transitions()[0].to());
+
+        let checking = node.find(&NodeId::new("Approval/In-Approval/Checking")).unwrap();
+        let transitions = checking.out_transitions();
+        assert_eq!(Some(&NodeId::new("Approval/In-Approval/Escalated")), transitions[0].to());
+        assert_eq!(Some(&NodeId::new("Approval/Approved")), transitions[1].to());
+        let parameter_value = |guard: &Guard| match guard {
+            Guard::Predicate(predicate_call) => predicate_call.parameters()[0].value().clone(),
+            _ => panic!("Expected a predicate!"),
+        };
+        assert_eq!(VariableValue::Reference("smv:///Approval/amount".parse().unwrap()), parameter_value(transitions[0].guard()));
+        assert_eq!(
+            VariableValue::Reference("smv:///Approval/In-Approval/approved".parse().unwrap()),
+            parameter_value(transitions[1].guard())
+        );
+        let escalated = node.find(&NodeId::new("Approval/In-Approval/Escalated")).unwrap();
+        assert_eq!(
+            VariableValue::Reference("smv:///Approval/In-Approval/approved".parse().unwrap()),
+            parameter_value(escalated.out_transitions()[0].guard())
+        );
+    }
+
     #[test]
     fn test_node_crud() {
         let connection = create_db_connection();
         Node::create(&connection).unwrap();
+        let mut node = read_state_chart("tests/review-task.json");
+        node.insert(&connection).unwrap();
+        let selected = Node::select(&connection, node.id()).unwrap().unwrap();
+        assert_eq!(node.id(), selected.id());
+        assert_eq!(node.start_node(), selected.start_node());
+        assert_eq!(node.nodes().len(), selected.nodes().len());
+        assert_eq!(node.attributes()[0].value(), selected.attributes()[0].value());
+        let escalated = selected.find(&NodeId::new("Review-Task/Escalated")).unwrap();
+        assert_eq!(1, escalated.out_transitions().len());
+        assert_eq!(Some(&NodeId::new("Review-Task/Done")), escalated.out_transitions()[0].to());
+        assert!(Node::select(&connection, &NodeId::new("Unknown")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_pseudo_node_crud() {
+        let connection = create_db_connection();
+        Node::create(&connection).unwrap();
+        let mut node = read_state_chart("tests/order-task.json");
+        node.insert(&connection).unwrap();
+        let selected = Node::select(&connection, node.id()).unwrap().unwrap();
+        assert_eq!(NodeKind::State, selected.kind());
+        let check = selected.find(&NodeId::new("Order-Task/Check")).unwrap();
+        assert_eq!(NodeKind::Choice, check.kind());
+        assert!(matches!(check.out_transitions()[1].guard(), Guard::Else));
+        let route = selected.find(&NodeId::new("Order-Task/Route")).unwrap();
+        assert_eq!(NodeKind::Junction, route.kind());
+    }
+
+    #[test]
+    fn test_node_update_delete() {
+        let connection = create_db_connection();
+        Node::create(&connection).unwrap();
+        let mut node = read_state_chart("tests/review-task.json");
+        let mut other = read_state_chart("tests/simple-task.json");
+        node.insert(&connection).unwrap();
+        other.insert(&connection).unwrap();
+
+        node.description = Some("Reviewed twice".into());
+        node.nodes.pop();
+        node.update(&connection).unwrap();
+        let selected = Node::select(&connection, node.id()).unwrap().unwrap();
+        assert_eq!(Some("Reviewed twice"), selected.description.as_deref());
+        assert_eq!(node.nodes().len(), selected.nodes().len());
+
+        node.delete(&connection).unwrap();
+        assert!(Node::select(&connection, node.id()).unwrap().is_none());
+        for table in ["Node", "Transition", "VariableDeclaration", "NodeTransitions", "SubNodes"] {
+            let rows: i64 = connection.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0)).unwrap();
+            let expected = match table {
+                "Node" => 1 + other.nodes().len() as i64,
+                "Transition" | "NodeTransitions" => other.nodes().iter().map(|node| node.out_transitions().len() as i64).sum(),
+                "SubNodes" => other.nodes().len() as i64,
+                _ => 0,
+            };
+            assert_eq!(expected, rows, "{table}");
+        }
+        assert!(Node::select(&connection, other.id()).unwrap().is_some());
+        assert!(matches!(node.delete(&connection), Err(Error::NotStored("Node"))));
+    }
+
+    #[test]
+    fn test_node_update_rollback() {
+        let connection = create_db_connection();
+        Node::create(&connection).unwrap();
+        let mut node = read_state_chart("tests/review-task.json");
+        let mut other = read_state_chart("tests/simple-task.json");
+        node.insert(&connection).unwrap();
+        other.insert(&connection).unwrap();
+
+        // The sub-node is already stored as part of the other state chart, so the insert fails.
+        let mut changed = node.clone();
+        changed.description = Some("Changed".into());
+        changed.nodes.push(other.nodes()[0].clone());
+        assert!(matches!(changed.update(&connection), Err(Error::Database(_))));
+        let selected = Node::select(&connection, node.id()).unwrap().unwrap();
+        assert_eq!(node.description, selected.description);
+        assert_eq!(node.nodes().len(), selected.nodes().len());
+        assert_eq!(node.nodes()[0].out_transitions().len(), selected.nodes()[0].out_transitions().len());
+
+        // The update is part of the transaction of the caller, like the save of a delivery.
+        let transaction = connection.unchecked_transaction().unwrap();
+        changed.nodes.pop();
+        changed.update(&connection).unwrap();
+        transaction.rollback().unwrap();
+        assert_eq!(node.description, Node::select(&connection, node.id()).unwrap().unwrap().description);
+    }
+
+    #[test]
+    fn test_node_insert_rollback() {
+        let connection = create_db_connection();
+        Node::create(&connection).unwrap();
+        let state_chart = Node::builder("Entry")
+            .on_entry(ActionCall::builder("raise").parameter("event", "sme:///entered"))
+            .start_node("Open")
+            .state(Node::builder("Open"))
+            .build()
+            .unwrap();
+        state_chart.clone().insert(&connection).unwrap();
+
+        // The actions of the root are inserted before the node, which is already stored.
+        let count = |table: &str| -> i64 { connection.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0)).unwrap() };
+        let action_calls = count("ActionCall");
+        assert!(matches!(state_chart.clone().insert(&connection), Err(Error::Database(_))));
+        assert_eq!(action_calls, count("ActionCall"));
+        assert_eq!(2, count("Node"));
     }
 }
@@ -0,0 +1,95 @@
+use crate::error::StateChartError;
+use crate::state_charts::VariableValue;
+use std::cmp::Ordering;
+
+/// Evaluates the predicate with the given name on the already resolved arguments. The predicates
+/// are provided by the service and cannot be defined by a state chart.
+pub fn evaluate(name: &str, arguments: &[VariableValue]) -> Result<bool, StateChartError> {
+    match name {
+        "equals" => Ok(compare(name, arguments)? == Ordering::Equal),
+        "not-equals" => Ok(compare(name, arguments)? != Ordering::Equal),
+        "greater" => Ok(compare(name, arguments)? == Ordering::Greater),
+        "greater-or-equals" => Ok(compare(name, arguments)? != Ordering::Less),
+        "lesser" => Ok(compare(name, arguments)? == Ordering::Less),
+        "lesser-or-equals" => Ok(compare(name, arguments)? != Ordering::Greater),
+        "is-true" => Ok(boolean(name, arguments)?),
+        "is-false" => Ok(!boolean(name, arguments)?),
+        _ => Err(StateChartError::UnknownPredicate(name.into())),
+    }
+}
+
+/// Provides the name of the predicate, which is true exactly when the given predicate is false
+/// for the same arguments.
+pub fn negation(name: &str) -> Option<&'static str> {
+    match name {
+        "equals" => Some("not-equals"),
+        "not-equals" => Some("equals"),
+        "greater" => Some("lesser-or-equals"),
+        "lesser-or-equals" => Some("greater"),
+        "lesser" => Some("greater-or-equals"),
+        "greater-or-equals" => Some("lesser"),
+        "is-true" => Some("is-false"),
+        "is-false" => Some("is-true"),
+        _ => None,
+    }
+}
+
+/// Compares the two arguments of a binary predicate. Integers and numbers can be compared with
+/// each other. All other values can only be compared with a value of the same type.
+fn compare(name: &str, arguments: &[VariableValue]) -> Result<Ordering, StateChartError> {
+    let ordering = match arguments {
+        [VariableValue::Integer(a), VariableValue::Integer(b)] => Some(a.cmp(b)),
+        [VariableValue::Integer(a), VariableValue::Number(b)] => (*a as f64).partial_cmp(b),
+        [VariableValue::Number(a), VariableValue::Integer(b)] => a.partial_cmp(&(*b as f64)),
+        [VariableValue::Number(a), VariableValue::Number(b)] => a.partial_cmp(b),
+        [VariableValue::String(a), VariableValue::String(b)] => Some(a.cmp(b)),
+        [VariableValue::Boolean(a), VariableValue::Boolean(b)] => Some(a.cmp(b)),
+        _ => None,
+    };
+    ordering.ok_or_else(|| StateChartError::InvalidPredicateArguments(name.into()))
+}
+
+/// Provides the single boolean argument of an unary predicate.
+fn boolean(name: &str, arguments: &[VariableValue]) -> Result<bool, StateChartError> {
+    if let [VariableValue::Boolean(b)] = arguments {
+        Ok(*b)
+    } else {
+        Err(StateChartError::InvalidPredicateArguments(name.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_predicates() {
+        let one = VariableValue::Integer(1);
+        let two = VariableValue::Number(2.0);
+        assert!(evaluate("lesser", &[one.clone(), two.clone()]).unwrap());
+        assert!(evaluate("greater", &[two.clone(), one.clone()]).unwrap());
+        assert!(evaluate("equals", &[two.clone(), VariableValue::Integer(2)]).unwrap());
+        assert!(evaluate("not-equals", &[one.clone(), two.clone()]).unwrap());
+        assert!(evaluate("lesser-or-equals", &[one.clone(), one.clone()]).unwrap());
+        evaluate("equals", &[one.clone(), VariableValue::String("1".into())]).unwrap_err();
+        evaluate("equals", &[one]).unwrap_err();
+    }
+
+    #[test]
+    fn test_boolean_predicates() {
+        assert!(evaluate("is-true", &[VariableValue::Boolean(true)]).unwrap());
+        assert!(evaluate("is-false", &[VariableValue::Boolean(false)]).unwrap());
+        evaluate("is-true", &[VariableValue::Integer(1)]).unwrap_err();
+        evaluate("unknown", &[]).unwrap_err();
+    }
+
+    #[test]
+    fn test_negation() {
+        let arguments = [VariableValue::Integer(1), VariableValue::Integer(2)];
+        for name in ["equals", "not-equals", "greater", "greater-or-equals", "lesser", "lesser-or-equals"] {
+            let negation = negation(name).unwrap();
+            assert_ne!(evaluate(name, &arguments).unwrap(), evaluate(negation, &arguments).unwrap());
+        }
+        assert_eq!(None, negation("unknown"));
+    }
+}
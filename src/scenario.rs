@@ -0,0 +1,321 @@
+//! Scenarios test a state chart with a sequence of steps and the expected outcome of each step.
+//! A scenario is a JSON file, which references the state chart relative to its own location:
+//!
+//! ```json
+//! {
+//!     "chart": "../tally-task.json",
+//!     "variables": { "Tally-Task/clicks": 10 },
+//!     "expect": { "states": ["Tally-Task/Counting"], "actions": ["increment"] },
+//!     "steps": [
+//!         { "send": "click", "expect": { "variables": { "Tally-Task/clicks": 11 } } },
+//!         { "set": { "Tally-Task/entries": 0 }, "expect": { "states": ["Tally-Task/Counting"] } }
+//!     ]
+//! }
+//! ```
+//!
+//! The state machine is started and the initial variables are set, before the first expectation
+//! is checked. Every step either sends an event or sets variables. The expected
+//! states are the active states without active sub-nodes, given as paths of the nodes, as the
+//! state ids contain the random id of the machine. The expected variables are addressed by the
+//! path of the declaring node and their name. The expected actions are the names of all actions,
+//! which were executed by the step. Everything, which isn't expected, isn't checked.
+//!
+//! Advancing the clock with "advance" is rejected, until the state charts have timed transitions.
+use open_api_matcher::ValidatedValue;
+use serde::{de, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::error::{Error, StateChartError};
+use crate::ids::{EventId, NodeId, VariableId};
+use crate::node::Node;
+use crate::state_charts::VariableValue;
+use crate::state_machine::StateMachine;
+use crate::validation;
+
+/// A scenario fails, if it can't be read or run, or if an expectation isn't met.
+#[derive(Error, Debug)]
+pub enum ScenarioError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    StateChart(#[from] StateChartError),
+    #[error("{0}")]
+    Read(#[from] Error),
+    #[error("Step {step} ({action}): {message}")]
+    Mismatch { step: usize, action: String, message: String },
+}
+
+/// The state chart, the initial variables and the steps of a scenario.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Scenario {
+    chart: PathBuf,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    variables: BTreeMap<String, VariableValue>,
+    #[serde(default)]
+    expect: Expectation,
+    #[serde(default)]
+    steps: Vec<Step>,
+}
+
+/// A step of a scenario with the expected outcome.
+#[derive(Deserialize, Debug)]
+#[serde(try_from = "StepDefinition")]
+pub struct Step {
+    action: StepAction,
+    expect: Expectation,
+}
+
+/// The step as it is written. A flattened action can't reject unknown keys, so every action is
+/// an optional key and exactly one of them must be given. The key "advance" is known, so that
+/// it is rejected explicitly.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct StepDefinition {
+    #[serde(default)]
+    send: Option<String>,
+    #[serde(default)]
+    set: Option<BTreeMap<String, VariableValue>>,
+    #[serde(default)]
+    advance: Option<de::IgnoredAny>,
+    #[serde(default)]
+    expect: Expectation,
+}
+
+impl TryFrom<StepDefinition> for Step {
+    type Error = String;
+
+    fn try_from(definition: StepDefinition) -> Result<Self, Self::Error> {
+        if definition.advance.is_some() {
+            return Err("Advancing the clock isn't supported, as state charts have no timed transitions.".into());
+        }
+        let action = match (definition.send, definition.set) {
+            (Some(event), None) => StepAction::Send(event),
+            (None, Some(variables)) => StepAction::Set(variables),
+            _ => return Err("A step needs exactly one of 'send' or 'set'.".into()),
+        };
+        Ok(Step { action, expect: definition.expect })
+    }
+}
+
+/// Sends the event given by name or id or sets the variables.
+#[derive(Debug)]
+pub enum StepAction {
+    Send(String),
+    Set(BTreeMap<String, VariableValue>),
+}
+impl fmt::Display for StepAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Send(event) => write!(f, "send {event}"),
+            Self::Set(variables) => write!(f, "set {}", variables.keys().cloned().collect::<Vec<String>>().join(", ")),
+        }
+    }
+}
+
+/// The state machine at the end of a scenario.
+pub struct Outcome {
+    pub state_machine: StateMachine,
+}
+
+/// The expected active states, values of variables and executed actions after a step.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Expectation {
+    #[serde(default)]
+    states: Option<Vec<String>>,
+    #[serde(default)]
+    variables: BTreeMap<String, VariableValue>,
+    #[serde(default)]
+    actions: Option<Vec<String>>,
+}
+
+impl Scenario {
+    /// Reads the scenario from the JSON file.
+    pub fn read(path: &Path) -> Result<Scenario, ScenarioError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn steps(&self) -> &Vec<Step> {
+        &self.steps
+    }
+
+    /// Runs the scenario against a new state machine of the state chart, which is read relative
+    /// to the base directory. The scenario stops at the first step, which doesn't meet the
+    /// expectation.
+    pub fn run(&self, base_dir: &Path) -> Result<Outcome, ScenarioError> {
+        let state_chart = Node::read(base_dir.join(&self.chart))?;
+        validation::validate(&state_chart)?;
+        let mut state_machine = StateMachine::new(state_chart)?;
+        set_variables(&mut state_machine, &self.variables)?;
+        check(&mut state_machine, &self.expect).map_err(|message| mismatch(0, "start", message))?;
+        for (index, step) in self.steps.iter().enumerate() {
+            match &step.action {
+                StepAction::Send(event) => {
+                    let event: EventId = if event.contains(':') { event.parse()? } else { format!("sme:///{event}").parse()? };
+                    state_machine.send_event(&event)?;
+                }
+                StepAction::Set(variables) => set_variables(&mut state_machine, variables)?,
+            }
+            check(&mut state_machine, &step.expect).map_err(|message| mismatch(index + 1, &step.action, message))?;
+        }
+        Ok(Outcome { state_machine })
+    }
+}
+
+/// Reads the scenario file and runs it with the state chart relative to the file.
+pub fn run_file(path: &Path) -> Result<Outcome, ScenarioError> {
+    Scenario::read(path)?.run(path.parent().unwrap_or(Path::new(".")))
+}
+
+/// Splits the reference to a variable into the id of the declaring node and the name.
+fn variable_reference(reference: &str) -> Result<(NodeId, &str), StateChartError> {
+    match reference.rsplit_once('/') {
+        Some((node_path, name)) => Ok((NodeId::new(node_path), name)),
+        None => Err(StateChartError::UnknownVariable(reference.into())),
+    }
+}
+
+fn set_variables(state_machine: &mut StateMachine, variables: &BTreeMap<String, VariableValue>) -> Result<(), StateChartError> {
+    for (reference, value) in variables {
+        let (node_id, name) = variable_reference(reference)?;
+        let variable_id = VariableId::new(state_machine.id(), &node_id, name)?;
+        state_machine.set_variable(&variable_id, &ValidatedValue::from(value))?;
+    }
+    Ok(())
+}
+
+/// Checks the state machine against the expectation. The executed actions are taken in any case,
+/// so that the next step starts without actions.
+fn check(state_machine: &mut StateMachine, expectation: &Expectation) -> Result<(), String> {
+    state_machine.take_messages();
+    let actions: Vec<String> = state_machine
+        .take_executed_actions()
+        .iter()
+        .map(|(_, action_call)| action_call.name().as_str().to_string())
+        .collect();
+    let error = |err: StateChartError| err.to_string();
+    if let Some(expected) = &expectation.states {
+        let states = state_machine
+            .active_states()
+            .map_err(error)?
+            .iter()
+            .map(|state_id| state_id.path().map(String::from))
+            .collect::<Result<Vec<String>, StateChartError>>()
+            .map_err(error)?;
+        if !same_elements(expected, &states) {
+            return Err(format!("Expected the states {expected:?}, but {states:?} are active."));
+        }
+    }
+    for (reference, expected) in &expectation.variables {
+        let (node_id, name) = variable_reference(reference).map_err(error)?;
+        match state_machine.variable(&node_id, name).map_err(error)? {
+            Some(value) if same_value(expected, value) => {}
+            Some(value) => return Err(format!("Expected {reference} to be {expected:?}, but it is {value:?}.")),
+            None => return Err(format!("Expected {reference} to be {expected:?}, but it isn't available.")),
+        }
+    }
+    if let Some(expected) = &expectation.actions {
+        if expected != &actions {
+            return Err(format!("Expected the actions {expected:?}, but {actions:?} were executed."));
+        }
+    }
+    Ok(())
+}
+
+/// The order of parallel states isn't relevant.
+fn same_elements(expected: &[String], actual: &[String]) -> bool {
+    let mut expected = expected.to_vec();
+    let mut actual = actual.to_vec();
+    expected.sort();
+    actual.sort();
+    expected == actual
+}
+
+/// Integers and numbers are compared by their value, as JSON doesn't distinguish them.
+fn same_value(expected: &VariableValue, actual: &VariableValue) -> bool {
+    match (expected, actual) {
+        (VariableValue::Integer(i), VariableValue::Number(n)) | (VariableValue::Number(n), VariableValue::Integer(i)) => *i as f64 == *n,
+        _ => expected == actual,
+    }
+}
+
+fn mismatch(step: usize, action: &(impl ToString + ?Sized), message: String) -> ScenarioError {
+    ScenarioError::Mismatch { step, action: action.to_string(), message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scenarios() {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir("tests/scenarios")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            if let Err(err) = run_file(&path) {
+                panic!("{}: {err}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn test_clock() {
+        let err = serde_json::from_str::<Scenario>(r#"{ "chart": "tally-task.json", "steps": [ { "advance": 60 } ] }"#).unwrap_err();
+        assert!(err.to_string().contains("Advancing the clock isn't supported"), "{err}");
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "chart": "tally-task.json",
+                "steps": [
+                    { "send": "click", "expect": { "variables": { "Tally-Task/clicks": 1 } } },
+                    { "send": "click", "expect": { "variables": { "Tally-Task/clicks": 3 } } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        match scenario.run(Path::new("tests")) {
+            Err(ScenarioError::Mismatch { step, action, message }) => {
+                assert_eq!(2, step);
+                assert_eq!("send click", action);
+                assert!(message.contains("Tally-Task/clicks"));
+            }
+            Err(err) => panic!("Expected a mismatch, but got {err:?}!"),
+            Ok(_) => panic!("Expected a mismatch!"),
+        }
+
+        let scenario: Scenario =
+            serde_json::from_str(r#"{ "chart": "tally-task.json", "expect": { "states": ["Tally-Task/Stopped"] } }"#).unwrap();
+        assert!(matches!(scenario.run(Path::new("tests")), Err(ScenarioError::Mismatch { step: 0, .. })));
+        assert!(serde_json::from_str::<Scenario>(r#"{ "chart": "tally-task.json", "expected": {} }"#).is_err());
+    }
+
+    #[test]
+    fn test_step_keys() {
+        let step = |step: &str| serde_json::from_str::<Step>(step);
+        assert!(matches!(step(r#"{ "send": "click", "expect": {} }"#).unwrap().action, StepAction::Send(_)));
+        assert!(matches!(step(r#"{ "set": {} }"#).unwrap().action, StepAction::Set(_)));
+        assert!(step(r#"{ "send": "click", "expcet": { "states": [] } }"#).is_err());
+        assert!(step(r#"{ "send": "click", "set": {} }"#).is_err());
+        assert!(step(r#"{ "expect": {} }"#).is_err());
+    }
+}
@@ -0,0 +1,746 @@
+use crate::error::StateChartError;
+use crate::ids::{EventId, NodeId};
+use crate::node::{Node, NodeKind};
+use crate::state_charts::{ActionCall, Guard, PredicateCall, Transition, TransitionKind, VariableValue};
+use open_api_matcher::ValidatedValue;
+use roxmltree::Document;
+use std::collections::BTreeMap;
+
+/// The media type of SCXML documents.
+pub const MIME_TYPE: &str = "application/scxml+xml";
+/// The namespace of the SCXML elements.
+const SCXML_NAMESPACE: &str = "http://www.w3.org/2005/07/scxml";
+/// The namespace of the attributes and elements, which hold the parts of a state chart without
+/// counterpart in SCXML: descriptions, deferred events, declared types, priorities and service
+/// actions.
+const SM_NAMESPACE: &str = "urn:rabe42:state-machines";
+
+/// Converts an SCXML document into a state chart. The name of the document is the name of the
+/// state chart and the ids of the states are the names of the nodes. Only the constructs, which
+/// have a counterpart in the state chart, are accepted:
+/// - Transitions have at most one event and one target. Conditions are predicate calls, e.g.
+///   "greater(left=smv:amount, right=100)", which guard the transition or the event.
+/// - Executable content is a single <raise>, <assign> or <sm:action>. Expressions are literals.
+/// - The data model only declares variables with a literal expression.
+///
+/// All other constructs are rejected with an UnsupportedScxml error.
+pub fn from_scxml(document: &str) -> Result<Node, StateChartError> {
+    let document = Document::parse(document).map_err(|err| StateChartError::InvalidScxml(err.to_string()))?;
+    let root = document.root_element();
+    if tag(root)? != "scxml" {
+        return Err(invalid("the root element must be <scxml>"));
+    }
+    let name = root.attribute("name").ok_or_else(|| invalid("<scxml> needs a name"))?;
+    let mut ids = BTreeMap::new();
+    ids.insert(name.to_string(), node_id(name)?);
+    collect_ids(root, name, &mut ids)?;
+    let value = node_value(root, &ids[name], &ids)?;
+    (&value).try_into()
+}
+
+/// Converts the state chart into an SCXML document. Choices and junctions, parallel roots and
+/// names, which are used by more than one node, can't be mapped and are rejected with an
+/// UnsupportedScxml error.
+pub fn to_scxml(state_chart: &Node) -> Result<String, StateChartError> {
+    if state_chart.kind() != NodeKind::State {
+        return Err(unsupported(format!("{} root", state_chart.kind().as_str())));
+    }
+    let mut names: Vec<&str> = Vec::new();
+    check_names(state_chart, &mut names)?;
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let mut attributes = format!(
+        r#"xmlns="{SCXML_NAMESPACE}" xmlns:sm="{SM_NAMESPACE}" version="1.0" name="{}""#,
+        escape(state_chart.id().name()?)
+    );
+    attributes.push_str(&node_attributes(state_chart)?);
+    write_element(&mut xml, 0, "scxml", &attributes, &node_body(state_chart, 1)?);
+    Ok(xml)
+}
+
+fn invalid(reason: &str) -> StateChartError {
+    StateChartError::InvalidScxml(reason.into())
+}
+
+fn unsupported(construct: String) -> StateChartError {
+    StateChartError::UnsupportedScxml(construct)
+}
+
+/// Provides the name of the element with the "sm:" prefix for the elements of the extension.
+/// Elements of other namespaces can't be mapped.
+fn tag(element: roxmltree::Node) -> Result<String, StateChartError> {
+    let name = element.tag_name().name();
+    match element.tag_name().namespace() {
+        Some(SCXML_NAMESPACE) => Ok(name.into()),
+        Some(SM_NAMESPACE) => Ok(format!("sm:{name}")),
+        _ => Err(unsupported(format!("<{name}>"))),
+    }
+}
+
+fn node_id(path: &str) -> Result<NodeId, StateChartError> {
+    let node_id = NodeId::new(path);
+    match node_id.path() {
+        Ok(_) => Ok(node_id),
+        Err(_) => Err(StateChartError::InvalidScxml(format!("'{path}' isn't a valid node name"))),
+    }
+}
+
+/// Assigns the id of the node to the id of every state below the element. The ids must be unique
+/// in the document, as transitions address their targets by them.
+fn collect_ids(element: roxmltree::Node, path: &str, ids: &mut BTreeMap<String, NodeId>) -> Result<(), StateChartError> {
+    for child in element.children().filter(|child| child.is_element()) {
+        if matches!(tag(child)?.as_str(), "state" | "parallel" | "final" | "history") {
+            let id = child.attribute("id").ok_or_else(|| unsupported(format!("<{}> without id", tag(child).unwrap_or_default())))?;
+            let child_path = format!("{path}/{id}");
+            if ids.insert(id.to_string(), node_id(&child_path)?).is_some() {
+                return Err(StateChartError::InvalidScxml(format!("id '{id}' is used more than once")));
+            }
+            collect_ids(child, &child_path, ids)?;
+        }
+    }
+    Ok(())
+}
+
+/// Provides the absolute id of the state with the given SCXML id.
+fn lookup(ids: &BTreeMap<String, NodeId>, id: &str) -> Result<ValidatedValue, StateChartError> {
+    ids.get(id)
+        .map(|node_id| ValidatedValue::String(node_id.into()))
+        .ok_or_else(|| StateChartError::InvalidScxml(format!("unknown target '{id}'")))
+}
+
+/// Provides the single id of a list of ids. Several ids can't be mapped.
+fn single<'a>(ids: &'a str, construct: &str) -> Result<Option<&'a str>, StateChartError> {
+    let mut tokens = ids.split_whitespace();
+    match (tokens.next(), tokens.next()) {
+        (first, None) => Ok(first),
+        _ => Err(unsupported(format!("{construct} '{ids}'"))),
+    }
+}
+
+/// Builds the node from the element as it would be provided in the JSON definition of the state
+/// chart. So the references are resolved by the same rules.
+fn node_value(
+    element: roxmltree::Node,
+    node_id: &NodeId,
+    ids: &BTreeMap<String, NodeId>,
+) -> Result<ValidatedValue, StateChartError> {
+    let element_tag = tag(element)?;
+    let mut node = BTreeMap::new();
+    node.insert("id".to_string(), ValidatedValue::String(node_id.into()));
+    let kind = match (element_tag.as_str(), element.attribute("type")) {
+        ("parallel", _) => Some("parallel"),
+        ("final", _) => Some("final"),
+        ("history", Some("deep")) => Some("deep-history"),
+        ("history", None | Some("shallow")) => Some("history"),
+        ("history", Some(history_type)) => return Err(invalid(&format!("unknown history type '{history_type}'"))),
+        _ => None,
+    };
+    if let Some(kind) = kind {
+        node.insert("kind".to_string(), ValidatedValue::String(kind.into()));
+    }
+    if let Some(description) = element.attribute((SM_NAMESPACE, "description")) {
+        node.insert("description".to_string(), ValidatedValue::String(description.into()));
+    }
+    if let Some(deferred_events) = element.attribute((SM_NAMESPACE, "defer")) {
+        let events = deferred_events.split_whitespace().map(event).collect::<Result<_, _>>()?;
+        node.insert("deferred-events".to_string(), ValidatedValue::Array(events));
+    }
+
+    let mut start_node = match element.attribute("initial") {
+        Some(initial) => single(initial, "initial")?.map(|initial| lookup(ids, initial)).transpose()?,
+        None => None,
+    };
+    let mut first_state = None;
+    let mut attributes = Vec::new();
+    let mut transitions = Vec::new();
+    let mut nodes = Vec::new();
+    for child in element.children().filter(|child| child.is_element()) {
+        let child_tag = tag(child)?;
+        match (element_tag.as_str(), child_tag.as_str()) {
+            ("history", "transition") => transitions.push(transition_value(child, ids)?),
+            ("history", _) | ("final", "transition") => return Err(unsupported(format!("<{child_tag}> in <{element_tag}>"))),
+            (_, "onentry" | "onexit") => {
+                let key = if child_tag == "onentry" { "on-entry" } else { "on-exit" };
+                if let Some(action) = action_value(child)? {
+                    if node.insert(key.to_string(), action).is_some() {
+                        return Err(unsupported(format!("several <{child_tag}> elements")));
+                    }
+                }
+            }
+            (_, "transition") => transitions.push(transition_value(child, ids)?),
+            ("scxml" | "state", "initial") => {
+                let transition = child
+                    .children()
+                    .find(|grand_child| grand_child.is_element())
+                    .ok_or_else(|| invalid("<initial> needs a transition"))?;
+                if start_node.is_some() || tag(transition)? != "transition" || action_value(transition)?.is_some() {
+                    return Err(unsupported("<initial> with more than a target".into()));
+                }
+                let target = transition.attribute("target").and_then(|target| single(target, "initial").transpose());
+                start_node = Some(lookup(ids, target.ok_or_else(|| invalid("<initial> needs a target"))??)?);
+            }
+            ("scxml" | "state" | "parallel", "datamodel") => {
+                for data in child.children().filter(|data| data.is_element()) {
+                    attributes.push(data_value(data)?);
+                }
+            }
+            ("scxml" | "state" | "parallel", "state" | "parallel" | "final" | "history") => {
+                let id = child.attribute("id").unwrap_or_default();
+                if first_state.is_none() && child_tag != "history" {
+                    first_state = Some(lookup(ids, id)?);
+                }
+                nodes.push(node_value(child, &ids[id], ids)?);
+            }
+            _ => return Err(unsupported(format!("<{child_tag}> in <{element_tag}>"))),
+        }
+    }
+    if matches!(element_tag.as_str(), "scxml" | "state") {
+        if let Some(start_node) = start_node.or(first_state) {
+            node.insert("start-node".to_string(), start_node);
+        }
+    }
+    if !attributes.is_empty() {
+        node.insert("attributes".to_string(), ValidatedValue::Array(attributes));
+    }
+    if !transitions.is_empty() {
+        node.insert("out-transitions".to_string(), ValidatedValue::Array(transitions));
+    }
+    if !nodes.is_empty() {
+        node.insert("nodes".to_string(), ValidatedValue::Array(nodes));
+    }
+    Ok(ValidatedValue::Object(node))
+}
+
+/// Builds the transition from the element. The condition of a transition with an event must be
+/// true as well, while a condition alone is the guard. A transition without both is a completion
+/// transition.
+fn transition_value(element: roxmltree::Node, ids: &BTreeMap<String, NodeId>) -> Result<ValidatedValue, StateChartError> {
+    let mut transition = BTreeMap::new();
+    let event_name = match element.attribute("event") {
+        Some(events) => single(events, "event list")?,
+        None => None,
+    };
+    match (event_name, element.attribute("cond")) {
+        (Some(event_name), Some(cond)) => {
+            transition.insert("guard".to_string(), event(event_name)?);
+            transition.insert("condition".to_string(), predicate_value(cond)?);
+        }
+        (Some(event_name), None) => {
+            transition.insert("guard".to_string(), event(event_name)?);
+        }
+        (None, Some(cond)) => {
+            transition.insert("guard".to_string(), predicate_value(cond)?);
+        }
+        (None, None) => {}
+    }
+    if let Some(target) = element.attribute("target").map(|target| single(target, "target list")).transpose()?.flatten() {
+        transition.insert("to".to_string(), lookup(ids, target)?);
+    }
+    match element.attribute("type") {
+        None | Some("external") => {}
+        Some("internal") => {
+            transition.insert("kind".to_string(), ValidatedValue::String("internal".into()));
+        }
+        Some(transition_type) => return Err(invalid(&format!("unknown transition type '{transition_type}'"))),
+    }
+    if let Some(priority) = element.attribute((SM_NAMESPACE, "priority")) {
+        let priority = priority.parse::<i64>().map_err(|_| invalid(&format!("invalid priority '{priority}'")))?;
+        transition.insert("priority".to_string(), ValidatedValue::Integer(priority));
+    }
+    if let Some(action) = action_value(element)? {
+        transition.insert("action".to_string(), action);
+    }
+    Ok(ValidatedValue::Object(transition))
+}
+
+/// Provides the event with the given name. Descriptors with wildcards can't be mapped.
+fn event(name: &str) -> Result<ValidatedValue, StateChartError> {
+    if name.contains('*') {
+        return Err(unsupported(format!("event descriptor '{name}'")));
+    }
+    let event_id: EventId = format!("sme:///{name}").parse()?;
+    Ok(ValidatedValue::String(event_id.to_string()))
+}
+
+/// Builds the predicate call from a condition of the form "name(parameter=value, ...)". Any
+/// other expression can't be mapped.
+fn predicate_value(cond: &str) -> Result<ValidatedValue, StateChartError> {
+    let unmappable = || unsupported(format!("cond '{cond}'"));
+    let (name, arguments) = cond.trim().strip_suffix(')').and_then(|cond| cond.split_once('(')).ok_or_else(unmappable)?;
+    let mut parameters = Vec::new();
+    for argument in split_arguments(arguments).into_iter().filter(|argument| !argument.trim().is_empty()) {
+        let (parameter, value) = argument.split_once('=').ok_or_else(unmappable)?;
+        parameters.push(parameter_value(parameter.trim(), literal(value)));
+    }
+    Ok(call_value(name.trim(), parameters))
+}
+
+/// Splits the arguments of a condition at the commas, which aren't quoted.
+fn split_arguments(arguments: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in arguments.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            (None, ',') => {
+                result.push(&arguments[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(&arguments[start..]);
+    result
+}
+
+/// Parses a literal expression. Booleans, null and numbers are written as in JSON, strings can be
+/// quoted. Anything else, like references or event ids, is taken as string.
+fn literal(expression: &str) -> ValidatedValue {
+    let expression = expression.trim();
+    let is_numeric = !expression.is_empty() && expression.chars().all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+    match expression {
+        "true" => ValidatedValue::Bool(true),
+        "false" => ValidatedValue::Bool(false),
+        "null" => ValidatedValue::None,
+        _ if is_numeric && expression.parse::<i64>().is_ok() => ValidatedValue::Integer(expression.parse().unwrap_or_default()),
+        _ if is_numeric && expression.parse::<f64>().is_ok() => ValidatedValue::Number(expression.parse().unwrap_or_default()),
+        _ => {
+            let unquoted = ['\'', '"']
+                .iter()
+                .find_map(|quote| expression.strip_prefix(*quote).and_then(|inner| inner.strip_suffix(*quote)));
+            ValidatedValue::String(unquoted.unwrap_or(expression).into())
+        }
+    }
+}
+
+fn parameter_value(name: &str, value: ValidatedValue) -> ValidatedValue {
+    let mut parameter = BTreeMap::new();
+    parameter.insert("name".to_string(), ValidatedValue::String(name.into()));
+    parameter.insert("value".to_string(), value);
+    ValidatedValue::Object(parameter)
+}
+
+fn call_value(name: &str, parameters: Vec<ValidatedValue>) -> ValidatedValue {
+    let mut call = BTreeMap::new();
+    call.insert("name".to_string(), ValidatedValue::String(name.into()));
+    call.insert("parameters".to_string(), ValidatedValue::Array(parameters));
+    ValidatedValue::Object(call)
+}
+
+/// Builds the action from the executable content of the element. As a node or transition has a
+/// single action, more than one element can't be mapped.
+fn action_value(element: roxmltree::Node) -> Result<Option<ValidatedValue>, StateChartError> {
+    let mut content = element.children().filter(|child| child.is_element());
+    let (action, None) = (content.next(), content.next()) else {
+        return Err(unsupported(format!("several actions in <{}>", tag(element)?)));
+    };
+    let Some(action) = action else {
+        return Ok(None);
+    };
+    let missing = |attribute: &str| StateChartError::InvalidScxml(format!("<{}> needs {attribute}", tag(action).unwrap_or_default()));
+    match tag(action)?.as_str() {
+        "raise" => {
+            let event = event(action.attribute("event").ok_or_else(|| missing("an event"))?)?;
+            Ok(Some(call_value("raise", vec![parameter_value("event", event)])))
+        }
+        "assign" => {
+            let location = action.attribute("location").ok_or_else(|| missing("a location"))?;
+            let expression = action.attribute("expr").ok_or_else(|| unsupported("<assign> without expr".into()))?;
+            let variable = match location.starts_with("smv:") {
+                true => location.to_string(),
+                false => format!("smv:{location}"),
+            };
+            Ok(Some(call_value(
+                "assign",
+                vec![
+                    parameter_value("variable", ValidatedValue::String(variable)),
+                    parameter_value("value", literal(expression)),
+                ],
+            )))
+        }
+        "sm:action" => {
+            let name = action.attribute("name").ok_or_else(|| missing("a name"))?;
+            let mut parameters = Vec::new();
+            for parameter in action.children().filter(|parameter| parameter.is_element()) {
+                if tag(parameter)? != "sm:param" {
+                    return Err(unsupported(format!("<{}> in <sm:action>", tag(parameter)?)));
+                }
+                let parameter_name = parameter.attribute("name").ok_or_else(|| invalid("<sm:param> needs a name"))?;
+                let value = parameter.attribute("value").ok_or_else(|| invalid("<sm:param> needs a value"))?;
+                parameters.push(parameter_value(parameter_name, literal(value)));
+            }
+            Ok(Some(call_value(name, parameters)))
+        }
+        other => Err(unsupported(format!("<{other}>"))),
+    }
+}
+
+/// Builds the declaration of a variable from a data element with literal expression. The type is
+/// derived from the literal, if it isn't given by the sm:type attribute.
+fn data_value(data: roxmltree::Node) -> Result<ValidatedValue, StateChartError> {
+    if tag(data)? != "data" {
+        return Err(unsupported(format!("<{}> in <datamodel>", tag(data)?)));
+    }
+    let name = data.attribute("id").ok_or_else(|| invalid("<data> needs an id"))?;
+    let expression = data.attribute("expr").ok_or_else(|| unsupported(format!("<data> '{name}' without expr")))?;
+    let value = literal(expression);
+    let value_type = match (data.attribute((SM_NAMESPACE, "type")), &value) {
+        (Some(value_type), _) => value_type,
+        (None, ValidatedValue::Bool(_)) => "boolean",
+        (None, ValidatedValue::Integer(_)) => "integer",
+        (None, ValidatedValue::Number(_)) => "number",
+        (None, _) => "string",
+    };
+    let mut declaration = BTreeMap::new();
+    declaration.insert("name".to_string(), ValidatedValue::String(name.into()));
+    declaration.insert("type".to_string(), ValidatedValue::String(value_type.into()));
+    declaration.insert("value".to_string(), value);
+    Ok(ValidatedValue::Object(declaration))
+}
+
+/// Checks, that every name is only used once, as it must be unique in the document.
+fn check_names<'a>(node: &'a Node, names: &mut Vec<&'a str>) -> Result<(), StateChartError> {
+    let node_name = node.id().name()?;
+    if names.contains(&node_name) {
+        return Err(unsupported(format!("name '{node_name}' used by more than one node")));
+    }
+    names.push(node_name);
+    for sub_node in node.nodes() {
+        check_names(sub_node, names)?;
+    }
+    Ok(())
+}
+
+/// Provides the attributes of the element of a node, which follow its id.
+fn node_attributes(node: &Node) -> Result<String, StateChartError> {
+    let mut attributes = String::new();
+    match (node.kind(), node.start_node()) {
+        (NodeKind::State, Some(start_node)) => attributes.push_str(&format!(r#" initial="{}""#, escape(start_node.name()?))),
+        (NodeKind::History, _) => attributes.push_str(r#" type="shallow""#),
+        (NodeKind::DeepHistory, _) => attributes.push_str(r#" type="deep""#),
+        _ => {}
+    }
+    if let Some(description) = node.description() {
+        attributes.push_str(&format!(r#" sm:description="{}""#, escape(description)));
+    }
+    if !node.deferred_events().is_empty() {
+        let events: Vec<&str> = node.deferred_events().iter().map(|event| event.name()).collect();
+        attributes.push_str(&format!(r#" sm:defer="{}""#, escape(&events.join(" "))));
+    }
+    Ok(attributes)
+}
+
+/// Provides the content of the element of a node: the data model, the entry and exit actions, the
+/// transitions and the sub-nodes.
+fn node_body(node: &Node, depth: usize) -> Result<String, StateChartError> {
+    let mut body = String::new();
+    if !node.attributes().is_empty() {
+        let mut datamodel = String::new();
+        for attribute in node.attributes() {
+            let mut data = format!(r#"id="{}" expr="{}""#, escape(attribute.name()), escape(&format_literal(attribute.value())?));
+            if let Some(derived) = literal_type(attribute.value()) {
+                if derived != attribute.value_type() {
+                    data.push_str(&format!(r#" sm:type="{}""#, escape(attribute.value_type())));
+                }
+            }
+            write_element(&mut datamodel, depth + 1, "data", &data, "");
+        }
+        write_element(&mut body, depth, "datamodel", "", &datamodel);
+    }
+    for (element, action) in [("onentry", node.on_entry()), ("onexit", node.on_exit())] {
+        if let Some(action) = action {
+            write_element(&mut body, depth, element, "", &action_xml(action, depth + 1)?);
+        }
+    }
+    for transition in node.out_transitions() {
+        transition_xml(&mut body, transition, depth)?;
+    }
+    for sub_node in node.nodes() {
+        let element = match sub_node.kind() {
+            NodeKind::State => "state",
+            NodeKind::Parallel => "parallel",
+            NodeKind::Final => "final",
+            NodeKind::History | NodeKind::DeepHistory => "history",
+            kind => return Err(unsupported(format!("{} '{}'", kind.as_str(), sub_node.id()))),
+        };
+        let attributes = format!(r#"id="{}"{}"#, escape(sub_node.id().name()?), node_attributes(sub_node)?);
+        write_element(&mut body, depth, element, &attributes, &node_body(sub_node, depth + 1)?);
+    }
+    Ok(body)
+}
+
+fn transition_xml(xml: &mut String, transition: &Transition, depth: usize) -> Result<(), StateChartError> {
+    let mut attributes = match transition.guard() {
+        Guard::Event(event) => format!(r#"event="{}""#, escape(event.name())),
+        Guard::Predicate(predicate_call) => format!(r#"cond="{}""#, escape(&cond(predicate_call)?)),
+        Guard::Completion => String::new(),
+        Guard::Else => return Err(unsupported("else guard".into())),
+    };
+    if let Some(condition) = transition.condition() {
+        attributes.push_str(&format!(r#" cond="{}""#, escape(&cond(condition)?)));
+    }
+    if let Some(to) = transition.to() {
+        attributes.push_str(&format!(r#" target="{}""#, escape(to.name()?)));
+    }
+    if transition.kind() == TransitionKind::Internal {
+        attributes.push_str(r#" type="internal""#);
+    }
+    if transition.priority() != 0 {
+        attributes.push_str(&format!(r#" sm:priority="{}""#, transition.priority()));
+    }
+    let content = match transition.action() {
+        Some(action) => action_xml(action, depth + 1)?,
+        None => String::new(),
+    };
+    write_element(xml, depth, "transition", attributes.trim_start(), &content);
+    Ok(())
+}
+
+fn cond(predicate_call: &PredicateCall) -> Result<String, StateChartError> {
+    let arguments = predicate_call
+        .parameters()
+        .iter()
+        .map(|parameter| Ok(format!("{}={}", parameter.name(), format_literal(parameter.value())?)))
+        .collect::<Result<Vec<String>, StateChartError>>()?;
+    Ok(format!("{}({})", predicate_call.name().as_str(), arguments.join(", ")))
+}
+
+/// Provides the executable content of the action. Raise and assign are written as the SCXML
+/// elements, all other actions of the service as sm:action.
+fn action_xml(action: &ActionCall, depth: usize) -> Result<String, StateChartError> {
+    let mut xml = String::new();
+    let values: Vec<&VariableValue> = action.parameters().iter().map(|parameter| parameter.value()).collect();
+    match (action.name().as_str(), values.as_slice()) {
+        ("raise", [VariableValue::String(event)]) if event.starts_with("sme:///") => {
+            let event: EventId = event.parse()?;
+            write_element(&mut xml, depth, "raise", &format!(r#"event="{}""#, escape(event.name())), "");
+        }
+        ("assign", [VariableValue::Reference(variable), value]) => {
+            let attributes = format!(r#"location="{}" expr="{}""#, escape(&variable.to_string()), escape(&format_literal(value)?));
+            write_element(&mut xml, depth, "assign", &attributes, "");
+        }
+        (name, _) => {
+            let mut parameters = String::new();
+            for parameter in action.parameters() {
+                let attributes = format!(
+                    r#"name="{}" value="{}""#,
+                    escape(parameter.name()),
+                    escape(&format_literal(parameter.value())?)
+                );
+                write_element(&mut parameters, depth + 1, "sm:param", &attributes, "");
+            }
+            write_element(&mut xml, depth, "sm:action", &format!(r#"name="{}""#, escape(name)), &parameters);
+        }
+    }
+    Ok(xml)
+}
+
+/// Writes a value as literal, which is parsed into the same value again. References and ids are
+/// written as they are, all other strings are quoted.
+fn format_literal(value: &VariableValue) -> Result<String, StateChartError> {
+    match value {
+        VariableValue::String(s) => {
+            let is_plain = (s.starts_with("smv:") || s.starts_with("sme:"))
+                && !s.contains(|c: char| c.is_whitespace() || "'\",()".contains(c));
+            if is_plain {
+                Ok(s.clone())
+            } else if !s.contains('\'') {
+                Ok(format!("'{s}'"))
+            } else if !s.contains('"') {
+                Ok(format!("\"{s}\""))
+            } else {
+                Err(unsupported(format!("string literal {s}")))
+            }
+        }
+        VariableValue::Integer(i) => Ok(i.to_string()),
+        VariableValue::Number(n) => Ok(format!("{n:?}")),
+        VariableValue::Boolean(b) => Ok(b.to_string()),
+        VariableValue::Reference(reference) => Ok(reference.to_string()),
+        VariableValue::None => Ok("null".into()),
+    }
+}
+
+/// Provides the type, which is derived from the literal of the value on import.
+fn literal_type(value: &VariableValue) -> Option<&'static str> {
+    match value {
+        VariableValue::None => None,
+        value => Some(value.get_type()),
+    }
+}
+
+/// Writes the element with the given attributes and content. An element without content is
+/// closed immediately.
+fn write_element(xml: &mut String, depth: usize, element: &str, attributes: &str, content: &str) {
+    let indent = "    ".repeat(depth);
+    let separator = if attributes.is_empty() { "" } else { " " };
+    if content.is_empty() {
+        xml.push_str(&format!("{indent}<{element}{separator}{attributes}/>\n"));
+    } else {
+        xml.push_str(&format!("{indent}<{element}{separator}{attributes}>\n{content}{indent}</{element}>\n"));
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation;
+    use open_api_matcher::OpenApi;
+
+    fn read_state_chart(file_name: &str) -> Node {
+        let open_api_file = std::fs::File::open("StateMachines.yml").unwrap();
+        let open_api = OpenApi::new(&open_api_file).unwrap();
+        let sc = std::fs::read_to_string(file_name).unwrap();
+        let sc_schema = open_api.get_schema("#/components/schemas/Node").unwrap();
+        let vvsc = ValidatedValue::new(&sc, &sc_schema, &open_api).unwrap();
+        (&vvsc).try_into().unwrap()
+    }
+
+    fn scxml(content: &str) -> String {
+        format!(r#"<scxml xmlns="{SCXML_NAMESPACE}" xmlns:sm="{SM_NAMESPACE}" version="1.0" name="Doc">{content}</scxml>"#)
+    }
+
+    #[test]
+    fn test_from_scxml() {
+        let state_chart = from_scxml(&std::fs::read_to_string("tests/media-player.scxml").unwrap()).unwrap();
+        validation::validate(&state_chart).unwrap();
+        assert_eq!(&NodeId::new("Media-Player"), state_chart.id());
+        assert_eq!(Some(&NodeId::new("Media-Player/Off")), state_chart.start_node());
+        assert_eq!("plays", state_chart.attributes()[0].name());
+        assert_eq!(&VariableValue::Integer(0), state_chart.attributes()[0].value());
+
+        let on = state_chart.find(&NodeId::new("Media-Player/On")).unwrap();
+        assert_eq!(NodeKind::Parallel, on.kind());
+        assert_eq!(None, on.start_node());
+        let playback = state_chart.find(&NodeId::new("Media-Player/On/Playback")).unwrap();
+        assert_eq!(Some(&NodeId::new("Media-Player/On/Playback/Stopped")), playback.start_node());
+        let history = state_chart.find(&NodeId::new("Media-Player/On/Playback/Last-Playback")).unwrap();
+        assert_eq!(NodeKind::History, history.kind());
+        let finished = state_chart.find(&NodeId::new("Media-Player/On/Playback/Finished")).unwrap();
+        assert_eq!(NodeKind::Final, finished.kind());
+
+        let playing = state_chart.find(&NodeId::new("Media-Player/On/Playback/Playing")).unwrap();
+        assert_eq!("increment", playing.on_entry().unwrap().name().as_str());
+        assert_eq!(
+            &VariableValue::Reference("smv:///Media-Player/plays".parse().unwrap()),
+            playing.on_entry().unwrap().parameters()[0].value()
+        );
+        assert!(playing.defers(&"sme:///louder".parse().unwrap()));
+        let resume = &state_chart.find(&NodeId::new("Media-Player/Off")).unwrap().out_transitions()[1];
+        assert_eq!(Some(&NodeId::new("Media-Player/On/Playback/Last-Playback")), resume.to());
+    }
+
+    #[test]
+    fn test_conditions_and_assignments() {
+        let state_chart = from_scxml(&scxml(
+            r#"<datamodel><data id="amount" expr="0"/><data id="owner" expr="'Ralf, Berger'"/></datamodel>
+               <state id="Open">
+                   <transition cond="greater(left=smv:amount, right=100)" target="Large" type="internal">
+                       <assign location="owner" expr="'Ann'"/>
+                   </transition>
+               </state>
+               <state id="Large"/>"#,
+        ))
+        .unwrap();
+        assert_eq!(Some(&NodeId::new("Doc/Open")), state_chart.start_node());
+        assert_eq!(&VariableValue::String("Ralf, Berger".into()), state_chart.attributes()[1].value());
+        let transition = &state_chart.nodes()[0].out_transitions()[0];
+        assert_eq!(TransitionKind::Internal, transition.kind());
+        match transition.guard() {
+            Guard::Predicate(predicate_call) => {
+                assert_eq!("greater", predicate_call.name().as_str());
+                assert_eq!(&VariableValue::Reference("smv:///Doc/amount".parse().unwrap()), predicate_call.parameters()[0].value());
+                assert_eq!(&VariableValue::Integer(100), predicate_call.parameters()[1].value());
+            }
+            _ => panic!("Expected a predicate!"),
+        }
+        let action = transition.action().unwrap();
+        assert_eq!("assign", action.name().as_str());
+        assert_eq!(&VariableValue::Reference("smv:///Doc/owner".parse().unwrap()), action.parameters()[0].value());
+    }
+
+    #[test]
+    fn test_event_with_condition() {
+        let document = scxml(
+            r#"<datamodel><data id="amount" expr="0"/></datamodel>
+               <state id="Open"><transition event="submit" cond="greater(left=smv:amount, right=100)" target="Large"/></state>
+               <state id="Large"/>"#,
+        );
+        let state_chart = from_scxml(&document).unwrap();
+        let transition = &state_chart.nodes()[0].out_transitions()[0];
+        assert!(matches!(transition.guard(), Guard::Event(event) if event.name() == "submit"));
+        let condition = transition.condition().unwrap();
+        assert_eq!("greater", condition.name().as_str());
+        assert_eq!(&VariableValue::Reference("smv:///Doc/amount".parse().unwrap()), condition.parameters()[0].value());
+        let exported = to_scxml(&state_chart).unwrap();
+        assert!(exported.contains(r#"<transition event="submit" cond="greater(left=smv:///Doc/amount, right=100)" target="Large"/>"#), "{exported}");
+        assert_eq!(exported, to_scxml(&from_scxml(&exported).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_unsupported_scxml() {
+        let unsupported = [
+            r#"<state id="A"><transition event="go" target="A B"/></state><state id="B"/>"#,
+            r#"<state id="A"><transition event="error.*" target="A"/></state>"#,
+            r#"<state id="A"><transition cond="x &gt; 5" target="A"/></state>"#,
+            r#"<state id="A"><onentry><send event="go"/></onentry></state>"#,
+            r#"<state id="A"><onentry><raise event="a"/><raise event="b"/></onentry></state>"#,
+            r#"<state id="A"><invoke src="other.scxml"/></state>"#,
+            r#"<state id="A"/><state><state id="B"/></state>"#,
+        ];
+        for content in unsupported {
+            match from_scxml(&scxml(content)) {
+                Err(StateChartError::UnsupportedScxml(_)) => {}
+                other => panic!("Expected an unsupported construct in {content}, got {:?}", other.map(|_| ())),
+            }
+        }
+        let invalid = [
+            r#"<state id="A"><transition event="go" target="Missing"/></state>"#,
+            r#"<state id="A"/><state id="A"/>"#,
+            r#"<state id="A">"#,
+        ];
+        for content in invalid {
+            match from_scxml(&scxml(content)) {
+                Err(StateChartError::InvalidScxml(_)) => {}
+                other => panic!("Expected an invalid document for {content}, got {:?}", other.map(|_| ())),
+            }
+        }
+        match from_scxml(&format!(r#"<scxml xmlns="{SCXML_NAMESPACE}"><state id="A"/></scxml>"#)) {
+            Err(StateChartError::InvalidScxml(_)) => {}
+            _ => panic!("Expected a missing name!"),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for state_chart in [
+            read_state_chart("tests/tally-task.json"),
+            read_state_chart("tests/relay-task.json"),
+            read_state_chart("tests/approval.json"),
+            from_scxml(&std::fs::read_to_string("tests/media-player.scxml").unwrap()).unwrap(),
+        ] {
+            let document = to_scxml(&state_chart).unwrap();
+            let imported = from_scxml(&document).unwrap();
+            assert_eq!(state_chart.id(), imported.id());
+            assert_eq!(document, to_scxml(&imported).unwrap());
+        }
+        let document = to_scxml(&read_state_chart("tests/approval.json")).unwrap();
+        assert!(document.contains(r#"target="Escalated" sm:priority="1""#));
+    }
+
+    #[test]
+    fn test_to_scxml_unsupported() {
+        match to_scxml(&read_state_chart("tests/order-task.json")) {
+            Err(StateChartError::UnsupportedScxml(construct)) => assert!(construct.starts_with("choice")),
+            _ => panic!("Expected an unsupported choice!"),
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! The HTTP layer, which provides the state charts and state machines as specified in
+//! StateMachines.yml. It is only available with the http feature.
+use hyper::{Method, StatusCode};
+use log::{debug, error, info};
+use open_api_matcher::{OpenApiOperation, OpenApiResponse, RequestParamters, ValidatedValue, Value};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use crate::delivery;
+use crate::diagram::{self, Format};
+use crate::error::{Error, StateChartError};
+use crate::ids::{EventId, NodeId, StateId, VariableId};
+use crate::lint;
+use crate::node::Node;
+use crate::scxml;
+use crate::sql::Crud;
+use crate::state_machine::StateMachine;
+use crate::validation;
+
+/// Serves the specification on the address. Without database file, the data is kept in memory
+/// only. It fails, if the database can't be opened or its tables can't be created.
+pub async fn serve(addr: SocketAddr, spec: &File, database: Option<&Path>) -> Result<(), Error> {
+    let pool = create_db_connection(database)?;
+    init_data_modell(&pool)?;
+    info!("Starting server on {:?}!", addr);
+    open_api_matcher::service::start(addr, spec, Box::new(handle), pool).await;
+    Ok(())
+}
+
+/// Creates and initialize the database connection pool.
+/// Every connection to an in memory database has its own database. Therefore, the pool must not
+/// provide more than one connection.
+fn create_db_connection(database: Option<&Path>) -> Result<Pool<SqliteConnectionManager>, Error> {
+    let pool = match database {
+        Some(path) => Pool::new(SqliteConnectionManager::file(path))?,
+        None => Pool::builder().max_size(1).build(SqliteConnectionManager::memory())?,
+    };
+    Ok(pool)
+}
+
+fn init_data_modell(pool: &Pool<SqliteConnectionManager>) -> Result<(), Error> {
+    let connection = pool.get()?;
+    Node::create(&connection)?;
+    StateMachine::create(&connection)?;
+    Ok(())
+}
+
+/// The central function, where all request must be handled.
+async fn handle(
+    request: open_api_matcher::service::RequestMatch,
+    ctx: Pool<SqliteConnectionManager>,
+) -> OpenApiResponse {
+    match request.into_match() {
+        (&Method::GET, "/state-chart/", _p, op) => {
+            let mut response = OpenApiResponse::new(op);
+            response.content(Vec::new().into());
+            response
+        }
+        (&Method::POST, "/state-chart/", p, op) => create_state_chart(p, op, ctx).await,
+        (&Method::GET, "/state-chart/{id}", p, op) => get_state_chart(p, op, ctx).await,
+        (&Method::GET, "/state-chart/{id}/diagram", p, op) => get_state_chart_diagram(p, op, ctx).await,
+        (&Method::GET, "/state-machine/{state-machine-id}/diagram", p, op) => {
+            get_state_machine_diagram(p, op, ctx).await
+        }
+        (&Method::POST, "/action/", _p, op) => {
+            let response = OpenApiResponse::new(op);
+            response
+        }
+        (&Method::POST, "/start/{state-chart-id}", p, op) => start_state_machine(p, op, ctx).await,
+        (&Method::POST, "/send/{state-machine-id}/{event-id}", p, op) => send_event(p, op, ctx).await,
+        (&Method::POST, "/set-var/{state-machine-id}/{variable-id}", p, op) => set_variable(p, op, ctx).await,
+        (&Method::GET, "/hello/{name}", p, op) => {
+            debug!("Matched '/hello/{{name}}'");
+            let answer = format!("Hello {}!", p.get_path_parameter("name"));
+            let mut response = OpenApiResponse::new(op);
+            response.content(Value::String(answer));
+            response
+        }
+        _ => {
+            error!("Unexpected match!");
+            OpenApiResponse::fall_through()
+        }
+    }
+}
+
+/// Creates and saves a new state chart, from the already validated parameters. The only validation
+/// which needs to happen here, is the validation, based on semantic level. This might be a wrong
+/// defintion of the state chart, where the start state might be missing.
+/// An SCXML document is provided as string content and converted into the state chart first.
+/// The response contains the id and the warnings of the static analysis.
+async fn create_state_chart(
+    p: &RequestParamters,
+    op: &OpenApiOperation,
+    pool: Pool<SqliteConnectionManager>,
+) -> OpenApiResponse {
+    debug!("[server::create_state_chart()]");
+    let mut response = OpenApiResponse::new(op);
+    let node_result: Result<Node, StateChartError> = match p.get_content() {
+        ValidatedValue::String(document) => scxml::from_scxml(document),
+        content => content.try_into(),
+    }
+    .and_then(|state_chart| validation::validate(&state_chart).map(|_| state_chart));
+    match node_result {
+        Ok(mut state_chart) => {
+            debug!("Received state chart successfully:\n{:?}", state_chart);
+            let connection = match pool.get() {
+                Ok(connection) => connection,
+                Err(err) => return error_response(op, err),
+            };
+            match Node::select(&connection, state_chart.id()) {
+                Ok(Some(_)) => return error_response(op, StateChartError::StateChartExists(state_chart.id().clone())),
+                Ok(None) => {}
+                Err(err) => return error_response(op, err),
+            }
+            if let Err(err) = state_chart.insert(&connection) {
+                return error_response(op, err);
+            }
+            let warnings = lint::lint(&state_chart);
+            let mut content = BTreeMap::new();
+            content.insert("id".to_string(), state_chart.id().into());
+            content.insert("warnings".to_string(), Value::Array(warnings.iter().map(Value::from).collect()));
+            response.content(Value::Object(content));
+        }
+        Err(err) => {
+            error!("[server::create_state_chart()]: {}", err);
+            return error_response(op, err);
+        }
+    }
+    response
+}
+
+/// Provides the state chart as JSON. If the Accept header asks for SCXML, the state chart is
+/// converted into an SCXML document.
+async fn get_state_chart(
+    p: &RequestParamters,
+    op: &OpenApiOperation,
+    pool: Pool<SqliteConnectionManager>,
+) -> OpenApiResponse {
+    debug!("[server::get_state_chart()]");
+    let state_chart_id = NodeId::from(p.get_path_parameter("id"));
+    let connection = match pool.get() {
+        Ok(connection) => connection,
+        Err(err) => return error_response(op, err),
+    };
+    let state_chart = match Node::select(&connection, &state_chart_id) {
+        Ok(Some(state_chart)) => state_chart,
+        Ok(None) => return error_response(op, StateChartError::UnknownStateChart(state_chart_id)),
+        Err(err) => return error_response(op, err),
+    };
+    let mut response = OpenApiResponse::new(op);
+    let accepts_scxml = p
+        .get_header_parameter("Accept")
+        .is_some_and(|accept| accept.contains(scxml::MIME_TYPE));
+    if accepts_scxml {
+        match scxml::to_scxml(&state_chart) {
+            Ok(document) => {
+                response.set_mime_type(scxml::MIME_TYPE.into());
+                response.content(Value::String(document));
+            }
+            Err(err) => return error_response(op, err),
+        }
+    } else {
+        match serde_json::to_value(&state_chart) {
+            Ok(json) => {
+                response.set_mime_type("application/json".into());
+                response.content(json_value(json));
+            }
+            Err(err) => return error_response(op, err),
+        }
+    }
+    response
+}
+
+/// Converts the serialized JSON into the content of a response.
+fn json_value(json: serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Number(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(values) => Value::Array(values.into_iter().map(json_value).collect()),
+        serde_json::Value::Object(attributes) => {
+            Value::Object(attributes.into_iter().map(|(name, value)| (name, json_value(value))).collect())
+        }
+    }
+}
+
+/// Renders the state chart as diagram in the requested format.
+async fn get_state_chart_diagram(
+    p: &RequestParamters,
+    op: &OpenApiOperation,
+    pool: Pool<SqliteConnectionManager>,
+) -> OpenApiResponse {
+    debug!("[server::get_state_chart_diagram()]");
+    let format = match diagram_format(p) {
+        Ok(format) => format,
+        Err(err) => return error_response(op, err),
+    };
+    let state_chart_id = NodeId::from(p.get_path_parameter("id"));
+    let connection = match pool.get() {
+        Ok(connection) => connection,
+        Err(err) => return error_response(op, err),
+    };
+    match Node::select(&connection, &state_chart_id) {
+        Ok(Some(state_chart)) => diagram_response(op, diagram::render(&state_chart, format, &[]), format),
+        Ok(None) => error_response(op, StateChartError::UnknownStateChart(state_chart_id)),
+        Err(err) => error_response(op, err),
+    }
+}
+
+/// Renders the state chart of the state machine as diagram in the requested format. The active
+/// nodes of the state machine are highlighted.
+async fn get_state_machine_diagram(
+    p: &RequestParamters,
+    op: &OpenApiOperation,
+    pool: Pool<SqliteConnectionManager>,
+) -> OpenApiResponse {
+    debug!("[server::get_state_machine_diagram()]");
+    let format = match diagram_format(p) {
+        Ok(format) => format,
+        Err(err) => return error_response(op, err),
+    };
+    let state_machine_id = StateId::from(p.get_path_parameter("state-machine-id"));
+    let connection = match pool.get() {
+        Ok(connection) => connection,
+        Err(err) => return error_response(op, err),
+    };
+    match StateMachine::select(&connection, &state_machine_id) {
+        Ok(Some(state_machine)) => {
+            let rendered = state_machine
+                .configuration()
+                .and_then(|configuration| diagram::render(state_machine.state_chart(), format, &configuration));
+            diagram_response(op, rendered, format)
+        }
+        Ok(None) => error_response(op, StateChartError::UnknownStateMachine(state_machine_id)),
+        Err(err) => error_response(op, err),
+    }
+}
+
+/// Provides the requested diagram format. Without format, DOT is rendered.
+fn diagram_format(p: &RequestParamters) -> Result<Format, StateChartError> {
+    p.get_query_parameter("format").unwrap_or("dot").try_into()
+}
+
+fn diagram_response(op: &OpenApiOperation, rendered: Result<String, StateChartError>, format: Format) -> OpenApiResponse {
+    match rendered {
+        Ok(diagram) => {
+            let mut response = OpenApiResponse::new(op);
+            response.set_mime_type(format.mime_type().into());
+            response.content(Value::String(diagram));
+            response
+        }
+        Err(err) => error_response(op, err),
+    }
+}
+
+/// Creates a new state machine from the state chart with the given id. The new state machine is
+/// saved, before its id is returned.
+async fn start_state_machine(
+    p: &RequestParamters,
+    op: &OpenApiOperation,
+    pool: Pool<SqliteConnectionManager>,
+) -> OpenApiResponse {
+    debug!("[server::start_state_machine()]");
+    let state_chart_id = NodeId::from(p.get_path_parameter("state-chart-id"));
+    let connection = match pool.get() {
+        Ok(connection) => connection,
+        Err(err) => return error_response(op, err),
+    };
+    match delivery::start(&connection, &state_chart_id) {
+        Ok(id) => {
+            let mut response = OpenApiResponse::new(op);
+            response.content((&id).into());
+            response
+        }
+        Err(err) => error_response(op, err),
+    }
+}
+
+/// Sends the event to a running state machine. Provides the chain of the events and transitions,
+/// triggered by the event, and the events, which are valid afterwards.
+async fn send_event(
+    p: &RequestParamters,
+    op: &OpenApiOperation,
+    pool: Pool<SqliteConnectionManager>,
+) -> OpenApiResponse {
+    debug!("[server::send_event()]");
+    let state_machine_id = StateId::from(p.get_path_parameter("state-machine-id"));
+    let event_id: EventId = match p.get_path_parameter("event-id").parse() {
+        Ok(event_id) => event_id,
+        Err(err) => return error_response(op, err),
+    };
+    let connection = match pool.get() {
+        Ok(connection) => connection,
+        Err(err) => return error_response(op, err),
+    };
+    match delivery::send(&connection, &state_machine_id, &event_id) {
+        Ok(reaction) => {
+            let mut response = OpenApiResponse::new(op);
+            response.content((&reaction).into());
+            response
+        }
+        Err(err) => error_response(op, err),
+    }
+}
+
+/// Sets the variable of a running state machine to the value provided in the request body.
+/// Provides the chain of the events and transitions, triggered by the new value, and the events,
+/// which are valid afterwards.
+async fn set_variable(
+    p: &RequestParamters,
+    op: &OpenApiOperation,
+    pool: Pool<SqliteConnectionManager>,
+) -> OpenApiResponse {
+    debug!("[server::set_variable()]");
+    let state_machine_id = StateId::from(p.get_path_parameter("state-machine-id"));
+    let variable_id: VariableId = match p.get_path_parameter("variable-id").parse() {
+        Ok(variable_id) => variable_id,
+        Err(err) => return error_response(op, err),
+    };
+    let connection = match pool.get() {
+        Ok(connection) => connection,
+        Err(err) => return error_response(op, err),
+    };
+    match delivery::set_variable(&connection, &state_machine_id, &variable_id, p.get_content()) {
+        Ok(reaction) => {
+            let mut response = OpenApiResponse::new(op);
+            response.content((&reaction).into());
+            response
+        }
+        Err(err) => error_response(op, err),
+    }
+}
+
+/// Provides the error as the JSON content of the response. The failures of the server are logged
+/// and answered with status 500, as they aren't caused by the request.
+fn error_response(op: &OpenApiOperation, err: impl Into<Error>) -> OpenApiResponse {
+    let err = err.into();
+    let status = if err.is_internal() {
+        error!("[server]: {err}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    } else if err.is_not_found() {
+        StatusCode::NOT_FOUND
+    } else if err.is_conflict() {
+        StatusCode::CONFLICT
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    let mut response = OpenApiResponse::new(op);
+    response.set_status(status);
+    response.set_mime_type("application/json".into());
+    response.content(err.into());
+    response
+}
@@ -1,4 +1,5 @@
 use r2d2::{ManageConnection, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::ToSql;
 
 /// The trait provides an interface to the naive database operations. It supports different
@@ -17,6 +18,26 @@ pub trait Crud<B: ManageConnection, K: ToSql> {
         Self: Sized;
 }
 
+/// Runs the operation in a savepoint, which is released, if the operation succeeds, and rolled
+/// back otherwise. Unlike transactions, savepoints nest, so that the operations can be part of
+/// the transaction of the caller.
+pub fn savepoint<T, E: From<rusqlite::Error>>(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    operation: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    connection.execute_batch("SAVEPOINT crud")?;
+    match operation() {
+        Ok(result) => {
+            connection.execute_batch("RELEASE crud")?;
+            Ok(result)
+        }
+        Err(err) => {
+            connection.execute_batch("ROLLBACK TO crud; RELEASE crud")?;
+            Err(err)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
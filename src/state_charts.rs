@@ -1,31 +1,44 @@
-use crate::error::StateChartError;
-use crate::ids::NodeId;
-use crate::sql::Crud;
+use crate::error::{Error, StateChartError};
+use crate::ids::{ActionId, EventId, NodeId, PredicateId, VariableReference};
+use crate::sql::{savepoint, Crud};
 use open_api_matcher::ValidatedValue;
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, Row, ToSql};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::BTreeMap;
-
-/// A system wide unique Id of a action.
-pub type ActionId = String;
-
-/// A system wide unique Id of a variable.
-pub type VariableId = String;
-
-/// A system wide unique Id of a event.
-pub type EventId = String;
-
-/// A system wide unique Id of a predicate.
-pub type PredicateId = String;
+use std::fmt;
 
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ActionCall {
+    #[serde(skip)]
     id: Option<i64>,
     name: ActionId,
+    #[serde(default)]
     parameters: Vec<Parameter>,
 }
+impl ActionCall {
+    pub fn name(&self) -> &ActionId {
+        &self.name
+    }
+
+    pub fn parameters(&self) -> &Vec<Parameter> {
+        &self.parameters
+    }
+
+    /// Starts the call of the action in code.
+    pub fn builder(name: &str) -> CallBuilder {
+        CallBuilder::new(name)
+    }
+
+    /// Resolves the relative variable references in the parameters of the call.
+    pub fn resolve_references(&mut self, scopes: &[(NodeId, Vec<String>)]) -> Result<(), StateChartError> {
+        resolve_parameter_references(&mut self.parameters, scopes)
+    }
+}
 impl TryFrom<&ValidatedValue> for ActionCall {
     type Error = StateChartError;
 
@@ -47,7 +60,7 @@ impl TryFrom<&ValidatedValue> for ActionCall {
 /// name and set of parameters can be used multiple times. In the relational world, this requires
 /// an additional object id of the action call relation/object.
 impl Crud<SqliteConnectionManager, i64> for ActionCall {
-    type Error = rusqlite::Error;
+    type Error = Error;
 
     fn get_id(&self) -> Option<&i64>
     {
@@ -91,29 +104,61 @@ impl Crud<SqliteConnectionManager, i64> for ActionCall {
         Ok(&self.id.as_ref().unwrap())
     }
 
-    fn update(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+    /// Updates the name of the action call. The parameters are replaced by the ones of the
+    /// receiver.
+    fn update(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("ActionCall"))?;
+        connection.execute("UPDATE ActionCall SET name = ? WHERE rowid = ?", params![self.name, id])?;
+        delete_parameters(connection, "ACParameterList", "action_call_id", id)?;
+        insert_parameters(connection, "ACParameterList", "action_call_id", id, &self.parameters)
     }
-    fn delete(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+
+    /// Deletes the action call together with its parameters.
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("ActionCall"))?;
+        delete_parameters(connection, "ACParameterList", "action_call_id", id)?;
+        connection.execute("DELETE FROM ActionCall WHERE rowid = ?", [id])?;
+        Ok(())
     }
-    fn select(_connection: &PooledConnection<SqliteConnectionManager>, _key_value: &i64) -> Result<Option<Self>, Self::Error>
+
+    /// Selects the action call together with its parameters.
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, key_value: &i64) -> Result<Option<Self>, Self::Error>
     where
         Self: Sized
     {
-        todo!()
+        let sql = "SELECT rowid, name FROM ActionCall WHERE rowid = ?";
+        let mut statement = connection.prepare(sql)?;
+        let mut rows = statement.query([key_value])?;
+        if let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let parameters = select_parameters(connection, "ACParameterList", "action_call_id", id)?;
+            Ok(Some(ActionCall { id: Some(id), name: row.get(1)?, parameters }))
+        } else {
+            Ok(None)
+        }
     }
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Parameter {
+    #[serde(skip)]
     id: Option<i64>,
-    name: VariableId,
+    name: String,
     value: VariableValue,
 }
+impl Parameter {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &VariableValue {
+        &self.value
+    }
+}
 impl TryFrom<&ValidatedValue> for Parameter {
     type Error = StateChartError;
 
@@ -129,7 +174,7 @@ impl TryFrom<&ValidatedValue> for Parameter {
 }
 
 impl Crud<SqliteConnectionManager, i64> for Parameter {
-    type Error = rusqlite::Error;
+    type Error = Error;
 
     fn get_id(&self) -> Option<&i64>
     {
@@ -166,13 +211,25 @@ impl Crud<SqliteConnectionManager, i64> for Parameter {
                                                              // parameters here!
         Ok(self.id.as_ref().unwrap())
     }
-    fn update(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+    /// Updates the name and the value of the parameter. The value columns of the previous type are
+    /// cleared.
+    fn update(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("Parameter"))?;
+        let value_column = self.value.get_column_name();
+        let sql = format!(
+            "UPDATE Parameter SET name = ?, value_type = ?, string_value = NULL, integer_value = NULL,
+             boolean_value = NULL, number_value = NULL, {value_column} = ? WHERE rowid = ?"
+        );
+        connection.execute(&sql, params![self.name, self.value.get_type(), self.value.to_string(), id])?;
+        Ok(())
     }
-    fn delete(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("Parameter"))?;
+        connection.execute("DELETE FROM Parameter WHERE rowid = ?", [id])?;
+        Ok(())
     }
 
     /// Selects a parameter with type and value from the database.
@@ -186,13 +243,7 @@ impl Crud<SqliteConnectionManager, i64> for Parameter {
         let mut rows = statement.query([key_value])?;
         if let Some(row) = rows.next()? {
             let value_type: String = row.get(2)?;
-            let value = match value_type.as_str() {
-                "string" => VariableValue::String(row.get(3)?),
-                "integer" => VariableValue::Integer(row.get(4)?),
-                "number" => VariableValue::Number(row.get(5)?),
-                "boolean" => VariableValue::Boolean(row.get(6)?),
-                _ => panic!("[Parameter::select()] Unexpected value type!"),
-            };
+            let value = value_from_row(row, &value_type, 3)?;
             Ok(Some(Parameter { id: Some(row.get(0)?), name: row.get(1)?, value }))
         } else {
             Ok(None)
@@ -202,23 +253,161 @@ impl Crud<SqliteConnectionManager, i64> for Parameter {
 
 /// The transition from one node to another.
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Transition {
+    #[serde(skip)]
     id: Option<i64>,
     /// The check, which tests, if the transaction is activated.
+    #[serde(default, skip_serializing_if = "Guard::is_completion")]
     guard: Guard,
-    /// The node, we reach after the transaction is activated.
-    to: NodeId,
+    /// The predicate, which must be true as well, when the event of the guard occurs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    condition: Option<PredicateCall>,
+    /// The node, we reach after the transaction is activated. A transition without target only
+    /// reacts by its action, without leaving or entering a node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    to: Option<NodeId>,
+    /// Defines, if the source is left, when the target is the source or one of its descendants.
+    #[serde(default, skip_serializing_if = "TransitionKind::is_external")]
+    kind: TransitionKind,
     /// The action called, if the transition is activated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     action: Option<ActionCall>,
+    /// Resolves conflicts with other enabled transitions: the transition with the higher priority
+    /// is taken. Between transitions of the same priority the transition of the inner node is
+    /// taken and then the first one of a node.
+    #[serde(default, skip_serializing_if = "is_default_priority")]
+    priority: i32,
+}
+fn is_default_priority(priority: &i32) -> bool {
+    *priority == 0
+}
+impl Transition {
+    pub fn guard(&self) -> &Guard {
+        &self.guard
+    }
+
+    pub fn condition(&self) -> Option<&PredicateCall> {
+        self.condition.as_ref()
+    }
+
+    pub fn to(&self) -> Option<&NodeId> {
+        self.to.as_ref()
+    }
+
+    pub fn kind(&self) -> TransitionKind {
+        self.kind
+    }
+
+    pub fn action(&self) -> Option<&ActionCall> {
+        self.action.as_ref()
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Starts a transition in code, which is triggered by the event. A bare name is an event of
+    /// the sme:/// scheme.
+    pub fn on(event: &str) -> TransitionBuilder {
+        TransitionBuilder::new(GuardBuilder::Event(event.into()))
+    }
+
+    /// Starts a transition in code, which is enabled by the predicate.
+    pub fn when(predicate: CallBuilder) -> TransitionBuilder {
+        TransitionBuilder::new(GuardBuilder::Predicate(predicate))
+    }
+
+    /// Starts the else branch of a choice or junction in code.
+    pub fn otherwise() -> TransitionBuilder {
+        TransitionBuilder::new(GuardBuilder::Else)
+    }
+
+    /// Starts a completion transition in code, which is taken without event.
+    pub fn completion() -> TransitionBuilder {
+        TransitionBuilder::new(GuardBuilder::Completion)
+    }
+
+    /// Resolves the target node and the variable references of guard, condition and action. The target is
+    /// resolved relative to the source node, a bare name addresses a sibling of it. The scopes
+    /// hold the ids and variable names of the source node and all its ancestors.
+    pub fn resolve_references(&mut self, source: &NodeId, scopes: &[(NodeId, Vec<String>)]) -> Result<(), StateChartError> {
+        if let Some(to) = self.to.take() {
+            self.to = Some(source.resolve_sibling(to.as_str())?);
+        }
+        if let Guard::Predicate(predicate_call) = &mut self.guard {
+            predicate_call.resolve_references(scopes)?;
+        }
+        if let Some(condition) = &mut self.condition {
+            condition.resolve_references(scopes)?;
+        }
+        if let Some(action) = &mut self.action {
+            action.resolve_references(scopes)?;
+        }
+        Ok(())
+    }
+}
+/// The guard type, the event and the ids of the predicate and action call, as they are stored with
+/// the transition. The predicate call of an event guard is the condition.
+type CallColumns = (&'static str, Option<EventId>, Option<i64>, Option<i64>);
+
+impl Transition {
+    /// Inserts the predicate call of the guard or the condition and the action call.
+    fn insert_calls(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<CallColumns, Error> {
+        let condition = match &mut self.condition {
+            Some(condition) => Some(*condition.insert(connection)?),
+            None => None,
+        };
+        let (guard_type, event, predicate_call) = match &mut self.guard {
+            Guard::Event(event) => ("event", Some(event.clone()), condition),
+            Guard::Predicate(predicate_call) => ("predicate", None, Some(*predicate_call.insert(connection)?)),
+            Guard::Else => ("else", None, None),
+            Guard::Completion => ("completion", None, None),
+        };
+        let action = match &mut self.action {
+            Some(action) => Some(*action.insert(connection)?),
+            None => None,
+        };
+        Ok((guard_type, event, predicate_call, action))
+    }
+
+    /// Deletes the predicate call of the guard, the condition and the action call of a stored
+    /// transition.
+    fn delete_calls(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Error> {
+        if let Guard::Predicate(predicate_call) = &self.guard {
+            predicate_call.delete(connection)?;
+        }
+        if let Some(condition) = &self.condition {
+            condition.delete(connection)?;
+        }
+        if let Some(action) = &self.action {
+            action.delete(connection)?;
+        }
+        Ok(())
+    }
 }
 impl TryFrom<&ValidatedValue> for Transition {
     type Error = StateChartError;
 
     fn try_from(value: &ValidatedValue) -> Result<Self, Self::Error> {
         if let ValidatedValue::Object(attributes) = value {
-            let guard = get_mandatory(attributes, "guard")?.try_into()?;
-            let to = get_mandatory(attributes, "to")?.try_into()?;
+            let guard = match attributes.get("guard") {
+                Some(guard) => guard.try_into()?,
+                None => Guard::Completion,
+            };
+            let condition = match attributes.get("condition") {
+                Some(condition) => Some(condition.try_into()?),
+                None => None,
+            };
+            let to = match attributes.get("to") {
+                Some(to) => Some(to.try_into()?),
+                None => None,
+            };
+            let kind = match attributes.get("kind") {
+                Some(kind) => kind.try_into()?,
+                None => TransitionKind::default(),
+            };
             let action = match attributes.get("action") {
                 None => None,
                 Some(v_action) => {
@@ -226,7 +415,12 @@ impl TryFrom<&ValidatedValue> for Transition {
                     Some(ac)
                 }
             };
-            Ok(Transition { id: None, guard, to, action })
+            let priority = match attributes.get("priority") {
+                Some(ValidatedValue::Integer(priority)) => i32::try_from(*priority).map_err(|_| StateChartError::UnexpectedType)?,
+                Some(_) => return Err(StateChartError::UnexpectedType),
+                None => 0,
+            };
+            Ok(Transition { id: None, guard, condition, to, kind, action, priority })
         } else {
             Err(StateChartError::UnexpectedType)
         }
@@ -234,7 +428,7 @@ impl TryFrom<&ValidatedValue> for Transition {
 }
 
 impl Crud<SqliteConnectionManager, i64> for Transition {
-    type Error = rusqlite::Error;
+    type Error = Error;
 
     fn get_id(&self) -> Option<&i64>
     {
@@ -247,9 +441,13 @@ impl Crud<SqliteConnectionManager, i64> for Transition {
     fn create(connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
         let sql = "CREATE TABLE IF NOT EXISTS Transition (
-                guard INTEGER NOT NULL,
-                target TEXT NOT NULL,
-                action INTEGER
+                guard_type TEXT NOT NULL,
+                event TEXT,
+                predicate_call INTEGER,
+                target TEXT,
+                kind TEXT NOT NULL,
+                action INTEGER,
+                priority INTEGER NOT NULL DEFAULT 0
             )";
         connection.execute(sql, [])?;
 
@@ -258,42 +456,206 @@ impl Crud<SqliteConnectionManager, i64> for Transition {
 
         Ok(())
     }
-    fn insert(&mut self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<&i64, Self::Error>
+
+    /// Inserts the transition together with the predicate call of the guard and the action call.
+    /// The guard itself is stored as part of the transition.
+    fn insert(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<&i64, Self::Error>
     {
-        todo!()
+        let (guard_type, event, predicate_call, action) = self.insert_calls(connection)?;
+        let sql = "INSERT INTO Transition ( guard_type, event, predicate_call, target, kind, action, priority ) VALUES ( ?, ?, ?, ?, ?, ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        let rowid = statement.insert(params![guard_type, event, predicate_call, self.to, self.kind, action, self.priority])?;
+        self.id = Some(rowid);
+        Ok(self.id.as_ref().unwrap())
     }
-    fn update(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+
+    /// Updates the transition. The predicate call of the guard and the action call are replaced by
+    /// the ones of the receiver.
+    fn update(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("Transition"))?;
+        savepoint(connection, || {
+            if let Some(stored) = Transition::select(connection, &id)? {
+                stored.delete_calls(connection)?;
+            }
+            let (guard_type, event, predicate_call, action) = self.clone().insert_calls(connection)?;
+            let sql = "UPDATE Transition SET guard_type = ?, event = ?, predicate_call = ?, target = ?, kind = ?, action = ?, priority = ?
+                       WHERE rowid = ?";
+            connection.execute(sql, params![guard_type, event, predicate_call, self.to, self.kind, action, self.priority, id])?;
+            Ok(())
+        })
     }
-    fn delete(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+
+    /// Deletes the transition together with the predicate call of the guard and the action call.
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("Transition"))?;
+        if let Some(stored) = Transition::select(connection, &id)? {
+            stored.delete_calls(connection)?;
+        }
+        connection.execute("DELETE FROM Transition WHERE rowid = ?", [id])?;
+        Ok(())
     }
-    fn select(_connection: &PooledConnection<SqliteConnectionManager>, _key_value: &i64) -> Result<Option<Self>, Self::Error>
+
+    /// Selects the transition together with its guard and action.
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, key_value: &i64) -> Result<Option<Self>, Self::Error>
     where
         Self: Sized
     {
-        todo!()
+        let sql = "SELECT rowid, guard_type, event, predicate_call, target, kind, action, priority FROM Transition WHERE rowid = ?";
+        let mut statement = connection.prepare(sql)?;
+        let mut rows = statement.query([key_value])?;
+        if let Some(row) = rows.next()? {
+            let guard_type: String = row.get(1)?;
+            let predicate_call_id: Option<i64> = row.get(3)?;
+            let predicate_call = match predicate_call_id {
+                Some(predicate_call_id) => match PredicateCall::select(connection, &predicate_call_id)? {
+                    Some(predicate_call) => Some(predicate_call),
+                    None => return Err(rusqlite::Error::QueryReturnedNoRows.into()),
+                },
+                None => None,
+            };
+            let (guard, condition) = match (guard_type.as_str(), predicate_call) {
+                ("event", condition) => (Guard::Event(row.get(2)?), condition),
+                ("else", _) => (Guard::Else, None),
+                ("completion", _) => (Guard::Completion, None),
+                (_, Some(predicate_call)) => (Guard::Predicate(predicate_call), None),
+                (_, None) => return Err(rusqlite::Error::QueryReturnedNoRows.into()),
+            };
+            let action_id: Option<i64> = row.get(6)?;
+            let action = match action_id {
+                Some(action_id) => ActionCall::select(connection, &action_id)?,
+                None => None,
+            };
+            Ok(Some(Transition { id: Some(row.get(0)?), guard, condition, to: row.get(4)?, kind: row.get(5)?, action, priority: row.get(7)? }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// An external transition leaves and enters its source again, even if the target is the source
+/// or one of its descendants. An internal transition doesn't leave its source in this case.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransitionKind {
+    #[default]
+    External,
+    Internal,
+}
+impl TransitionKind {
+    pub fn is_external(&self) -> bool {
+        *self == Self::External
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::External => "external",
+            Self::Internal => "internal",
+        }
+    }
+}
+
+impl TryFrom<&str> for TransitionKind {
+    type Error = StateChartError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "external" => Ok(Self::External),
+            "internal" => Ok(Self::Internal),
+            _ => Err(StateChartError::UnexpectedType),
+        }
+    }
+}
+
+impl TryFrom<&ValidatedValue> for TransitionKind {
+    type Error = StateChartError;
+
+    fn try_from(value: &ValidatedValue) -> Result<Self, Self::Error> {
+        if let ValidatedValue::String(kind) = value {
+            kind.as_str().try_into()
+        } else {
+            Err(StateChartError::UnexpectedType)
+        }
     }
 }
 
+impl ToSql for TransitionKind {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>, rusqlite::Error> {
+        self.as_str().to_sql()
+    }
+}
+
+impl FromSql for TransitionKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str()?.try_into().map_err(|_| FromSqlError::InvalidType)
+    }
+}
 
 /// The guard on a trasition holds the condition under which a transaction is activated.
 /// It will be evaluated by the state machine runtime.
+/// The else guard is only valid for the out transitions of a choice or junction. It is selected,
+/// if no other guard of the pseudo node is true.
+/// A transition without guard is a completion transition. It is taken without event, as soon as
+/// its source node is active.
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub enum Guard {
     Event(EventId),
     Predicate(PredicateCall),
+    Else,
+    Completion,
+}
+impl Guard {
+    pub fn is_completion(&self) -> bool {
+        matches!(self, Self::Completion)
+    }
+}
+/// A transition without guard is a completion transition.
+impl Default for Guard {
+    fn default() -> Self {
+        Self::Completion
+    }
+}
+
+/// The guard is written like in JSON: the event id or "else" as string, the predicate call as
+/// object. A completion transition has no guard at all.
+impl Serialize for Guard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Event(event) => event.serialize(serializer),
+            Self::Predicate(predicate_call) => predicate_call.serialize(serializer),
+            Self::Else => serializer.serialize_str("else"),
+            Self::Completion => serializer.serialize_none(),
+        }
+    }
+}
+impl<'de> Deserialize<'de> for Guard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Definition {
+            Name(String),
+            Predicate(PredicateCall),
+        }
+        match Definition::deserialize(deserializer)? {
+            Definition::Name(name) if name == "else" => Ok(Self::Else),
+            Definition::Name(event) => event.parse().map(Self::Event).map_err(de::Error::custom),
+            Definition::Predicate(predicate_call) => Ok(Self::Predicate(predicate_call)),
+        }
+    }
 }
 
 impl TryFrom<&ValidatedValue> for Guard {
     type Error = StateChartError;
 
     fn try_from(value: &ValidatedValue) -> Result<Self, Self::Error> {
-        if let ValidatedValue::String(event_id) = value {
-            Ok(Guard::Event(event_id.into()))
+        if let ValidatedValue::String(guard) = value {
+            if guard == "else" {
+                Ok(Guard::Else)
+            } else {
+                Ok(Guard::Event(value.try_into()?))
+            }
         } else if let ValidatedValue::Object(_) = value {
             let predicate_call = value.try_into()?;
             Ok(Guard::Predicate(predicate_call))
@@ -303,62 +665,84 @@ impl TryFrom<&ValidatedValue> for Guard {
     }
 }
 
+/// The guard is stored as part of the transition. Only the predicate call of a predicate guard is
+/// an object of its own, whose id is the id of the guard. The other guards can't be stored on
+/// their own.
 impl Crud<SqliteConnectionManager, i64> for Guard {
-    type Error = rusqlite::Error;
+    type Error = Error;
 
     fn get_id(&self) -> Option<&i64>
     {
-        // FIXME: This needs to be managed! If the guard is an entity, it must be a struct!
-        // Otherwise it should go to the Transition entity.
-        None
+        match self {
+            Self::Predicate(predicate_call) => predicate_call.get_id(),
+            _ => None,
+        }
     }
 
-    /// Crates the tables, needed to store a parameter of a action call or a predicate.
-    /// To simulate the enumeration behaviour the value type must be stored with it.
-    /// It *MUST* have the value "string", "integer", "bool", "number", "none".
     fn create(connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        let sql = "CREATE TABLE IF NOT EXISTS Guard (
-                guard_type TEXT NOT NULL,
-                event TEXT,
-                predicate_call INTEGER
-            )";
-        connection.execute(sql, [])?;
-
-        PredicateCall::create(connection)?;
-
-        Ok(())
+        PredicateCall::create(connection)
     }
-    fn insert(&mut self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<&i64, Self::Error>
+    fn insert(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<&i64, Self::Error>
     {
-        todo!()
+        match self {
+            Self::Predicate(predicate_call) => predicate_call.insert(connection),
+            _ => Err(Error::NotStored("Guard without predicate")),
+        }
     }
-    fn update(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+    fn update(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        match self {
+            Self::Predicate(predicate_call) => predicate_call.update(connection),
+            _ => Err(Error::NotStored("Guard without predicate")),
+        }
     }
-    fn delete(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        match self {
+            Self::Predicate(predicate_call) => predicate_call.delete(connection),
+            _ => Err(Error::NotStored("Guard without predicate")),
+        }
     }
-    fn select(_connection: &PooledConnection<SqliteConnectionManager>, _key_value: &i64) -> Result<Option<Self>, Self::Error>
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, key_value: &i64) -> Result<Option<Self>, Self::Error>
     where
         Self: Sized
     {
-        todo!()
+        Ok(PredicateCall::select(connection, key_value)?.map(Self::Predicate))
     }
 }
 
-
 /// The call of a predicate may be a guard. The predicate of all transactions of the current state
 /// will be evaluated when ever a variable value was modified.
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PredicateCall {
+    #[serde(skip)]
     id: Option<i64>,
     name: PredicateId,
+    #[serde(default)]
     parameters: Vec<Parameter>,
 }
+impl PredicateCall {
+    pub fn name(&self) -> &PredicateId {
+        &self.name
+    }
+
+    pub fn parameters(&self) -> &Vec<Parameter> {
+        &self.parameters
+    }
+
+    /// Starts the call of the predicate in code.
+    pub fn builder(name: &str) -> CallBuilder {
+        CallBuilder::new(name)
+    }
+
+    /// Resolves the relative variable references in the parameters of the call.
+    pub fn resolve_references(&mut self, scopes: &[(NodeId, Vec<String>)]) -> Result<(), StateChartError> {
+        resolve_parameter_references(&mut self.parameters, scopes)
+    }
+}
 impl TryFrom<&ValidatedValue> for PredicateCall {
     type Error = StateChartError;
 
@@ -375,7 +759,7 @@ impl TryFrom<&ValidatedValue> for PredicateCall {
 }
 
 impl Crud<SqliteConnectionManager, i64> for PredicateCall {
-    type Error = rusqlite::Error;
+    type Error = Error;
 
     fn get_id(&self) -> Option<&i64>
     {
@@ -413,26 +797,49 @@ impl Crud<SqliteConnectionManager, i64> for PredicateCall {
         }
         Ok(&self.id.as_ref().unwrap())
     }
-    fn update(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+    /// Updates the name of the predicate call. The parameters are replaced by the ones of the
+    /// receiver.
+    fn update(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("PredicateCall"))?;
+        connection.execute("UPDATE PredicateCall SET name = ? WHERE rowid = ?", params![self.name, id])?;
+        delete_parameters(connection, "PCParameterList", "predicate_call_id", id)?;
+        insert_parameters(connection, "PCParameterList", "predicate_call_id", id, &self.parameters)
     }
-    fn delete(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+
+    /// Deletes the predicate call together with its parameters.
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("PredicateCall"))?;
+        delete_parameters(connection, "PCParameterList", "predicate_call_id", id)?;
+        connection.execute("DELETE FROM PredicateCall WHERE rowid = ?", [id])?;
+        Ok(())
     }
-    fn select(_connection: &PooledConnection<SqliteConnectionManager>, _key_value: &i64) -> Result<Option<Self>, Self::Error>
+
+    /// Selects the predicate call together with its parameters.
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, key_value: &i64) -> Result<Option<Self>, Self::Error>
     where
         Self: Sized
     {
-        todo!()
+        let sql = "SELECT rowid, name FROM PredicateCall WHERE rowid = ?";
+        let mut statement = connection.prepare(sql)?;
+        let mut rows = statement.query([key_value])?;
+        if let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let parameters = select_parameters(connection, "PCParameterList", "predicate_call_id", id)?;
+            Ok(Some(PredicateCall { id: Some(id), name: row.get(1)?, parameters }))
+        } else {
+            Ok(None)
+        }
     }
 }
 
 /// Declares a variable inside of a state chart state.
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct VariableDeclaration {
+    #[serde(skip)]
     id: Option<i64>,
     name: String,
     r#type: String,
@@ -443,9 +850,40 @@ impl VariableDeclaration {
         &self.name
     }
 
+    pub fn value_type(&self) -> &str {
+        &self.r#type
+    }
+
     pub fn value(&self) -> &VariableValue {
         &self.value
     }
+
+    /// Declares a variable in code. The type is the type of the initial value.
+    pub fn new(name: &str, value: VariableValue) -> Self {
+        VariableDeclaration { id: None, name: name.into(), r#type: value.get_type().into(), value }
+    }
+
+    /// Coerces the provided value into the declared type of the variable. Integers are accepted
+    /// as numbers and numbers without a fraction as integers. Strings are parsed into the declared
+    /// type.
+    pub fn coerce(&self, value: VariableValue) -> Result<VariableValue, StateChartError> {
+        let mismatch = || StateChartError::TypeMismatch(self.name.clone(), self.r#type.clone());
+        match (self.r#type.as_str(), value) {
+            ("string", VariableValue::String(s)) => Ok(VariableValue::String(s)),
+            ("integer", VariableValue::Integer(i)) => Ok(VariableValue::Integer(i)),
+            // The bounds are exact powers of two, so that no value is saturated by the cast.
+            ("integer", VariableValue::Number(n)) if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 => {
+                Ok(VariableValue::Integer(n as i64))
+            }
+            ("integer", VariableValue::String(s)) => s.trim().parse().map(VariableValue::Integer).map_err(|_| mismatch()),
+            ("number", VariableValue::Number(n)) => Ok(VariableValue::Number(n)),
+            ("number", VariableValue::Integer(i)) => Ok(VariableValue::Number(i as f64)),
+            ("number", VariableValue::String(s)) => s.trim().parse().map(VariableValue::Number).map_err(|_| mismatch()),
+            ("boolean", VariableValue::Boolean(b)) => Ok(VariableValue::Boolean(b)),
+            ("boolean", VariableValue::String(s)) => s.trim().parse().map(VariableValue::Boolean).map_err(|_| mismatch()),
+            _ => Err(mismatch()),
+        }
+    }
 }
 impl TryFrom<&ValidatedValue> for VariableDeclaration {
     type Error = StateChartError;
@@ -465,7 +903,7 @@ impl TryFrom<&ValidatedValue> for VariableDeclaration {
 }
 
 impl Crud<SqliteConnectionManager, i64> for VariableDeclaration {
-    type Error = rusqlite::Error;
+    type Error = Error;
 
     fn get_id(&self) -> Option<&i64>
     {
@@ -480,10 +918,11 @@ impl Crud<SqliteConnectionManager, i64> for VariableDeclaration {
         let sql = "CREATE TABLE IF NOT EXISTS VariableDeclaration (
                 name TEXT NOT NULL,
                 variable_type TEXT NOT NULL,
+                value_type TEXT NOT NULL,
                 string_value TEXT,
                 integer_value INTEGER,
                 number_value REAL,
-                bool_value INTEGER
+                boolean_value INTEGER
             )";
         connection.execute(sql, [])?;
 
@@ -492,68 +931,242 @@ impl Crud<SqliteConnectionManager, i64> for VariableDeclaration {
     fn insert(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<&i64, Self::Error>
     {
         let value_column = self.value.get_column_name();
-        let sql = format!("INSERT INTO VariableDeclaration ( name, variable_type, {value_column} ) VALUES ( ?, ?, ? )");
+        let sql = format!("INSERT INTO VariableDeclaration ( name, variable_type, value_type, {value_column} ) VALUES ( ?, ?, ?, ? )");
         let mut statement = connection.prepare(&sql)?;
-        let rowid = statement.insert(params![self.name, self.value.get_type(), self.value.to_string() ])?;
+        let rowid = statement.insert(params![self.name, self.r#type, self.value.get_type(), self.value.to_string() ])?;
         self.id = Some(rowid);
         Ok(self.id.as_ref().unwrap())
     }
-    fn update(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+    /// Updates the declaration. The value columns of the previous type are cleared.
+    fn update(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("VariableDeclaration"))?;
+        let value_column = self.value.get_column_name();
+        let sql = format!(
+            "UPDATE VariableDeclaration SET name = ?, variable_type = ?, value_type = ?, string_value = NULL,
+             integer_value = NULL, number_value = NULL, boolean_value = NULL, {value_column} = ? WHERE rowid = ?"
+        );
+        connection.execute(&sql, params![self.name, self.r#type, self.value.get_type(), self.value.to_string(), id])?;
+        Ok(())
     }
-    fn delete(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
+
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error>
     {
-        todo!()
+        let id = self.id.ok_or(Error::NotStored("VariableDeclaration"))?;
+        connection.execute("DELETE FROM VariableDeclaration WHERE rowid = ?", [id])?;
+        Ok(())
     }
-    fn select(_connection: &PooledConnection<SqliteConnectionManager>, _key_value: &i64) -> Result<Option<Self>, Self::Error>
+
+    /// Selects the variable declaration with its initial value.
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, key_value: &i64) -> Result<Option<Self>, Self::Error>
     where
         Self: Sized
     {
-        todo!()
+        let sql = "SELECT rowid, name, variable_type, value_type, string_value, integer_value, number_value, boolean_value
+                   FROM VariableDeclaration WHERE rowid = ?";
+        let mut statement = connection.prepare(sql)?;
+        let mut rows = statement.query([key_value])?;
+        if let Some(row) = rows.next()? {
+            let value_type: String = row.get(3)?;
+            Ok(Some(VariableDeclaration {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                r#type: row.get(2)?,
+                value: value_from_row(row, &value_type, 4)?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Builds the call of an action or a predicate in code. The name is checked, when the state chart
+/// is built.
+#[derive(Clone, Debug)]
+pub struct CallBuilder {
+    name: String,
+    parameters: Vec<Parameter>,
+}
+impl CallBuilder {
+    fn new(name: &str) -> Self {
+        CallBuilder { name: name.into(), parameters: Vec::new() }
+    }
+
+    /// Adds the parameter. Variables are referenced like in JSON, e.g. "smv:amount".
+    pub fn parameter(mut self, name: &str, value: impl Into<VariableValue>) -> Self {
+        self.parameters.push(Parameter { id: None, name: name.into(), value: value.into() });
+        self
+    }
+
+    pub fn build_action_call(self) -> Result<ActionCall, StateChartError> {
+        Ok(ActionCall { id: None, name: self.name.parse()?, parameters: self.parameters })
+    }
+
+    pub fn build_predicate_call(self) -> Result<PredicateCall, StateChartError> {
+        Ok(PredicateCall { id: None, name: self.name.parse()?, parameters: self.parameters })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum GuardBuilder {
+    Event(String),
+    Predicate(CallBuilder),
+    Else,
+    Completion,
+}
+
+/// Builds a transition in code. The target is referenced relative to the source node, like in
+/// JSON.
+#[derive(Clone, Debug)]
+pub struct TransitionBuilder {
+    guard: GuardBuilder,
+    condition: Option<CallBuilder>,
+    to: Option<String>,
+    kind: TransitionKind,
+    action: Option<CallBuilder>,
+    priority: i32,
+}
+impl TransitionBuilder {
+    fn new(guard: GuardBuilder) -> Self {
+        TransitionBuilder { guard, condition: None, to: None, kind: TransitionKind::default(), action: None, priority: 0 }
+    }
+
+    /// The transition on the event is only taken, if the predicate is true too.
+    pub fn condition(mut self, predicate: CallBuilder) -> Self {
+        self.condition = Some(predicate);
+        self
+    }
+
+    pub fn to(mut self, target: &str) -> Self {
+        self.to = Some(target.into());
+        self
+    }
+
+    /// The source isn't left, if the target is the source or one of its descendants.
+    pub fn internal(mut self) -> Self {
+        self.kind = TransitionKind::Internal;
+        self
+    }
+
+    pub fn action(mut self, action: CallBuilder) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    /// The transition with the higher priority is taken, if several transitions are enabled.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn build(self) -> Result<Transition, StateChartError> {
+        let guard = match self.guard {
+            GuardBuilder::Event(event) if event.contains(':') => Guard::Event(event.parse()?),
+            GuardBuilder::Event(event) => Guard::Event(format!("sme:///{event}").parse()?),
+            GuardBuilder::Predicate(predicate) => Guard::Predicate(predicate.build_predicate_call()?),
+            GuardBuilder::Else => Guard::Else,
+            GuardBuilder::Completion => Guard::Completion,
+        };
+        let condition = self.condition.map(CallBuilder::build_predicate_call).transpose()?;
+        let action = self.action.map(CallBuilder::build_action_call).transpose()?;
+        Ok(Transition {
+            id: None,
+            guard,
+            condition,
+            to: self.to.as_deref().map(NodeId::from),
+            kind: self.kind,
+            action,
+            priority: self.priority,
+        })
+    }
+}
+
+/// Replaces the variable references in the parameter values by typed ones. An absolute reference
+/// starts with "smv:///". "smv:./approved" and "smv:../priority" address a variable relative to
+/// the innermost scope, while a bare "smv:priority" is looked up lexically from the innermost
+/// scope up to the root.
+fn resolve_parameter_references(
+    parameters: &mut [Parameter],
+    scopes: &[(NodeId, Vec<String>)],
+) -> Result<(), StateChartError> {
+    for parameter in parameters {
+        if let VariableValue::String(reference) = &parameter.value {
+            if reference.starts_with("smv:///") {
+                parameter.value = VariableValue::Reference(reference.parse()?);
+            } else if let Some(relative) = reference.strip_prefix("smv:") {
+                parameter.value = VariableValue::Reference(resolve_variable_reference(relative, scopes)?);
+            }
+        }
     }
+    Ok(())
 }
 
-/// The variable value holds the value of a variable attribute or parameter.
+/// Resolves a single relative variable reference to the declaring node and the name.
+fn resolve_variable_reference(reference: &str, scopes: &[(NodeId, Vec<String>)]) -> Result<VariableReference, StateChartError> {
+    let unresolved = || StateChartError::UnresolvedReference(format!("smv:{reference}"));
+    let (node_id, name) = match reference.rsplit_once('/') {
+        Some((node_reference, name)) => {
+            let (current, _) = scopes.last().ok_or_else(unresolved)?;
+            (current.resolve(node_reference)?, name)
+        }
+        None => {
+            let (node_id, _) = scopes
+                .iter()
+                .rev()
+                .find(|(_, names)| names.iter().any(|name| name == reference))
+                .ok_or_else(unresolved)?;
+            (node_id.clone(), reference)
+        }
+    };
+    VariableReference::new(&node_id, name)
+}
+
+/// The variable value holds the value of a variable attribute or parameter. Only the parameters
+/// of a state chart reference variables.
 #[derive(Debug, PartialEq, Clone)]
 pub enum VariableValue {
     String(String),
     Integer(i64),
     Number(f64),
     Boolean(bool),
+    Reference(VariableReference),
     None,
 }
 impl VariableValue {
 
     /// Provides access to the type of the variable value, which is intended to be used as the
     /// column of the value in the database.
-    fn get_column_name(&self) -> &'static str {
+    pub fn get_column_name(&self) -> &'static str {
         match self {
             Self::String(_) => "string_value",
             Self::Integer(_) => "integer_value",
             Self::Number(_) => "number_value",
             Self::Boolean(_) => "boolean_value",
-            Self::None => "string_value",
+            Self::Reference(_) | Self::None => "string_value",
         }
     }
 
-    fn get_type(&self) -> &'static str {
+    pub fn get_type(&self) -> &'static str {
         match self {
             Self::String(_) => "string",
             Self::Integer(_) => "integer",
             Self::Number(_) => "number",
             Self::Boolean(_) => "boolean",
+            Self::Reference(_) => "reference",
             Self::None => "string",
         }
     }
-
-    fn to_string(&self) -> String {
+}
+/// Booleans are written as `1` and `0`, the absent value as an empty string.
+impl fmt::Display for VariableValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::String(s) => s.clone(),
-            Self::Integer(i) => format!("{}", i),
-            Self::Number(n) => format!("{}", n),
-            Self::Boolean(b) => if *b { String::from("1") } else { String::from("0") },
-            Self::None => String::from(""),
+            Self::String(s) => write!(f, "{s}"),
+            Self::Integer(i) => write!(f, "{i}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Boolean(b) => write!(f, "{}", if *b { "1" } else { "0" }),
+            Self::Reference(reference) => write!(f, "{reference}"),
+            Self::None => Ok(()),
         }
     }
 }
@@ -562,6 +1175,69 @@ impl Default for VariableValue {
         Self::None
     }
 }
+/// The value is written as the plain JSON value of its type, a reference as string.
+impl Serialize for VariableValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::String(s) => serializer.serialize_str(s),
+            Self::Reference(reference) => serializer.collect_str(reference),
+            Self::Integer(i) => serializer.serialize_i64(*i),
+            Self::Number(n) => serializer.serialize_f64(*n),
+            Self::Boolean(b) => serializer.serialize_bool(*b),
+            Self::None => serializer.serialize_none(),
+        }
+    }
+}
+impl<'de> Deserialize<'de> for VariableValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Definition {
+            Boolean(bool),
+            Integer(i64),
+            Number(f64),
+            String(String),
+            None(()),
+        }
+        Ok(match Option::<Definition>::deserialize(deserializer)? {
+            Some(Definition::Boolean(b)) => Self::Boolean(b),
+            Some(Definition::Integer(i)) => Self::Integer(i),
+            Some(Definition::Number(n)) => Self::Number(n),
+            Some(Definition::String(s)) => Self::String(s),
+            Some(Definition::None(())) | None => Self::None,
+        })
+    }
+}
+impl From<&str> for VariableValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+impl From<String> for VariableValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+impl From<i32> for VariableValue {
+    fn from(value: i32) -> Self {
+        Self::Integer(value.into())
+    }
+}
+impl From<i64> for VariableValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+impl From<f64> for VariableValue {
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+impl From<bool> for VariableValue {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
 impl TryFrom<&ValidatedValue> for VariableValue {
     type Error = StateChartError;
 
@@ -576,6 +1252,18 @@ impl TryFrom<&ValidatedValue> for VariableValue {
         }
     }
 }
+impl From<&VariableValue> for ValidatedValue {
+    fn from(value: &VariableValue) -> Self {
+        match value {
+            VariableValue::String(s) => Self::String(s.clone()),
+            VariableValue::Integer(i) => Self::Integer(*i),
+            VariableValue::Number(n) => Self::Number(*n),
+            VariableValue::Boolean(b) => Self::Bool(*b),
+            VariableValue::Reference(reference) => Self::String(reference.to_string()),
+            VariableValue::None => Self::None,
+        }
+    }
+}
 
 
 /// Derives a vector of parameters from an array of validated values.
@@ -594,6 +1282,73 @@ fn parameters_from_validated_values(
     }
 }
 
+/// Selects the parameters, linked to an action or predicate call by the given parameter list.
+fn select_parameters(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    list_table: &str,
+    call_column: &str,
+    call_id: i64,
+) -> Result<Vec<Parameter>, Error> {
+    let sql = format!("SELECT parameter_id FROM {list_table} WHERE {call_column} = ? ORDER BY rowid");
+    let mut statement = connection.prepare(&sql)?;
+    let parameter_ids = statement
+        .query_map([call_id], |row| row.get(0))?
+        .collect::<Result<Vec<i64>, rusqlite::Error>>()?;
+    let mut parameters = Vec::new();
+    for parameter_id in parameter_ids {
+        if let Some(parameter) = Parameter::select(connection, &parameter_id)? {
+            parameters.push(parameter);
+        }
+    }
+    Ok(parameters)
+}
+
+/// Stores copies of the parameters and links them to an action or predicate call by the given
+/// parameter list.
+fn insert_parameters(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    list_table: &str,
+    call_column: &str,
+    call_id: i64,
+    parameters: &[Parameter],
+) -> Result<(), Error> {
+    let sql = format!("INSERT INTO {list_table} ( {call_column}, parameter_id ) VALUES ( ?, ? )");
+    let mut statement = connection.prepare(&sql)?;
+    for parameter in parameters {
+        let parameter_id = *parameter.clone().insert(connection)?;
+        statement.insert(params![call_id, parameter_id])?;
+    }
+    Ok(())
+}
+
+/// Deletes the parameters, linked to an action or predicate call by the given parameter list,
+/// together with their links.
+fn delete_parameters(
+    connection: &PooledConnection<SqliteConnectionManager>,
+    list_table: &str,
+    call_column: &str,
+    call_id: i64,
+) -> Result<(), Error> {
+    for parameter in select_parameters(connection, list_table, call_column, call_id)? {
+        parameter.delete(connection)?;
+    }
+    connection.execute(&format!("DELETE FROM {list_table} WHERE {call_column} = ?"), [call_id])?;
+    Ok(())
+}
+
+/// Reads a variable value from the value columns of a row. The columns are expected in the order
+/// string, integer, number and boolean, starting with the provided index.
+pub fn value_from_row(row: &Row, value_type: &str, index: usize) -> Result<VariableValue, Error> {
+    match value_type {
+        "string" => Ok(VariableValue::String(row.get(index)?)),
+        "integer" => Ok(VariableValue::Integer(row.get(index + 1)?)),
+        "number" => Ok(VariableValue::Number(row.get(index + 2)?)),
+        "boolean" => Ok(VariableValue::Boolean(row.get(index + 3)?)),
+        "reference" => Ok(VariableValue::Reference(row.get::<_, String>(index)?.parse()?)),
+        _ => Err(Error::UnknownValueType(value_type.into())),
+    }
+}
+
 /// Retrieves a mandatory attribute from a standard map.
 pub fn get_mandatory<'a>(
     attributes: &'a BTreeMap<String, ValidatedValue>,
@@ -633,6 +1388,10 @@ mod tests {
     fn test_guard_try_into() {
         let event_id = ValidatedValue::String("sme:///open".into());
         let _guard: Guard = (&event_id).try_into().unwrap();
+        let invalid_event_id = ValidatedValue::String("open".into());
+        Guard::try_from(&invalid_event_id).unwrap_err();
+        let else_guard = ValidatedValue::String("else".into());
+        assert!(matches!(Guard::try_from(&else_guard).unwrap(), Guard::Else));
 
         let mut pc_attributes: BTreeMap<String, ValidatedValue> = BTreeMap::new();
         pc_attributes.insert("name".into(), ValidatedValue::String("A".into()));
@@ -669,6 +1428,18 @@ mod tests {
         let _vd: VariableDeclaration = (&vd1).try_into().unwrap();
     }
 
+    #[test]
+    fn test_variable_declaration_coerce() {
+        let declaration =
+            VariableDeclaration { id: None, name: "count".into(), r#type: "integer".into(), value: VariableValue::Integer(0) };
+        assert_eq!(VariableValue::Integer(3), declaration.coerce(VariableValue::Number(3.0)).unwrap());
+        assert_eq!(VariableValue::Integer(i64::MIN), declaration.coerce(VariableValue::Number(i64::MIN as f64)).unwrap());
+        assert!(matches!(declaration.coerce(VariableValue::Number(3.5)), Err(StateChartError::TypeMismatch(_, _))));
+        assert!(matches!(declaration.coerce(VariableValue::Number(1e30)), Err(StateChartError::TypeMismatch(_, _))));
+        assert!(matches!(declaration.coerce(VariableValue::Number(i64::MAX as f64)), Err(StateChartError::TypeMismatch(_, _))));
+        assert!(matches!(declaration.coerce(VariableValue::Number(f64::NAN)), Err(StateChartError::TypeMismatch(_, _))));
+    }
+
     fn create_db_connection() -> PooledConnection<SqliteConnectionManager> {
         let manager = r2d2_sqlite::SqliteConnectionManager::memory();
         let pool = Pool::builder().max_size(10).build(manager).unwrap();
@@ -680,7 +1451,7 @@ mod tests {
         let connection = create_db_connection();
         Parameter::create(&connection).unwrap();
         let mut p1 = Parameter { id: None, name: "p1".into(), value: VariableValue::String("a string".into()) };
-        let oid_p1 = p1.insert(&connection).unwrap();
+        let oid_p1 = *p1.insert(&connection).unwrap();
         let mut p2 = Parameter { id: None, name: "p2".into(), value: VariableValue::Integer(3623456) };
         p2.insert(&connection).unwrap();
         let mut p3 = Parameter { id: None, name: "p3".into(), value: VariableValue::Number(3623456.123456) };
@@ -694,6 +1465,24 @@ mod tests {
         let r_p1 = Parameter::select(&connection, &oid_p1).unwrap().unwrap();
         assert_eq!(r_p1.name, p1.name);
         assert_eq!(r_p1.value, p1.value);
+
+        p1.value = VariableValue::Integer(42);
+        p1.update(&connection).unwrap();
+        assert_eq!(VariableValue::Integer(42), Parameter::select(&connection, &oid_p1).unwrap().unwrap().value);
+        p1.value = VariableValue::Reference("smv:///Approval/amount".parse().unwrap());
+        p1.update(&connection).unwrap();
+        assert_eq!(p1.value, Parameter::select(&connection, &oid_p1).unwrap().unwrap().value);
+        p1.delete(&connection).unwrap();
+        assert!(Parameter::select(&connection, &oid_p1).unwrap().is_none());
+        let unsaved = Parameter { id: None, name: "p6".into(), value: VariableValue::None };
+        assert!(matches!(unsaved.update(&connection), Err(Error::NotStored("Parameter"))));
+
+        // A value type, which isn't known, is an error instead of a panic.
+        connection.execute("UPDATE Parameter SET value_type = 'date' WHERE rowid = ?", [p2.id]).unwrap();
+        match Parameter::select(&connection, &p2.id.unwrap()) {
+            Err(err @ Error::UnknownValueType(_)) => assert_eq!(102, err.error_id()),
+            _ => panic!("Expected an unknown value type!"),
+        }
     }
 
     #[test]
@@ -701,13 +1490,29 @@ mod tests {
         let connection = create_db_connection();
         PredicateCall::create(&connection).unwrap();
         Parameter::create(&connection).unwrap();
-        let mut pred1 = PredicateCall { id: None, name: "False".into(), parameters: Vec::new() };
+        let mut pred1 = PredicateCall { id: None, name: "False".parse().unwrap(), parameters: Vec::new() };
         pred1.insert(&connection).unwrap();
         let a = Parameter { id: None, name: "a".into(), value: VariableValue::Boolean(false) };
-        let mut is_true = PredicateCall { id: None, name: "is_true".into(), parameters: vec![a] };
-        is_true.insert(&connection).unwrap();
-
-        // FIXME: Check, if we can retrieve also.
+        let mut is_true = PredicateCall { id: None, name: "is_true".parse().unwrap(), parameters: vec![a] };
+        let id = *is_true.insert(&connection).unwrap();
+
+        let selected = PredicateCall::select(&connection, &id).unwrap().unwrap();
+        assert_eq!("is_true", selected.name.as_str());
+        assert_eq!(VariableValue::Boolean(false), selected.parameters[0].value);
+
+        is_true.parameters = vec![
+            Parameter { id: None, name: "a".into(), value: VariableValue::Boolean(true) },
+            Parameter { id: None, name: "b".into(), value: VariableValue::Integer(2) },
+        ];
+        is_true.update(&connection).unwrap();
+        let selected = PredicateCall::select(&connection, &id).unwrap().unwrap();
+        assert_eq!(2, selected.parameters.len());
+        assert_eq!(VariableValue::Boolean(true), selected.parameters[0].value);
+
+        is_true.delete(&connection).unwrap();
+        assert!(PredicateCall::select(&connection, &id).unwrap().is_none());
+        let parameters: i64 = connection.query_row("SELECT COUNT(*) FROM Parameter", [], |row| row.get(0)).unwrap();
+        assert_eq!(0, parameters);
     }
 
     #[test]
@@ -715,13 +1520,25 @@ mod tests {
         let connection = create_db_connection();
         ActionCall::create(&connection).unwrap();
         Parameter::create(&connection).unwrap();
-        let mut ac1 = ActionCall { id: None, name: "send".into(), parameters: Vec::new() };
+        let mut ac1 = ActionCall { id: None, name: "send".parse().unwrap(), parameters: Vec::new() };
         ac1.insert(&connection).unwrap();
         let msg = Parameter { id: None, name: "msg".into(), value: VariableValue::String("A message to you Rudi!".into()) };
-        let mut ac2 = ActionCall { id: None, name: "send".into(), parameters: vec![msg] };
-        ac2.insert(&connection).unwrap();
-
-        // FIXME: Check, if we can retrieve also.
+        let mut ac2 = ActionCall { id: None, name: "send".parse().unwrap(), parameters: vec![msg] };
+        let id = *ac2.insert(&connection).unwrap();
+
+        let selected = ActionCall::select(&connection, &id).unwrap().unwrap();
+        assert_eq!(VariableValue::String("A message to you Rudi!".into()), selected.parameters[0].value);
+
+        ac2.name = "log".parse().unwrap();
+        ac2.parameters.clear();
+        ac2.update(&connection).unwrap();
+        let selected = ActionCall::select(&connection, &id).unwrap().unwrap();
+        assert_eq!("log", selected.name.as_str());
+        assert!(selected.parameters.is_empty());
+
+        ac2.delete(&connection).unwrap();
+        assert!(ActionCall::select(&connection, &id).unwrap().is_none());
+        assert!(ActionCall::select(&connection, ac1.get_id().unwrap()).unwrap().is_some());
     }
 
     #[test]
@@ -729,8 +1546,80 @@ mod tests {
         let connection = create_db_connection();
         VariableDeclaration::create(&connection).unwrap();
         let mut vd1 = VariableDeclaration { id: None, name: "vd1".into(), r#type: "string".into(), value: VariableValue::String("Hello".into()) };
-        vd1.insert(&connection).unwrap();
+        let id = *vd1.insert(&connection).unwrap();
+
+        let selected = VariableDeclaration::select(&connection, &id).unwrap().unwrap();
+        assert_eq!(VariableValue::String("Hello".into()), selected.value);
 
-        // FIXME: Check, if we can retrieve also.
+        vd1.r#type = "integer".into();
+        vd1.value = VariableValue::Integer(7);
+        vd1.update(&connection).unwrap();
+        let selected = VariableDeclaration::select(&connection, &id).unwrap().unwrap();
+        assert_eq!("integer", selected.value_type());
+        assert_eq!(VariableValue::Integer(7), selected.value);
+
+        vd1.delete(&connection).unwrap();
+        assert!(VariableDeclaration::select(&connection, &id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_transition_crud() {
+        let connection = create_db_connection();
+        Transition::create(&connection).unwrap();
+        let mut transition = Transition::when(PredicateCall::builder("is_true").parameter("a", true))
+            .to("scn:///Task/Done")
+            .action(ActionCall::builder("send").parameter("msg", "done"))
+            .build()
+            .unwrap();
+        let id = *transition.insert(&connection).unwrap();
+
+        let selected = Transition::select(&connection, &id).unwrap().unwrap();
+        assert!(matches!(&selected.guard, Guard::Predicate(predicate_call) if predicate_call.name.as_str() == "is_true"));
+        assert_eq!("send", selected.action.as_ref().unwrap().name.as_str());
+
+        // The guard and the action of the update replace the stored ones.
+        let mut changed = Transition::on("finish").to("scn:///Task/Done").priority(1).build().unwrap();
+        changed.id = Some(id);
+        changed.update(&connection).unwrap();
+        let selected = Transition::select(&connection, &id).unwrap().unwrap();
+        assert!(matches!(&selected.guard, Guard::Event(event) if event.name() == "finish"));
+        assert!(selected.action.is_none());
+        assert_eq!(1, selected.priority);
+        for table in ["PredicateCall", "ActionCall", "Parameter"] {
+            let rows: i64 = connection.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0)).unwrap();
+            assert_eq!(0, rows, "{table}");
+        }
+
+        // The condition of an event guard is stored as its predicate call. The update is part of
+        // the transaction of the caller.
+        let mut conditional = Transition::on("finish").condition(PredicateCall::builder("is_true").parameter("a", true)).build().unwrap();
+        conditional.id = Some(id);
+        let transaction = connection.unchecked_transaction().unwrap();
+        conditional.update(&connection).unwrap();
+        transaction.commit().unwrap();
+        let selected = Transition::select(&connection, &id).unwrap().unwrap();
+        assert!(matches!(&selected.guard, Guard::Event(event) if event.name() == "finish"));
+        assert_eq!("is_true", selected.condition().unwrap().name.as_str());
+
+        conditional.delete(&connection).unwrap();
+        assert!(Transition::select(&connection, &id).unwrap().is_none());
+        let rows: i64 = connection.query_row("SELECT COUNT(*) FROM PredicateCall", [], |row| row.get(0)).unwrap();
+        assert_eq!(0, rows);
+    }
+
+    #[test]
+    fn test_guard_crud() {
+        let connection = create_db_connection();
+        Guard::create(&connection).unwrap();
+        let mut guard = Guard::Predicate(PredicateCall::builder("is_true").build_predicate_call().unwrap());
+        let id = *guard.insert(&connection).unwrap();
+        assert_eq!(Some(&id), guard.get_id());
+        assert!(matches!(Guard::select(&connection, &id).unwrap(), Some(Guard::Predicate(_))));
+        guard.delete(&connection).unwrap();
+        assert!(Guard::select(&connection, &id).unwrap().is_none());
+
+        // The other guards are stored with their transition only.
+        assert!(matches!(Guard::Else.insert(&connection), Err(Error::NotStored(_))));
+        assert!(matches!(Guard::Completion.delete(&connection), Err(Error::NotStored(_))));
     }
 }
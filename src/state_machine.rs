@@ -1,67 +1,1695 @@
-use crate::error::StateChartError;
-use crate::ids::StateId;
-use crate::node::Node;
+use log::debug;
+use open_api_matcher::ValidatedValue;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::actions::{self, Effect};
+use crate::error::{Error, StateChartError};
+use crate::ids::{EventId, NodeId, StateId, VariableId, VariableReference};
+use crate::node::{Node, NodeKind};
+use crate::predicates;
+use crate::sql::Crud;
+use crate::state_charts::{value_from_row, ActionCall, Guard, PredicateCall, Transition, TransitionKind, VariableValue};
+use crate::state_machine_log::{LogEntryType, StateMachineLog};
+
+/// The maximal number of eventless transitions in a single run-to-completion step. A state chart,
+/// which exceeds the limit, most likely contains a loop of eventless transitions.
+const MAX_MICROSTEPS: usize = 100;
+
+/// The maximal number of raised and re-dispatched deferred events in a single run-to-completion
+/// step. A state chart, which exceeds the limit, most likely raises events in a loop.
+const MAX_INTERNAL_EVENTS: usize = 1000;
+
+/// An enabled transition, with the target and the actions of the junctions, it passes. A
+/// transition without target only reacts with its actions.
+struct Step {
+    source: NodeId,
+    target: Option<NodeId>,
+    kind: TransitionKind,
+    actions: Vec<ActionCall>,
+    /// The transition of the source and the branches of the junctions as node and index.
+    transitions: Vec<(NodeId, usize)>,
+    priority: i32,
+}
+
+/// The final target of the junctions with the actions and the selected branches as node and index.
+type JunctionPath = (NodeId, Vec<ActionCall>, Vec<(NodeId, usize)>);
+
+/// A message from a state machine to other state machines. The messages are collected while the
+/// state machine reacts and are delivered afterwards by the delivery module.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// Starts the child with the given id from the state chart. The child is bound to the parent.
+    Start { state_chart: NodeId, child: StateId, parent: StateId },
+    /// Sends the event to the state machine.
+    Send(StateId, EventId),
+    /// Notifies the parent, that the child is finished.
+    Done { parent: StateId, child: StateId },
+    /// Cancels the child, as the parent left the node, the child was bound to.
+    Cancel(StateId),
+}
+
+/// An observation of the reaction of a state machine, which shows, which parts of the state
+/// chart were exercised. A transition is addressed by its source and its index in the out
+/// transitions of the source.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Observation {
+    /// The node was entered.
+    Entered(NodeId),
+    /// The transition was taken.
+    Fired(NodeId, usize),
+    /// The predicate of the transition was evaluated with the result.
+    Evaluated(NodeId, usize, bool),
+}
 
 /// A state machine is a running state chart.
 #[allow(dead_code)]
+#[derive(Clone)]
 pub struct StateMachine {
     /// This is the id of the root state. Constructed from an unique name of the machine and the id
     /// of the state chart.
     id: StateId,
     /// Contains a complete copy of the state chart
     state_chart: Node,
-    /// This is the id of the current state.
+    /// This is the id of the current state. Inside of parallel nodes, several states are active.
+    /// Then it is the first active state in the order of the state chart.
     current_state: StateId,
+    /// The ids of all active nodes.
+    configuration: Vec<NodeId>,
+    /// The nodes, which were active, when the parent of a history node was left the last time.
+    /// The id of the history node is the key.
+    history: Vec<(NodeId, Vec<NodeId>)>,
+    /// The values of the variables of all active nodes. The key is the path of the declaring node,
+    /// followed by the name of the variable.
+    variables: BTreeMap<String, VariableValue>,
+    /// The queue of the events, which were deferred by an active node, in the order of arrival.
+    deferred_events: Vec<EventId>,
+    /// The queue of the events, raised by actions. It is processed before the next external
+    /// event, so it is always empty, when the state machine is stable.
+    internal_events: VecDeque<EventId>,
+    /// The state machine, which invoked this state machine.
+    parent: Option<StateId>,
+    /// The invoked state machines, together with the node of this machine, they are bound to.
+    children: Vec<(NodeId, StateId)>,
+    /// The messages to other state machines, which weren't delivered yet.
+    messages: Vec<Message>,
+    /// The actions, which were executed since the last call of take_executed_actions(), together
+    /// with the node, they belong to. They aren't persisted.
+    executed_actions: Vec<(NodeId, ActionCall)>,
+    /// The observations since the last call of take_observations(). The predicates are evaluated
+    /// while the state machine is only read, so they are collected in a cell. They aren't
+    /// persisted.
+    observations: RefCell<Vec<Observation>>,
+    /// The log of all events, variable changes and transitions of this machine.
+    log: StateMachineLog,
 }
 #[allow(dead_code)]
 impl StateMachine {
-    /// Creates a new state machine, based on the provided state chart.
+    /// Creates a new state machine, based on the provided state chart. The eventless transitions
+    /// of the initial state and the transitions of the raised events are taken, until the state
+    /// machine is stable.
     pub fn new(state_chart: Node) -> Result<StateMachine, StateChartError> {
         let id = StateId::new(state_chart.id())?;
-        if let Some(start_node) = state_chart.start_node() {
-            let current_state = StateId::new_with_node(&id, &start_node)?;
-            Ok(StateMachine {
-                id,
-                state_chart,
-                current_state,
-            })
-        } else {
-            Err(StateChartError::NoRoot)
+        StateMachine::start(state_chart, id, None)
+    }
+
+    /// Creates a new state machine with the given id, which was invoked by the parent.
+    pub fn invoked(state_chart: Node, id: StateId, parent: StateId) -> Result<StateMachine, StateChartError> {
+        StateMachine::start(state_chart, id, Some(parent))
+    }
+
+    fn start(state_chart: Node, id: StateId, parent: Option<StateId>) -> Result<StateMachine, StateChartError> {
+        if state_chart.kind() != NodeKind::Parallel && state_chart.start_node().is_none() {
+            return Err(StateChartError::NoRoot);
         }
+        let root = state_chart.id().clone();
+        let mut state_machine = StateMachine {
+            current_state: id.clone(),
+            configuration: Vec::new(),
+            history: Vec::new(),
+            log: StateMachineLog::new(id.clone()),
+            id,
+            state_chart,
+            variables: BTreeMap::new(),
+            deferred_events: Vec::new(),
+            internal_events: VecDeque::new(),
+            parent,
+            children: Vec::new(),
+            messages: Vec::new(),
+            executed_actions: Vec::new(),
+            observations: RefCell::new(Vec::new()),
+        };
+        state_machine.enter_targets(&[root])?;
+        state_machine.update_current_state()?;
+        state_machine.notify_parent_if_finished()?;
+        state_machine.stabilize()?;
+        Ok(state_machine)
     }
 
     pub fn id(&self) -> &StateId {
         &self.id
     }
 
-    // TODO: generate a StateId from the NodeId of the state chart.
-    // TODO: The operations regarding the setting of variables and events goes here.
+    pub fn state_chart(&self) -> &Node {
+        &self.state_chart
+    }
+
+    pub fn current_state(&self) -> &StateId {
+        &self.current_state
+    }
+
+    /// Provides the ids of all active nodes in the order of the state chart, starting with the
+    /// root.
+    pub fn configuration(&self) -> Result<Vec<NodeId>, StateChartError> {
+        self.active_node_ids()
+    }
+
+    /// Provides the active states, which have no active sub-node, in the order of the state chart.
+    /// Without parallel nodes, this is only the current state.
+    pub fn active_states(&self) -> Result<Vec<StateId>, StateChartError> {
+        self.active_leaves()?
+            .iter()
+            .map(|leaf| StateId::new_with_node(&self.id, leaf))
+            .collect()
+    }
+
+    pub fn deferred_events(&self) -> &Vec<EventId> {
+        &self.deferred_events
+    }
+
+    pub fn parent(&self) -> Option<&StateId> {
+        self.parent.as_ref()
+    }
+
+    pub fn children(&self) -> &Vec<(NodeId, StateId)> {
+        &self.children
+    }
+
+    /// Takes the messages to other state machines, which were collected since the last call.
+    pub fn take_messages(&mut self) -> Vec<Message> {
+        std::mem::take(&mut self.messages)
+    }
+
+    /// Takes the actions, which were executed since the last call, in the order of execution.
+    pub fn take_executed_actions(&mut self) -> Vec<(NodeId, ActionCall)> {
+        std::mem::take(&mut self.executed_actions)
+    }
+
+    /// Takes the observations, which were made since the last call, in the order of the reaction.
+    pub fn take_observations(&mut self) -> Vec<Observation> {
+        std::mem::take(self.observations.get_mut())
+    }
+
+    /// A state machine is finished, if a final node on the top level of the state chart was
+    /// entered.
+    pub fn is_finished(&self) -> bool {
+        self.state_chart
+            .nodes()
+            .iter()
+            .any(|node| node.kind() == NodeKind::Final && self.is_active(node.id()))
+    }
+
+    /// Releases the finished child and sends the done.invoke event of the node, the child is bound
+    /// to, e.g. "done.invoke.Open". A child, which isn't bound any more, was cancelled already.
+    pub fn child_done(&mut self, child: &StateId) -> Result<Vec<EventId>, StateChartError> {
+        let Some(index) = self.children.iter().position(|(_, id)| id == child) else {
+            return Ok(Vec::new());
+        };
+        let (node_id, _) = self.children.remove(index);
+        self.send_event(&done_invoke(&node_id)?)
+    }
+
+    /// Cancels all children of the state machine, as it is cancelled itself.
+    pub fn cancel(&mut self) {
+        for (_, child) in std::mem::take(&mut self.children) {
+            self.messages.push(Message::Cancel(child));
+        }
+    }
+
+    /// Sends the done.invoke event to the parent, if the state machine was invoked and is
+    /// finished now.
+    fn notify_parent_if_finished(&mut self) -> Result<(), StateChartError> {
+        if let Some(parent) = &self.parent {
+            if self.is_finished() {
+                self.messages.push(Message::Done { parent: parent.clone(), child: self.id.clone() });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn log(&self) -> &StateMachineLog {
+        &self.log
+    }
+
+    /// Provides a key of the stable state of the machine: the active nodes, the recorded history,
+    /// the values of the variables and the deferred events. Machines of the same state chart with
+    /// the same key react the same. Integers and numbers with the same value have the same key.
+    pub(crate) fn fingerprint(&self) -> String {
+        let variables: Vec<String> = self.variables.iter().map(|(key, value)| format!("{key}={value}")).collect();
+        format!("{:?}|{:?}|{}|{:?}", self.configuration, self.history, variables.join(","), self.deferred_events)
+    }
+
+    /// Provides the current value of a variable, addressed by the path of the declaring node and
+    /// the name of the variable.
+    pub fn variable(&self, node_id: &NodeId, name: &str) -> Result<Option<&VariableValue>, StateChartError> {
+        Ok(self.variables.get(&format!("{}/{name}", node_id.path()?)))
+    }
+
+    /// Sets a new value to a variable of an active node. The value is checked against the
+    /// declared type of the variable. Afterwards the eventless transitions are taken, until the
+    /// state machine is stable.
+    /// Provides the events, which are valid in the resulting state.
+    pub fn set_variable(&mut self, variable_id: &VariableId, value: &ValidatedValue) -> Result<Vec<EventId>, StateChartError> {
+        debug!("[state_machine::set_variable({variable_id})]");
+        if variable_id.machine_id() != self.id.id()? {
+            return Err(StateChartError::UnknownVariable(variable_id.to_string()));
+        }
+        self.assign(variable_id, value.try_into()?)?;
+        self.stabilize()?;
+        self.enabled_events()
+    }
+
+    /// Sends the event to the state machine. Afterwards the eventless transitions and the raised
+    /// events are taken and the deferred events are dispatched again, until the state machine is
+    /// stable.
+    /// Provides the events, which are valid in the resulting state.
+    pub fn send_event(&mut self, event: &EventId) -> Result<Vec<EventId>, StateChartError> {
+        debug!("[state_machine::send_event({event})]");
+        self.log.log(LogEntryType::Event(event.clone()));
+        self.dispatch(event)?;
+        self.stabilize()?;
+        self.enabled_events()
+    }
+
+    /// Dispatches the event. In each active state, the first transition guarded by the event is
+    /// selected, starting with the state up to the root. An event, which is deferred by an active
+    /// node, is queued instead. Any other event, which doesn't enable a transition, is ignored.
+    fn dispatch(&mut self, event: &EventId) -> Result<(), StateChartError> {
+        if self.defers(event)? {
+            self.log.log(LogEntryType::Deferral(event.clone()));
+            self.deferred_events.push(event.clone());
+        } else {
+            let steps = self.enabled_transitions(Some(event))?;
+            self.take_transitions(steps)?;
+        }
+        Ok(())
+    }
+
+    /// Checks, if the event is deferred in the current state. Starting with each active state up
+    /// to the root, the first node, which either has a transition guarded by the event or defers
+    /// the event, decides. A transition of a node takes precedence over the deferral by the same
+    /// node. The event is deferred, if no active state has a transition for it and at least one
+    /// defers it.
+    fn defers(&self, event: &EventId) -> Result<bool, StateChartError> {
+        let mut deferred = false;
+        for leaf in self.active_leaves()? {
+            for node_id in self.chain(&leaf)?.iter().rev() {
+                let node = self.node(node_id)?;
+                if node
+                    .out_transitions()
+                    .iter()
+                    .any(|transition| matches!(transition.guard(), Guard::Event(guard) if guard == event))
+                {
+                    return Ok(false);
+                }
+                if node.defers(event) {
+                    deferred = true;
+                    break;
+                }
+            }
+        }
+        Ok(deferred)
+    }
+
+    /// Takes the eventless transitions, until the state machine is stable. Afterwards the events
+    /// of the internal queue are dispatched one after the other, each followed by the eventless
+    /// transitions, as in SCXML. When the internal queue is empty, the first deferred event, which
+    /// isn't deferred by the current state any more, is taken from the queue and dispatched again.
+    fn stabilize(&mut self) -> Result<(), StateChartError> {
+        for _ in 0..=MAX_INTERNAL_EVENTS {
+            self.run_to_completion()?;
+            if let Some(event) = self.internal_events.pop_front() {
+                self.log.log(LogEntryType::Raise(event.clone()));
+                self.dispatch(&event)?;
+                continue;
+            }
+            let mut next = None;
+            for (index, event) in self.deferred_events.iter().enumerate() {
+                if !self.defers(event)? {
+                    next = Some(index);
+                    break;
+                }
+            }
+            match next {
+                Some(index) => {
+                    let event = self.deferred_events.remove(index);
+                    self.log.log(LogEntryType::Redispatch(event.clone()));
+                    self.dispatch(&event)?;
+                }
+                None => return Ok(()),
+            }
+        }
+        Err(StateChartError::RaiseLoop(self.current_state.clone()))
+    }
+
+    /// Assigns a new value to a variable of an entered node, after it was checked against the
+    /// declared type of the variable.
+    fn assign(&mut self, variable_id: &VariableId, value: VariableValue) -> Result<(), StateChartError> {
+        let declaration = self
+            .state_chart
+            .find(&variable_id.node_id())
+            .and_then(|node| node.attributes().iter().find(|attribute| attribute.name() == variable_id.name()))
+            .ok_or_else(|| StateChartError::UnknownVariable(variable_id.to_string()))?;
+        let value = declaration.coerce(value)?;
+        let key = format!("{}/{}", variable_id.node_path(), variable_id.name());
+        if !self.variables.contains_key(&key) {
+            return Err(StateChartError::VariableNotInScope(variable_id.to_string()));
+        }
+
+        self.log.log(LogEntryType::VariableSetting(variable_id.clone(), value.to_string()));
+        self.variables.insert(key, value);
+        Ok(())
+    }
+
+    /// Provides all events, which may trigger a transition in the current state.
+    pub fn enabled_events(&self) -> Result<Vec<EventId>, StateChartError> {
+        let mut events: Vec<EventId> = Vec::new();
+        for node_id in self.active_node_ids()?.iter().rev() {
+            for transition in self.node(node_id)?.out_transitions() {
+                if let Guard::Event(event) = transition.guard() {
+                    if !events.contains(event) {
+                        events.push(event.clone());
+                    }
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    /// Provides the ids of all active nodes in the order of the state chart, starting with the
+    /// root. A node is always followed by its active sub-nodes.
+    fn active_node_ids(&self) -> Result<Vec<NodeId>, StateChartError> {
+        fn collect(node: &Node, configuration: &[NodeId], active: &mut Vec<NodeId>) {
+            if configuration.contains(node.id()) {
+                active.push(node.id().clone());
+                for sub_node in node.nodes() {
+                    collect(sub_node, configuration, active);
+                }
+            }
+        }
+        let mut active = Vec::new();
+        collect(&self.state_chart, &self.configuration, &mut active);
+        Ok(active)
+    }
+
+    /// Provides the active nodes without active sub-nodes in the order of the state chart.
+    fn active_leaves(&self) -> Result<Vec<NodeId>, StateChartError> {
+        let active = self.active_node_ids()?;
+        Ok(active
+            .iter()
+            .filter(|node_id| !active.iter().any(|other| is_descendant(other, node_id)))
+            .cloned()
+            .collect())
+    }
+
+    fn is_active(&self, node_id: &NodeId) -> bool {
+        self.configuration.contains(node_id)
+    }
+
+    /// The current state is the first active leaf.
+    fn update_current_state(&mut self) -> Result<(), StateChartError> {
+        if let Some(leaf) = self.active_leaves()?.first() {
+            self.current_state = StateId::new_with_node(&self.id, leaf)?;
+        }
+        Ok(())
+    }
+
+    /// Provides the ids of the nodes from the root of the state chart down to the given node.
+    fn chain(&self, node_id: &NodeId) -> Result<Vec<NodeId>, StateChartError> {
+        match self.state_chart.path_to(node_id) {
+            Some(path) => Ok(path.iter().map(|node| node.id().clone()).collect()),
+            None => Err(StateChartError::UnknownNode(node_id.clone())),
+        }
+    }
+
+    fn node(&self, node_id: &NodeId) -> Result<&Node, StateChartError> {
+        self.state_chart
+            .find(node_id)
+            .ok_or_else(|| StateChartError::UnknownNode(node_id.clone()))
+    }
+
+    /// Takes the enabled eventless transitions, until the state machine is stable. Each set of
+    /// transitions is a microstep, which may enable the next one, by its actions or the states it
+    /// reaches.
+    fn run_to_completion(&mut self) -> Result<(), StateChartError> {
+        for _ in 0..MAX_MICROSTEPS {
+            let steps = self.enabled_transitions(None)?;
+            if steps.is_empty() {
+                return Ok(());
+            }
+            self.take_transitions(steps)?;
+        }
+        Err(StateChartError::CompletionLoop(self.current_state.clone()))
+    }
+
+    /// Selects the enabled transitions of all active states. For each active state, the enabled
+    /// transition with the highest priority is selected, starting with the state up to the root.
+    /// Between transitions of the same priority, the transition of the descendant is selected and
+    /// then the first transition of a node. Without event, a transition is enabled, if it is a
+    /// completion transition or if its predicate is true. With an event, only the transitions
+    /// guarded by the event are enabled.
+    /// Two transitions conflict, if they leave a common node. Then the transition with the higher
+    /// priority is preferred, then the transition of the descendant, otherwise the transition,
+    /// which was selected first in the order of the state chart.
+    fn enabled_transitions(&self, event: Option<&EventId>) -> Result<Vec<Step>, StateChartError> {
+        let mut selected: Vec<(Step, Vec<NodeId>)> = Vec::new();
+        for leaf in self.active_leaves()? {
+            let mut enabled: Option<Step> = None;
+            for node_id in self.chain(&leaf)?.iter().rev() {
+                let above = enabled.as_ref().map(|step| step.priority);
+                if let Some(step) = self.enabled_transition(node_id, event, above)? {
+                    enabled = Some(step);
+                }
+            }
+            let step = match enabled {
+                Some(step) => step,
+                None => continue,
+            };
+            if selected.iter().any(|(other, _)| other.source == step.source) {
+                continue;
+            }
+            let exit_set = self.exit_set(&step)?;
+            let overlaps = |other_exit_set: &Vec<NodeId>| other_exit_set.iter().any(|node_id| exit_set.contains(node_id));
+            let preempts = |other: &Step| {
+                step.priority > other.priority || (step.priority == other.priority && is_descendant(&step.source, &other.source))
+            };
+            if selected.iter().any(|(other, other_exit_set)| overlaps(other_exit_set) && !preempts(other)) {
+                continue;
+            }
+            selected.retain(|(_, other_exit_set)| !overlaps(other_exit_set));
+            selected.push((step, exit_set));
+        }
+        Ok(selected.into_iter().map(|(step, _)| step).collect())
+    }
+
+    /// Provides the enabled transition of the node with the highest priority, the first one
+    /// between transitions of the same priority. Only the transitions with a priority above the
+    /// given one are considered. A target, which is a junction, is followed to its final target,
+    /// before the transition is taken.
+    fn enabled_transition(&self, node_id: &NodeId, event: Option<&EventId>, above: Option<i32>) -> Result<Option<Step>, StateChartError> {
+        for (index, transition) in by_priority(self.node(node_id)?.out_transitions(), above) {
+            let enabled = match (transition.guard(), event) {
+                (Guard::Event(guard), Some(event)) if guard == event => match transition.condition() {
+                    Some(condition) => self.evaluate(condition, node_id, index)?,
+                    None => true,
+                },
+                (Guard::Predicate(predicate_call), None) => self.evaluate(predicate_call, node_id, index)?,
+                (Guard::Completion, None) => true,
+                _ => false,
+            };
+            if !enabled {
+                continue;
+            }
+            let step = |target, junction_actions: Vec<ActionCall>, branches: Vec<(NodeId, usize)>| Step {
+                source: node_id.clone(),
+                target,
+                kind: transition.kind(),
+                actions: transition.action().cloned().into_iter().chain(junction_actions).collect(),
+                transitions: std::iter::once((node_id.clone(), index)).chain(branches).collect(),
+                priority: transition.priority(),
+            };
+            match transition.to() {
+                None => return Ok(Some(step(None, Vec::new(), Vec::new()))),
+                Some(to) => {
+                    if let Some((target, junction_actions, branches)) = self.follow_junctions(to)? {
+                        return Ok(Some(step(Some(target), junction_actions, branches)));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Provides the active nodes, which are left by the transition. A transition without target
+    /// doesn't leave any node.
+    fn exit_set(&self, step: &Step) -> Result<Vec<NodeId>, StateChartError> {
+        match &step.target {
+            Some(target) => {
+                let domain = self.domain(&step.source, target, step.kind)?;
+                Ok(self.active_node_ids()?.into_iter().filter(|node_id| below(domain.as_ref(), node_id)).collect())
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Provides the domain of the transition from the source to the target. The domain is the
+    /// innermost proper ancestor of the source, which also contains the target. The domain of an
+    /// internal transition is the source itself, if the target is the source or one of its
+    /// descendants. A transition of the root to one of its descendants has no domain, as it
+    /// leaves and enters the root again.
+    fn domain(&self, source: &NodeId, target: &NodeId, kind: TransitionKind) -> Result<Option<NodeId>, StateChartError> {
+        let source_chain = self.chain(source)?;
+        let target_chain = self.chain(target)?;
+        let length = if kind == TransitionKind::Internal && target_chain.starts_with(&source_chain) {
+            source_chain.len()
+        } else {
+            source_chain[..source_chain.len() - 1]
+                .iter()
+                .zip(target_chain.iter())
+                .take_while(|(a, b)| a == b)
+                .count()
+        };
+        Ok(length.checked_sub(1).map(|index| source_chain[index].clone()))
+    }
+
+    /// Follows the junctions, starting with the target, with the current values of the
+    /// variables. Provides the final target together with the actions and the selected
+    /// branches, or None, if a junction has no enabled branch.
+    fn follow_junctions(&self, target: &NodeId) -> Result<Option<JunctionPath>, StateChartError> {
+        let mut target = target.clone();
+        let mut actions = Vec::new();
+        let mut branches = Vec::new();
+        while self.node(&target)?.kind() == NodeKind::Junction {
+            match self.select_branch(&target)? {
+                Some((index, branch)) => {
+                    actions.extend(branch.action().cloned());
+                    branches.push((target.clone(), index));
+                    target = branch.to().cloned().ok_or_else(|| StateChartError::InvalidPseudoNode(target.clone()))?;
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((target, actions, branches)))
+    }
+
+    /// Selects the out transition of a choice or junction together with its index. The transition
+    /// with a true predicate and the highest priority is selected, the first one between
+    /// transitions of the same priority, otherwise the else branch.
+    fn select_branch(&self, node_id: &NodeId) -> Result<Option<(usize, &Transition)>, StateChartError> {
+        let transitions = self.node(node_id)?.out_transitions();
+        for (index, transition) in by_priority(transitions, None) {
+            if let Guard::Predicate(predicate_call) = transition.guard() {
+                if self.evaluate(predicate_call, node_id, index)? {
+                    return Ok(Some((index, transition)));
+                }
+            }
+        }
+        Ok(transitions.iter().enumerate().find(|(_, transition)| matches!(transition.guard(), Guard::Else)))
+    }
+
+    /// Evaluates the predicate call of the transition with the current values of the referenced
+    /// variables.
+    fn evaluate(&self, predicate_call: &PredicateCall, node_id: &NodeId, index: usize) -> Result<bool, StateChartError> {
+        let mut arguments = Vec::new();
+        for parameter in predicate_call.parameters() {
+            arguments.push(self.resolve(parameter.value())?);
+        }
+        let result = predicates::evaluate(predicate_call.name().as_str(), &arguments)?;
+        self.observations.borrow_mut().push(Observation::Evaluated(node_id.clone(), index, result));
+        Ok(result)
+    }
+
+    /// Resolves the value of a parameter. A reference is replaced by the current value of the
+    /// variable.
+    fn resolve(&self, value: &VariableValue) -> Result<VariableValue, StateChartError> {
+        match value {
+            VariableValue::Reference(reference) => self
+                .variable(reference.node_id(), reference.name())?
+                .cloned()
+                .ok_or_else(|| StateChartError::VariableNotInScope(reference.to_string())),
+            _ => Ok(value.clone()),
+        }
+    }
+
+    /// Provides the id of the referenced variable in this state machine.
+    fn variable_id(&self, reference: &VariableReference) -> Result<VariableId, StateChartError> {
+        VariableId::new(&self.id, reference.node_id(), reference.name())
+    }
+
+    /// Executes the action call and applies its effects to the state machine. A state machine,
+    /// invoked by the action, is bound to the given node and cancelled, when the node is left.
+    fn execute(&mut self, action_call: &ActionCall, node_id: &NodeId) -> Result<(), StateChartError> {
+        debug!("[state_machine::execute({})]", action_call.name());
+        let parameters: Vec<VariableValue> =
+            action_call.parameters().iter().map(|parameter| parameter.value().clone()).collect();
+        let effects = actions::execute(action_call.name().as_str(), &parameters, |value| self.resolve(value))?;
+        self.executed_actions.push((node_id.clone(), action_call.clone()));
+        for effect in effects {
+            match effect {
+                Effect::Assign(reference, value) => {
+                    let variable_id = self.variable_id(&reference)?;
+                    self.assign(&variable_id, value)?;
+                }
+                Effect::Raise(event) => self.internal_events.push_back(event),
+                Effect::Invoke(state_chart, variable) => {
+                    let child = StateId::new(&state_chart)?;
+                    if let Some(reference) = variable {
+                        let variable_id = self.variable_id(&reference)?;
+                        self.assign(&variable_id, VariableValue::String(child.to_string()))?;
+                    }
+                    self.children.push((node_id.clone(), child.clone()));
+                    self.messages.push(Message::Start { state_chart, child, parent: self.id.clone() });
+                }
+                Effect::Send(state_machine, event) => self.messages.push(Message::Send(state_machine, event)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes the selected transitions one after the other in the order of the state chart. As they
+    /// don't conflict, a transition is only skipped, if its source was left by an earlier one.
+    fn take_transitions(&mut self, steps: Vec<Step>) -> Result<(), StateChartError> {
+        for step in steps {
+            if self.is_active(&step.source) {
+                self.take_transition(step)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes the transition from the source to the target node. All active nodes below the domain
+    /// of the transition are left, the actions of the transition are executed and all nodes from
+    /// the domain down to the new states are entered.
+    /// A choice is evaluated after the actions were executed. The transition continues with the
+    /// selected branch, the choice as source. The choice itself is never entered. A history node
+    /// is replaced by the nodes, it recorded.
+    /// A transition without target only executes its actions. No node is left or entered.
+    /// State machines, invoked by the actions of a transition, are bound to the innermost node,
+    /// which stays active.
+    fn take_transition(&mut self, step: Step) -> Result<(), StateChartError> {
+        let Step { mut source, target, mut kind, mut actions, transitions, .. } = step;
+        self.observations
+            .get_mut()
+            .extend(transitions.into_iter().map(|(node_id, index)| Observation::Fired(node_id, index)));
+        let mut target = match target {
+            Some(target) => target,
+            None => {
+                for action in &actions {
+                    self.execute(action, &source)?;
+                }
+                return Ok(());
+            }
+        };
+        let from = self.first_leaf(&source)?.unwrap_or_else(|| source.clone());
+        loop {
+            let domain = self.domain(&source, &target, kind)?;
+            self.exit_below(domain.as_ref())?;
+            let scope = domain.unwrap_or_else(|| self.state_chart.id().clone());
+            for action in &actions {
+                self.execute(action, &scope)?;
+            }
+            if self.node(&target)?.kind() != NodeKind::Choice {
+                break;
+            }
+            let target_chain = self.chain(&target)?;
+            for node_id in &target_chain[..target_chain.len() - 1] {
+                if !self.is_active(node_id) {
+                    self.enter_node(node_id)?;
+                }
+            }
+            let (index, branch) = self
+                .select_branch(&target)?
+                .map(|(index, branch)| (index, branch.clone()))
+                .ok_or_else(|| StateChartError::NoEnabledBranch(target.clone()))?;
+            let to = branch.to().ok_or_else(|| StateChartError::InvalidPseudoNode(target.clone()))?;
+            let (next, junction_actions, branches) = self
+                .follow_junctions(to)?
+                .ok_or_else(|| StateChartError::NoEnabledBranch(to.clone()))?;
+            let observations = self.observations.get_mut();
+            observations.push(Observation::Fired(target.clone(), index));
+            observations.extend(branches.into_iter().map(|(node_id, index)| Observation::Fired(node_id, index)));
+            actions = branch.action().cloned().into_iter().chain(junction_actions).collect();
+            kind = TransitionKind::External;
+            source = std::mem::replace(&mut target, next);
+        }
+
+        let targets = if self.node(&target)?.kind().is_history() {
+            self.restore_history(&target)?
+        } else {
+            vec![target]
+        };
+        self.enter_targets(&targets)?;
+        self.update_current_state()?;
+        if let Some(to) = self.first_leaf(&targets[0])? {
+            self.log.log(LogEntryType::Transaction(from, to));
+        }
+        self.notify_parent_if_finished()
+    }
+
+    /// Provides the first active leaf, which is the node itself or one of its descendants.
+    fn first_leaf(&self, node_id: &NodeId) -> Result<Option<NodeId>, StateChartError> {
+        Ok(self
+            .active_leaves()?
+            .into_iter()
+            .find(|leaf| leaf == node_id || is_descendant(leaf, node_id)))
+    }
+
+    /// Leaves all active nodes below the domain, starting with the innermost nodes. Without
+    /// domain, all active nodes are left. The active nodes below a node with a history node are
+    /// recorded, before any node is left.
+    fn exit_below(&mut self, domain: Option<&NodeId>) -> Result<(), StateChartError> {
+        let active = self.active_node_ids()?;
+        let exit_set: Vec<NodeId> = active.iter().filter(|node_id| below(domain, node_id)).cloned().collect();
+        for node_id in &exit_set {
+            let histories: Vec<(NodeId, NodeKind)> = self
+                .node(node_id)?
+                .nodes()
+                .iter()
+                .filter(|sub_node| sub_node.kind().is_history())
+                .map(|sub_node| (sub_node.id().clone(), sub_node.kind()))
+                .collect();
+            for (history, kind) in histories {
+                let recorded: Vec<NodeId> = match kind {
+                    NodeKind::DeepHistory => self
+                        .active_leaves()?
+                        .into_iter()
+                        .filter(|leaf| is_descendant(leaf, node_id))
+                        .collect(),
+                    _ => active.iter().filter(|other| is_child(other, node_id)).cloned().collect(),
+                };
+                self.history.retain(|(id, _)| id != &history);
+                self.history.push((history, recorded));
+            }
+        }
+        for node_id in exit_set.iter().rev() {
+            self.exit_node(node_id)?;
+        }
+        Ok(())
+    }
+
+    /// Provides the nodes, recorded by the history node. If its parent wasn't left before, the
+    /// target of the out transition of the history node is used, after the action of the
+    /// transition was executed. Without out transition, the parent is entered by default.
+    fn restore_history(&mut self, history: &NodeId) -> Result<Vec<NodeId>, StateChartError> {
+        if let Some((_, recorded)) = self.history.iter().find(|(id, _)| id == history) {
+            if !recorded.is_empty() {
+                return Ok(recorded.clone());
+            }
+        }
+        let parent = self.chain(history)?.into_iter().rev().nth(1).ok_or_else(|| StateChartError::InvalidPseudoNode(history.clone()))?;
+        match self.node(history)?.out_transitions().first().cloned() {
+            Some(transition) => {
+                self.observations.get_mut().push(Observation::Fired(history.clone(), 0));
+                if let Some(action) = transition.action() {
+                    self.execute(action, &parent)?;
+                }
+                Ok(transition.to().cloned().into_iter().collect())
+            }
+            None => Ok(vec![parent]),
+        }
+    }
+
+    /// Enters the targets together with all their ancestors, which aren't active yet. Afterwards
+    /// the targets are entered by default. The regions of parallel ancestors, which don't
+    /// contain a target, are entered by default at last.
+    fn enter_targets(&mut self, targets: &[NodeId]) -> Result<(), StateChartError> {
+        let mut regions = Vec::new();
+        for target in targets {
+            let chain = self.chain(target)?;
+            for (index, node_id) in chain.iter().enumerate() {
+                if !self.is_active(node_id) {
+                    self.enter_node(node_id)?;
+                }
+                let node = self.node(node_id)?;
+                if let (NodeKind::Parallel, Some(next)) = (node.kind(), chain.get(index + 1)) {
+                    regions.extend(
+                        node.nodes()
+                            .iter()
+                            .filter(|region| region.id() != next && !region.kind().is_pseudo())
+                            .map(|region| region.id().clone()),
+                    );
+                }
+            }
+        }
+        for target in targets {
+            self.enter_default(target)?;
+        }
+        for region in &regions {
+            if !self.is_active(region) {
+                self.enter_default(region)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Enters the node and its default sub-nodes. All regions of a parallel node are entered. A
+    /// state, which has no active sub-node, enters its start node. If it has no start node, its
+    /// first sub-node is used.
+    fn enter_default(&mut self, node_id: &NodeId) -> Result<(), StateChartError> {
+        if !self.is_active(node_id) {
+            self.enter_node(node_id)?;
+        }
+        let node = self.node(node_id)?;
+        let mut sub_nodes = node.nodes().iter().filter(|sub_node| !sub_node.kind().is_pseudo());
+        match node.kind() {
+            NodeKind::Parallel => {
+                let regions: Vec<NodeId> = sub_nodes.map(|region| region.id().clone()).collect();
+                for region in &regions {
+                    self.enter_default(region)?;
+                }
+            }
+            _ => {
+                let start = match (node.start_node(), sub_nodes.next()) {
+                    (Some(start_node), _) => Some(start_node.clone()),
+                    (None, Some(first)) => Some(first.id().clone()),
+                    (None, None) => None,
+                };
+                let entered = self.configuration.iter().any(|other| is_child(other, node_id));
+                if let (Some(start), false) = (start, entered) {
+                    self.enter_targets(&[start])?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Enters the node by initializing the declared variables and executing the entry action. A
+    /// final node raises the done.state event of its parent. If the parent is a region, which
+    /// completes the last region of a parallel node, the done.state event of the parallel node is
+    /// raised as well.
+    fn enter_node(&mut self, node_id: &NodeId) -> Result<(), StateChartError> {
+        let path = node_id.path()?;
+        let node = self.node(node_id)?;
+        let kind = node.kind();
+        let on_entry = node.on_entry().cloned();
+        let variables: Vec<(String, VariableValue)> = node
+            .attributes()
+            .iter()
+            .map(|attribute| (format!("{path}/{}", attribute.name()), attribute.value().clone()))
+            .collect();
+        self.variables.extend(variables);
+        self.configuration.push(node_id.clone());
+        self.observations.get_mut().push(Observation::Entered(node_id.clone()));
+        if let Some(on_entry) = on_entry {
+            self.execute(&on_entry, node_id)?;
+        }
+        if kind == NodeKind::Final {
+            let chain = self.chain(node_id)?;
+            if let Some(parent) = chain.iter().rev().nth(1) {
+                self.internal_events.push_back(done_state(parent)?);
+            }
+            if let Some(parallel) = chain.iter().rev().nth(2) {
+                let parallel = self.node(parallel)?;
+                let completed = |region: &Node| {
+                    region.nodes().iter().any(|sub_node| {
+                        sub_node.kind() == NodeKind::Final && self.configuration.contains(sub_node.id())
+                    })
+                };
+                let regions = parallel.nodes().iter().filter(|region| !region.kind().is_pseudo());
+                if parallel.kind() == NodeKind::Parallel && regions.clone().all(completed) {
+                    let event = done_state(parallel.id())?;
+                    self.internal_events.push_back(event);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Leaves the node by executing the exit action, cancelling the state machines bound to the
+    /// node and removing the declared variables.
+    fn exit_node(&mut self, node_id: &NodeId) -> Result<(), StateChartError> {
+        if let Some(on_exit) = self.node(node_id)?.on_exit().cloned() {
+            self.execute(&on_exit, node_id)?;
+        }
+        let (cancelled, children): (Vec<_>, Vec<_>) = std::mem::take(&mut self.children)
+            .into_iter()
+            .partition(|(bound, _)| bound == node_id);
+        self.children = children;
+        for (_, child) in cancelled {
+            self.messages.push(Message::Cancel(child));
+        }
+        let path = node_id.path()?;
+        for attribute in self.state_chart.find(node_id).into_iter().flat_map(|node| node.attributes()) {
+            self.variables.remove(&format!("{path}/{}", attribute.name()));
+        }
+        self.configuration.retain(|active| active != node_id);
+        Ok(())
+    }
+
+    /// Saves the changed state of the machine together with the new entries of the log.
+    pub fn save(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Error> {
+        self.update(connection)?;
+        self.log.insert(connection)?;
+        Ok(())
+    }
+
+    /// Inserts the queue of the deferred events in the order of their arrival.
+    fn insert_deferred_events(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Error> {
+        let sql = "INSERT INTO StateMachineDeferredEvent ( state_machine_id, event ) VALUES ( ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for event in &self.deferred_events {
+            statement.insert(params![self.id, event])?;
+        }
+        Ok(())
+    }
+
+    /// Inserts the invoked state machines together with the nodes, they are bound to.
+    fn insert_children(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Error> {
+        let sql = "INSERT INTO StateMachineChild ( state_machine_id, node_id, child_id ) VALUES ( ?, ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for (node_id, child) in &self.children {
+            statement.insert(params![self.id, node_id, child])?;
+        }
+        Ok(())
+    }
+
+    /// Inserts the active nodes and the nodes, recorded by the history nodes.
+    fn insert_configuration(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Error> {
+        let sql = "INSERT INTO StateMachineConfiguration ( state_machine_id, node_id ) VALUES ( ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for node_id in &self.configuration {
+            statement.insert(params![self.id, node_id])?;
+        }
+        let sql = "INSERT INTO StateMachineHistory ( state_machine_id, history_node, node_id ) VALUES ( ?, ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for (history, recorded) in &self.history {
+            for node_id in recorded {
+                statement.insert(params![self.id, history, node_id])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts the current values of all variables of the state machine.
+    fn insert_variables(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Error> {
+        for (name, value) in &self.variables {
+            let value_column = value.get_column_name();
+            let sql = format!(
+                "INSERT INTO StateMachineVariable ( state_machine_id, name, value_type, {value_column} ) VALUES ( ?, ?, ?, ? )"
+            );
+            connection.execute(&sql, params![self.id, name, value.get_type(), value.to_string()])?;
+        }
+        Ok(())
+    }
+}
+
+/// Orders the transitions with their index by descending priority and keeps the order of the
+/// state chart between transitions of the same priority. Only the transitions with a priority
+/// above the given one are provided.
+fn by_priority(transitions: &[Transition], above: Option<i32>) -> Vec<(usize, &Transition)> {
+    let mut transitions: Vec<(usize, &Transition)> = transitions
+        .iter()
+        .enumerate()
+        .filter(|(_, transition)| above.is_none_or(|above| transition.priority() > above))
+        .collect();
+    transitions.sort_by_key(|(_, transition)| std::cmp::Reverse(transition.priority()));
+    transitions
+}
+
+/// Checks, if the node is a proper descendant of the ancestor.
+fn is_descendant(node_id: &NodeId, ancestor: &NodeId) -> bool {
+    match (node_id.path(), ancestor.path()) {
+        (Ok(path), Ok(ancestor)) => path.len() > ancestor.len() + 1 && path.starts_with(ancestor) && path[ancestor.len()..].starts_with('/'),
+        _ => false,
+    }
+}
+
+/// Checks, if the node is a direct sub-node of the parent.
+fn is_child(node_id: &NodeId, parent: &NodeId) -> bool {
+    match (node_id.path(), parent.path()) {
+        (Ok(path), Ok(parent)) => path.rsplit_once('/').is_some_and(|(prefix, _)| prefix == parent),
+        _ => false,
+    }
+}
+
+/// Checks, if the node is below the domain. Every node is below a missing domain.
+fn below(domain: Option<&NodeId>, node_id: &NodeId) -> bool {
+    domain.is_none_or(|domain| is_descendant(node_id, domain))
+}
+
+/// Provides the event, which is raised, when a final sub-node of the node was entered.
+pub(crate) fn done_state(node_id: &NodeId) -> Result<EventId, StateChartError> {
+    done_event("state", node_id)
+}
+
+/// Provides the event, which is sent, when the state machine invoked by the node is finished.
+pub(crate) fn done_invoke(node_id: &NodeId) -> Result<EventId, StateChartError> {
+    done_event("invoke", node_id)
+}
+
+fn done_event(kind: &str, node_id: &NodeId) -> Result<EventId, StateChartError> {
+    format!("sme:///done.{kind}.{}", node_id.name()?).parse()
+}
+
+impl Crud<SqliteConnectionManager, StateId> for StateMachine {
+    type Error = Error;
+
+    fn get_id(&self) -> Option<&StateId> {
+        Some(&self.id)
+    }
+
+    /// Creates the tables of the state machine and its variables. The state chart is referenced
+    /// by its id and must be saved before.
+    fn create(connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error> {
+        debug!("[state_machine::Crud::create()]");
+        let sql = "CREATE TABLE IF NOT EXISTS StateMachine (
+                id TEXT NOT NULL UNIQUE,
+                state_chart TEXT NOT NULL,
+                current_state TEXT NOT NULL,
+                parent TEXT
+            )";
+        connection.execute(sql, [])?;
+        let sql = "CREATE TABLE IF NOT EXISTS StateMachineVariable (
+                state_machine_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                value_type TEXT NOT NULL,
+                string_value TEXT,
+                integer_value INTEGER,
+                number_value REAL,
+                boolean_value INTEGER
+            )";
+        connection.execute(sql, [])?;
+        let sql = "CREATE TABLE IF NOT EXISTS StateMachineDeferredEvent (
+                state_machine_id TEXT NOT NULL,
+                event TEXT NOT NULL
+            )";
+        connection.execute(sql, [])?;
+        let sql = "CREATE TABLE IF NOT EXISTS StateMachineChild (
+                state_machine_id TEXT NOT NULL,
+                node_id TEXT NOT NULL,
+                child_id TEXT NOT NULL
+            )";
+        connection.execute(sql, [])?;
+        let sql = "CREATE TABLE IF NOT EXISTS StateMachineConfiguration (
+                state_machine_id TEXT NOT NULL,
+                node_id TEXT NOT NULL
+            )";
+        connection.execute(sql, [])?;
+        let sql = "CREATE TABLE IF NOT EXISTS StateMachineHistory (
+                state_machine_id TEXT NOT NULL,
+                history_node TEXT NOT NULL,
+                node_id TEXT NOT NULL
+            )";
+        connection.execute(sql, [])?;
+        StateMachineLog::create(connection)?;
+        Ok(())
+    }
+
+    fn insert(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<&StateId, Self::Error> {
+        debug!("[state_machine::Crud::insert()]");
+        let sql = "INSERT INTO StateMachine ( id, state_chart, current_state, parent ) VALUES ( ?, ?, ?, ? )";
+        connection.execute(sql, params![self.id, self.state_chart.id(), self.current_state, self.parent])?;
+        self.insert_variables(connection)?;
+        self.insert_deferred_events(connection)?;
+        self.insert_children(connection)?;
+        self.insert_configuration(connection)?;
+        self.log.insert(connection)?;
+        Ok(&self.id)
+    }
+
+    /// Updates the current state, the values of the variables, the queue of the deferred events,
+    /// the children, the active nodes and the history. The new entries of the log are saved by
+    /// save().
+    fn update(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error> {
+        debug!("[state_machine::Crud::update()]");
+        let sql = "UPDATE StateMachine SET current_state = ? WHERE id = ?";
+        connection.execute(sql, params![self.current_state, self.id])?;
+        let sql = "DELETE FROM StateMachineVariable WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        self.insert_variables(connection)?;
+        let sql = "DELETE FROM StateMachineDeferredEvent WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        self.insert_deferred_events(connection)?;
+        let sql = "DELETE FROM StateMachineChild WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        self.insert_children(connection)?;
+        let sql = "DELETE FROM StateMachineConfiguration WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        let sql = "DELETE FROM StateMachineHistory WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        self.insert_configuration(connection)
+    }
+
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error> {
+        debug!("[state_machine::Crud::delete()]");
+        let sql = "DELETE FROM StateMachineVariable WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        let sql = "DELETE FROM StateMachineDeferredEvent WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        let sql = "DELETE FROM StateMachineChild WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        let sql = "DELETE FROM StateMachineConfiguration WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        let sql = "DELETE FROM StateMachineHistory WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.id])?;
+        let sql = "DELETE FROM StateMachine WHERE id = ?";
+        connection.execute(sql, params![self.id])?;
+        self.log.delete(connection)
+    }
+
+    /// Selects the state machine together with the state chart, the variables, the deferred events,
+    /// the children, the active nodes, the history and the log.
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, key_value: &StateId) -> Result<Option<Self>, Self::Error>
+    where
+        Self: Sized,
+    {
+        debug!("[state_machine::Crud::select()]");
+        let sql = "SELECT state_chart, current_state, parent FROM StateMachine WHERE id = ?";
+        let mut statement = connection.prepare(sql)?;
+        let mut rows = statement.query(params![key_value])?;
+        if let Some(row) = rows.next()? {
+            let state_chart_id: NodeId = row.get(0)?;
+            let state_chart = match Node::select(connection, &state_chart_id)? {
+                Some(state_chart) => state_chart,
+                None => return Err(StateChartError::UnknownStateChart(state_chart_id).into()),
+            };
+
+            let sql = "SELECT name, value_type, string_value, integer_value, number_value, boolean_value
+                       FROM StateMachineVariable WHERE state_machine_id = ?";
+            let mut statement = connection.prepare(sql)?;
+            let mut variable_rows = statement.query(params![key_value])?;
+            let mut variables = BTreeMap::new();
+            while let Some(variable_row) = variable_rows.next()? {
+                let value_type: String = variable_row.get(1)?;
+                variables.insert(variable_row.get(0)?, value_from_row(variable_row, &value_type, 2)?);
+            }
+
+            let sql = "SELECT event FROM StateMachineDeferredEvent WHERE state_machine_id = ? ORDER BY rowid";
+            let mut statement = connection.prepare(sql)?;
+            let deferred_events = statement
+                .query_map(params![key_value], |row| row.get(0))?
+                .collect::<Result<Vec<EventId>, rusqlite::Error>>()?;
+
+            let sql = "SELECT node_id, child_id FROM StateMachineChild WHERE state_machine_id = ? ORDER BY rowid";
+            let mut statement = connection.prepare(sql)?;
+            let children = statement
+                .query_map(params![key_value], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<(NodeId, StateId)>, rusqlite::Error>>()?;
+
+            let sql = "SELECT node_id FROM StateMachineConfiguration WHERE state_machine_id = ? ORDER BY rowid";
+            let mut statement = connection.prepare(sql)?;
+            let configuration = statement
+                .query_map(params![key_value], |row| row.get(0))?
+                .collect::<Result<Vec<NodeId>, rusqlite::Error>>()?;
+
+            let sql = "SELECT history_node, node_id FROM StateMachineHistory WHERE state_machine_id = ? ORDER BY rowid";
+            let mut statement = connection.prepare(sql)?;
+            let mut history: Vec<(NodeId, Vec<NodeId>)> = Vec::new();
+            for entry in statement.query_map(params![key_value], |row| Ok((row.get(0)?, row.get(1)?)))? {
+                let (history_node, node_id): (NodeId, NodeId) = entry?;
+                match history.iter_mut().find(|(id, _)| id == &history_node) {
+                    Some((_, recorded)) => recorded.push(node_id),
+                    None => history.push((history_node, vec![node_id])),
+                }
+            }
+
+            Ok(Some(StateMachine {
+                id: key_value.clone(),
+                state_chart,
+                current_state: row.get(1)?,
+                configuration,
+                history,
+                variables,
+                deferred_events,
+                internal_events: VecDeque::new(),
+                parent: row.get(2)?,
+                children,
+                messages: Vec::new(),
+                executed_actions: Vec::new(),
+                observations: RefCell::new(Vec::new()),
+                log: StateMachineLog::select(connection, key_value)?
+                    .unwrap_or_else(|| StateMachineLog::new(key_value.clone())),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use open_api_matcher::{OpenApi, ValidatedValue};
+    use open_api_matcher::OpenApi;
+    use r2d2::Pool;
 
-    #[test]
-    fn test_extract_node_path() {
+    fn read_state_chart(file_name: &str) -> Node {
         let open_api_file = std::fs::File::open("StateMachines.yml").unwrap();
         let open_api = OpenApi::new(&open_api_file).unwrap();
-        let sc = std::fs::read_to_string("tests/simple-task.json").unwrap();
+        let sc = std::fs::read_to_string(file_name).unwrap();
         let sc_schema = open_api.get_schema("#/components/schemas/Node").unwrap();
         let vvsc = ValidatedValue::new(&sc, &sc_schema, &open_api).unwrap();
-        let node: Node = (&vvsc).try_into().unwrap();
+        (&vvsc).try_into().unwrap()
+    }
+
+    fn variable_id(state_machine: &StateMachine, path: &str) -> VariableId {
+        format!("smv:///{}/{path}", state_machine.id().id().unwrap()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_extract_node_path() {
+        let node = read_state_chart("tests/simple-task.json");
         let _state_machine = StateMachine::new(node).unwrap();
     }
 
     #[test]
     fn test_state_machine_new() {
-        // Read a state chart.
-        let open_api_file = std::fs::File::open("StateMachines.yml").unwrap();
-        let _open_api = OpenApi::new(&open_api_file).unwrap();
-        let _sc = std::fs::read_to_string("tests/simple-task.json").unwrap();
-        // TODO: Create a state machine from it.
-        // TODO: Check, that the root_state is set correctly.
-        // TODO: Check, that the current_state is set correctly.
+        let state_machine = StateMachine::new(read_state_chart("tests/simple-task.json")).unwrap();
+        assert_eq!("Simple-Task", state_machine.id().path().unwrap());
+        assert_eq!(
+            &NodeId::new("Simple-Task/New"),
+            state_machine.current_state().node_id().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_set_variable() {
+        let mut state_machine = StateMachine::new(read_state_chart("tests/review-task.json")).unwrap();
+        let priority = variable_id(&state_machine, "Review-Task/priority");
+        let events = state_machine.set_variable(&priority, &ValidatedValue::Integer(1)).unwrap();
+        assert_eq!(vec!["sme:///assign".parse::<EventId>().unwrap()], events);
+        assert_eq!(
+            Some(&VariableValue::Integer(1)),
+            state_machine.variable(&NodeId::new("Review-Task"), "priority").unwrap()
+        );
+
+        // A number without fraction is accepted for an integer.
+        state_machine.set_variable(&priority, &ValidatedValue::Number(2.0)).unwrap();
+        assert_eq!(
+            Some(&VariableValue::Integer(2)),
+            state_machine.variable(&NodeId::new("Review-Task"), "priority").unwrap()
+        );
+        match state_machine.set_variable(&priority, &ValidatedValue::String("high".into())) {
+            Err(StateChartError::TypeMismatch(_, _)) => {}
+            _ => panic!("Expected a type mismatch!"),
+        }
+        match state_machine.set_variable(&variable_id(&state_machine, "Review-Task/unknown"), &ValidatedValue::Integer(1)) {
+            Err(StateChartError::UnknownVariable(_)) => {}
+            _ => panic!("Expected an unknown variable!"),
+        }
+        let approved = variable_id(&state_machine, "Review-Task/Escalated/approved");
+        match state_machine.set_variable(&approved, &ValidatedValue::Bool(true)) {
+            Err(StateChartError::VariableNotInScope(_)) => {}
+            _ => panic!("Expected a variable out of scope!"),
+        }
+        let other_machine: VariableId = "smv:///4711/Review-Task/priority".parse().unwrap();
+        match state_machine.set_variable(&other_machine, &ValidatedValue::Integer(1)) {
+            Err(StateChartError::UnknownVariable(_)) => {}
+            _ => panic!("Expected an unknown variable!"),
+        }
+    }
+
+    #[test]
+    fn test_set_variable_triggers_transition() {
+        let mut state_machine = StateMachine::new(read_state_chart("tests/review-task.json")).unwrap();
+        let priority = variable_id(&state_machine, "Review-Task/priority");
+        let events = state_machine.set_variable(&priority, &ValidatedValue::Integer(3)).unwrap();
+        assert!(events.is_empty());
+        assert_eq!(
+            &NodeId::new("Review-Task/Escalated"),
+            state_machine.current_state().node_id().unwrap()
+        );
+        assert_eq!(
+            Some(&VariableValue::Boolean(false)),
+            state_machine.variable(&NodeId::new("Review-Task/Escalated"), "approved").unwrap()
+        );
+
+        let approved = variable_id(&state_machine, "Review-Task/Escalated/approved");
+        state_machine.set_variable(&approved, &ValidatedValue::String("true".into())).unwrap();
+        assert_eq!(
+            &NodeId::new("Review-Task/Done"),
+            state_machine.current_state().node_id().unwrap()
+        );
+        // The variables of the left node are out of scope.
+        assert_eq!(None, state_machine.variable(&NodeId::new("Review-Task/Escalated"), "approved").unwrap());
+
+        let entries = state_machine.log().entries();
+        assert_eq!(4, entries.len());
+        assert_eq!(
+            &LogEntryType::VariableSetting(priority, "3".into()),
+            entries[0].entry()
+        );
+        assert_eq!(
+            &LogEntryType::Transaction(NodeId::new("Review-Task/Open"), NodeId::new("Review-Task/Escalated")),
+            entries[1].entry()
+        );
+    }
+
+    fn create_db_connection() -> PooledConnection<SqliteConnectionManager> {
+        let manager = r2d2_sqlite::SqliteConnectionManager::memory();
+        let pool = Pool::builder().max_size(10).build(manager).unwrap();
+        pool.get().unwrap()
+    }
+
+    #[test]
+    fn test_relative_references() {
+        let mut state_machine = StateMachine::new(read_state_chart("tests/approval.json")).unwrap();
+        let amount = variable_id(&state_machine, "Approval/amount");
+        // The guard of Checking is already true, when it is entered.
+        state_machine.set_variable(&amount, &ValidatedValue::Integer(2000)).unwrap();
+        assert_eq!(
+            &NodeId::new("Approval/In-Approval/Escalated"),
+            state_machine.current_state().node_id().unwrap()
+        );
+        let approved = variable_id(&state_machine, "Approval/In-Approval/approved");
+        state_machine.set_variable(&approved, &ValidatedValue::Bool(true)).unwrap();
+        assert_eq!(&NodeId::new("Approval/Approved"), state_machine.current_state().node_id().unwrap());
+    }
+
+    #[test]
+    fn test_choice_after_action() {
+        let mut state_machine = StateMachine::new(read_state_chart("tests/order-task.json")).unwrap();
+        let amount = variable_id(&state_machine, "Order-Task/amount");
+        state_machine.set_variable(&amount, &ValidatedValue::Integer(600)).unwrap();
+        let submitted = variable_id(&state_machine, "Order-Task/submitted");
+        state_machine.set_variable(&submitted, &ValidatedValue::Bool(true)).unwrap();
+        // The choice sees the amount, incremented by the action of the transition.
+        assert_eq!(
+            &NodeId::new("Order-Task/Manual-Approval"),
+            state_machine.current_state().node_id().unwrap()
+        );
+        assert_eq!(
+            Some(&VariableValue::Integer(1100)),
+            state_machine.variable(&NodeId::new("Order-Task"), "amount").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_junction_before_action() {
+        let mut state_machine = StateMachine::new(read_state_chart("tests/order-task.json")).unwrap();
+        let amount = variable_id(&state_machine, "Order-Task/amount");
+        state_machine.set_variable(&amount, &ValidatedValue::Integer(600)).unwrap();
+        let express = variable_id(&state_machine, "Order-Task/express");
+        state_machine.set_variable(&express, &ValidatedValue::Bool(true)).unwrap();
+        // The junction was evaluated with the amount before the action of the transition.
+        assert_eq!(&NodeId::new("Order-Task/Approved"), state_machine.current_state().node_id().unwrap());
+        assert_eq!(
+            Some(&VariableValue::Integer(1100)),
+            state_machine.variable(&NodeId::new("Order-Task"), "amount").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_run_to_completion() {
+        let state_machine = StateMachine::new(read_state_chart("tests/counter-task.json")).unwrap();
+        assert_eq!(&NodeId::new("Counter-Task/Done"), state_machine.current_state().node_id().unwrap());
+        assert_eq!(
+            Some(&VariableValue::Integer(3)),
+            state_machine.variable(&NodeId::new("Counter-Task"), "count").unwrap()
+        );
+        let transitions = state_machine
+            .log()
+            .entries()
+            .iter()
+            .filter(|entry| matches!(entry.entry(), LogEntryType::Transaction(_, _)))
+            .count();
+        assert_eq!(4, transitions);
+    }
+
+    #[test]
+    fn test_completion_loop() {
+        match StateMachine::new(read_state_chart("tests/ping-pong.json")) {
+            Err(StateChartError::CompletionLoop(_)) => {}
+            _ => panic!("Expected a completion loop!"),
+        }
+    }
+
+    #[test]
+    fn test_internal_and_external_transition() {
+        let mut state_machine = StateMachine::new(read_state_chart("tests/session-task.json")).unwrap();
+        let entries = |state_machine: &StateMachine| {
+            state_machine.variable(&NodeId::new("Session-Task"), "entries").unwrap().cloned()
+        };
+        assert_eq!(Some(VariableValue::Integer(1)), entries(&state_machine));
+
+        // The internal transition doesn't leave Active.
+        let refresh = variable_id(&state_machine, "Session-Task/refresh");
+        state_machine.set_variable(&refresh, &ValidatedValue::Bool(true)).unwrap();
+        assert_eq!(Some(VariableValue::Integer(1)), entries(&state_machine));
+        assert_eq!(&NodeId::new("Session-Task/Active/Idle"), state_machine.current_state().node_id().unwrap());
+
+        // The external transition leaves and enters Active again.
+        let restart = variable_id(&state_machine, "Session-Task/restart");
+        state_machine.set_variable(&restart, &ValidatedValue::Bool(true)).unwrap();
+        assert_eq!(Some(VariableValue::Integer(2)), entries(&state_machine));
+        assert_eq!(&NodeId::new("Session-Task/Active/Idle"), state_machine.current_state().node_id().unwrap());
+    }
+
+    #[test]
+    fn test_transition_without_target() {
+        let mut state_machine = StateMachine::new(read_state_chart("tests/tally-task.json")).unwrap();
+        let variable = |state_machine: &StateMachine, name: &str| {
+            state_machine.variable(&NodeId::new("Tally-Task"), name).unwrap().cloned()
+        };
+        let click: EventId = "sme:///click".parse().unwrap();
+        for _ in 0..3 {
+            state_machine.send_event(&click).unwrap();
+        }
+        assert_eq!(Some(VariableValue::Integer(3)), variable(&state_machine, "clicks"));
+        assert_eq!(Some(VariableValue::Integer(1)), variable(&state_machine, "entries"));
+        assert_eq!(&NodeId::new("Tally-Task/Counting"), state_machine.current_state().node_id().unwrap());
+
+        // An event, which isn't enabled, is ignored.
+        let events = state_machine.send_event(&"sme:///unknown".parse().unwrap()).unwrap();
+        assert_eq!(vec![click.clone(), "sme:///stop".parse().unwrap()], events);
+
+        let events = state_machine.send_event(&"sme:///stop".parse().unwrap()).unwrap();
+        assert!(events.is_empty());
+        assert_eq!(&NodeId::new("Tally-Task/Stopped"), state_machine.current_state().node_id().unwrap());
+    }
+
+    #[test]
+    fn test_event_with_condition() {
+        let state_chart = Node::builder("Order")
+            .attribute("amount", 0)
+            .start_node("Open")
+            .state(
+                Node::builder("Open")
+                    .transition(
+                        Transition::on("submit")
+                            .condition(PredicateCall::builder("greater").parameter("left", "smv:amount").parameter("right", 100))
+                            .to("Large")
+                            .priority(1),
+                    )
+                    .transition(Transition::on("submit").to("Small")),
+            )
+            .state(Node::builder("Small"))
+            .state(Node::builder("Large"))
+            .build()
+            .unwrap();
+        let submit: EventId = "sme:///submit".parse().unwrap();
+        let mut state_machine = StateMachine::new(state_chart.clone()).unwrap();
+        state_machine.send_event(&submit).unwrap();
+        assert_eq!(&NodeId::new("Order/Small"), state_machine.current_state().node_id().unwrap());
+
+        // Changing the variable doesn't take the transition, as it waits for the event.
+        let mut state_machine = StateMachine::new(state_chart).unwrap();
+        let amount = variable_id(&state_machine, "Order/amount");
+        state_machine.set_variable(&amount, &ValidatedValue::Integer(101)).unwrap();
+        assert_eq!(&NodeId::new("Order/Open"), state_machine.current_state().node_id().unwrap());
+        state_machine.send_event(&submit).unwrap();
+        assert_eq!(&NodeId::new("Order/Large"), state_machine.current_state().node_id().unwrap());
+    }
+
+    #[test]
+    fn test_priorities() {
+        let state_chart = Node::builder("Task")
+            .start_node("Work")
+            .state(
+                Node::builder("Work")
+                    .start_node("Doing")
+                    .transition(Transition::on("cancel").to("Cancelled").priority(1))
+                    .state(
+                        Node::builder("Doing")
+                            .transition(Transition::on("cancel").to("../../Aborted"))
+                            .transition(Transition::on("pause").to("Paused"))
+                            .transition(Transition::on("pause").to("Waiting").priority(2)),
+                    )
+                    .state(Node::builder("Paused"))
+                    .state(Node::builder("Waiting").transition(Transition::on("go").to("../../Parallel"))),
+            )
+            .state(
+                Node::builder("Parallel")
+                    .kind(NodeKind::Parallel)
+                    .state(Node::builder("B").start_node("Idle").state(Node::builder("Idle").transition(Transition::on("go").to("Busy"))).state(Node::builder("Busy")))
+                    .state(Node::builder("A").start_node("Idle").state(Node::builder("Idle").transition(Transition::on("go").to("../../../Left").priority(1)))),
+            )
+            .state(Node::builder("Cancelled"))
+            .state(Node::builder("Aborted"))
+            .state(Node::builder("Left"))
+            .build()
+            .unwrap();
+        let send = |state_machine: &mut StateMachine, event: &str| {
+            state_machine.send_event(&format!("sme:///{event}").parse().unwrap()).unwrap();
+        };
+        let active = |state_machine: &StateMachine| -> Vec<NodeId> {
+            state_machine.active_states().unwrap().iter().map(|state| state.node_id().unwrap().clone()).collect()
+        };
+
+        // The transition of the parent is taken, as it has the higher priority.
+        let mut state_machine = StateMachine::new(state_chart.clone()).unwrap();
+        send(&mut state_machine, "cancel");
+        assert_eq!(vec![NodeId::new("Task/Cancelled")], active(&state_machine));
+
+        // The later transition of the node is taken, as it has the higher priority.
+        let mut state_machine = StateMachine::new(state_chart).unwrap();
+        send(&mut state_machine, "pause");
+        assert_eq!(vec![NodeId::new("Task/Work/Waiting")], active(&state_machine));
+
+        // The transition of the second region leaves the parallel node, as it has the higher
+        // priority than the transition of the first region.
+        send(&mut state_machine, "go");
+        assert_eq!(vec![NodeId::new("Task/Parallel/B/Idle"), NodeId::new("Task/Parallel/A/Idle")], active(&state_machine));
+        send(&mut state_machine, "go");
+        assert_eq!(vec![NodeId::new("Task/Left")], active(&state_machine));
+    }
+
+    #[test]
+    fn test_deferred_events() {
+        let connection = create_db_connection();
+        Node::create(&connection).unwrap();
+        StateMachine::create(&connection).unwrap();
+        let mut state_chart = read_state_chart("tests/payment-task.json");
+        state_chart.insert(&connection).unwrap();
+        let mut state_machine = StateMachine::new(state_chart).unwrap();
+        state_machine.insert(&connection).unwrap();
+
+        let payment_received: EventId = "sme:///payment-received".parse().unwrap();
+        state_machine.send_event(&payment_received).unwrap();
+        assert_eq!(&NodeId::new("Payment-Task/New"), state_machine.current_state().node_id().unwrap());
+        assert_eq!(&vec![payment_received.clone()], state_machine.deferred_events());
+        state_machine.save(&connection).unwrap();
+
+        // The queue survives the reload of the state machine.
+        let mut selected = StateMachine::select(&connection, state_machine.id()).unwrap().unwrap();
+        assert_eq!(&vec![payment_received.clone()], selected.deferred_events());
+        assert_eq!(&LogEntryType::Deferral(payment_received.clone()), selected.log().entries()[1].entry());
+
+        // Submitted doesn't defer the payment, which is dispatched again on entry.
+        selected.send_event(&"sme:///submit".parse().unwrap()).unwrap();
+        assert_eq!(&NodeId::new("Payment-Task/Paid"), selected.current_state().node_id().unwrap());
+        assert!(selected.deferred_events().is_empty());
+        selected.save(&connection).unwrap();
+
+        let selected = StateMachine::select(&connection, state_machine.id()).unwrap().unwrap();
+        assert!(selected.deferred_events().is_empty());
+        let entries: Vec<&LogEntryType> = selected.log().entries().iter().map(|entry| entry.entry()).collect();
+        assert_eq!(&LogEntryType::Redispatch(payment_received), entries[entries.len() - 2]);
+    }
+
+    #[test]
+    fn test_parallel_history_and_final() {
+        let connection = create_db_connection();
+        Node::create(&connection).unwrap();
+        StateMachine::create(&connection).unwrap();
+        let document = std::fs::read_to_string("tests/media-player.scxml").unwrap();
+        let mut state_chart = crate::scxml::from_scxml(&document).unwrap();
+        state_chart.insert(&connection).unwrap();
+        let mut state_machine = StateMachine::new(state_chart).unwrap();
+        state_machine.insert(&connection).unwrap();
+        let node = |path: &str| NodeId::new(&format!("Media-Player/{path}"));
+        let active = |state_machine: &StateMachine| -> Vec<NodeId> {
+            state_machine.active_states().unwrap().iter().map(|state| state.node_id().unwrap().clone()).collect()
+        };
+        let send = |state_machine: &mut StateMachine, event: &str| {
+            state_machine.send_event(&format!("sme:///{event}").parse().unwrap()).unwrap();
+        };
+        let plays = |state_machine: &StateMachine| state_machine.variable(&NodeId::new("Media-Player"), "plays").unwrap().cloned();
+
+        // Without recorded history, the default transition of the history node is taken.
+        send(&mut state_machine, "resume");
+        assert_eq!(vec![node("On/Playback/Stopped"), node("On/Volume/Normal")], active(&state_machine));
+        send(&mut state_machine, "play");
+        send(&mut state_machine, "louder");
+        assert_eq!(vec![node("On/Playback/Playing"), node("On/Volume/Loud")], active(&state_machine));
+        send(&mut state_machine, "power");
+        assert_eq!(vec![node("Off")], active(&state_machine));
+        state_machine.save(&connection).unwrap();
+
+        // The history survives the reload and restores the playback, while the volume is entered
+        // by default.
+        let mut selected = StateMachine::select(&connection, state_machine.id()).unwrap().unwrap();
+        send(&mut selected, "resume");
+        assert_eq!(vec![node("On/Playback/Playing"), node("On/Volume/Normal")], active(&selected));
+        assert_eq!(Some(VariableValue::Integer(2)), plays(&selected));
+        selected.save(&connection).unwrap();
+
+        let mut selected = StateMachine::select(&connection, state_machine.id()).unwrap().unwrap();
+        assert_eq!(vec![node("On/Playback/Playing"), node("On/Volume/Normal")], active(&selected));
+        // The final node completes the playback region, which leaves the parallel node.
+        send(&mut selected, "finish");
+        assert_eq!(vec![node("Off")], active(&selected));
+        assert!(selected
+            .log()
+            .new_entries()
+            .any(|entry| entry.entry() == &LogEntryType::Raise("sme:///done.state.Playback".parse().unwrap())));
+    }
+
+    #[test]
+    fn test_raised_events() {
+        let mut state_machine = StateMachine::new(read_state_chart("tests/relay-task.json")).unwrap();
+        let events = state_machine.send_event(&"sme:///start".parse().unwrap()).unwrap();
+        assert!(events.is_empty());
+        assert_eq!(&NodeId::new("Relay-Task/Done"), state_machine.current_state().node_id().unwrap());
+
+        let node = |name: &str| NodeId::new(&format!("Relay-Task/{name}"));
+        let chain: Vec<&LogEntryType> = state_machine.log().new_entries().map(|entry| entry.entry()).collect();
+        assert_eq!(
+            vec![
+                &LogEntryType::Event("sme:///start".parse().unwrap()),
+                &LogEntryType::Transaction(node("Idle"), node("Received")),
+                &LogEntryType::Raise("sme:///check".parse().unwrap()),
+                &LogEntryType::Transaction(node("Received"), node("Checked")),
+                &LogEntryType::Raise("sme:///done".parse().unwrap()),
+                &LogEntryType::Transaction(node("Checked"), node("Done")),
+            ],
+            chain
+        );
+    }
+
+    #[test]
+    fn test_raise_chain() {
+        // Every step raises the event, which leads to the next step. The chain is closed by the
+        // transition of the last step back to the first one.
+        let relay = |length: usize, closed: bool| {
+            let step = |index: usize| format!("Step-{index}");
+            let mut builder = Node::builder("Relay").start_node("Step-0");
+            for index in 0..length {
+                let target = if closed && index + 1 == length { step(0) } else { step(index + 1) };
+                builder = builder.state(
+                    Node::builder(&step(index))
+                        .on_entry(ActionCall::builder("raise").parameter("event", "sme:///next"))
+                        .transition(Transition::on("next").to(&target)),
+                );
+            }
+            if !closed {
+                builder = builder.state(Node::builder(&step(length)));
+            }
+            builder.build().unwrap()
+        };
+        let state_machine = StateMachine::new(relay(3 * MAX_MICROSTEPS, false)).unwrap();
+        assert_eq!(&NodeId::new("Relay/Step-300"), state_machine.current_state().node_id().unwrap());
+        match StateMachine::new(relay(3, true)) {
+            Err(StateChartError::RaiseLoop(_)) => {}
+            _ => panic!("Expected a raise loop!"),
+        }
+    }
+
+    #[test]
+    fn test_state_machine_crud() {
+        let connection = create_db_connection();
+        Node::create(&connection).unwrap();
+        StateMachine::create(&connection).unwrap();
+        let mut state_chart = read_state_chart("tests/review-task.json");
+        state_chart.insert(&connection).unwrap();
+        let mut state_machine = StateMachine::new(state_chart).unwrap();
+        state_machine.insert(&connection).unwrap();
+
+        let priority = variable_id(&state_machine, "Review-Task/priority");
+        state_machine.set_variable(&priority, &ValidatedValue::Integer(5)).unwrap();
+        state_machine.save(&connection).unwrap();
+
+        let selected = StateMachine::select(&connection, state_machine.id()).unwrap().unwrap();
+        assert_eq!(state_machine.current_state(), selected.current_state());
+        assert_eq!(
+            Some(&VariableValue::Integer(5)),
+            selected.variable(&NodeId::new("Review-Task"), "priority").unwrap()
+        );
+        assert_eq!(2, selected.log().entries().len());
+
+        selected.delete(&connection).unwrap();
+        assert!(StateMachine::select(&connection, state_machine.id()).unwrap().is_none());
     }
 }
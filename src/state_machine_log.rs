@@ -1,35 +1,217 @@
-use crate::ids::NodeId;
-use crate::state_charts::VariableId;
-use std::time::SystemTime;
+use crate::error::Error;
+use crate::ids::{EventId, NodeId, StateId, VariableId};
+use crate::sql::Crud;
+use log::debug;
+use open_api_matcher::Value;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// The log of all events, variable changes and node changes of a particular state machine.
+/// The log is append only. New entries are kept in memory, until the log is saved.
 #[allow(dead_code)]
+#[derive(Clone)]
 pub struct StateMachineLog {
-    // Database connection...
+    state_machine: StateId,
+    entries: Vec<StateMachineLogEntry>,
 }
 #[allow(dead_code)]
 impl StateMachineLog {
-    /// Initializes the log with the connection to the log store.
-    pub fn init() {}
+    /// Initializes an empty log for the given state machine.
+    pub fn new(state_machine: StateId) -> Self {
+        StateMachineLog { state_machine, entries: Vec::new() }
+    }
 
     /// Creates a new log entry.
-    pub fn log(_entry: LogEntryType) {}
+    pub fn log(&mut self, entry: LogEntryType) {
+        self.entries.push(StateMachineLogEntry { id: None, timestamp: SystemTime::now(), entry });
+    }
+
+    pub fn entries(&self) -> &Vec<StateMachineLogEntry> {
+        &self.entries
+    }
+
+    /// Provides the entries, which were logged since the log was selected or saved the last time.
+    pub fn new_entries(&self) -> impl Iterator<Item = &StateMachineLogEntry> {
+        self.entries.iter().filter(|entry| entry.id.is_none())
+    }
+}
+
+impl Crud<SqliteConnectionManager, StateId> for StateMachineLog {
+    type Error = Error;
+
+    fn get_id(&self) -> Option<&StateId> {
+        Some(&self.state_machine)
+    }
+
+    fn create(connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error> {
+        debug!("[state_machine_log::Crud::create()]");
+        let sql = "CREATE TABLE IF NOT EXISTS StateMachineLog (
+                state_machine_id TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                entry_type TEXT NOT NULL,
+                subject TEXT NOT NULL,
+                object TEXT
+            )";
+        connection.execute(sql, [])?;
+        Ok(())
+    }
+
+    /// Inserts all entries, which aren't already in the database.
+    fn insert(&mut self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<&StateId, Self::Error> {
+        let sql = "INSERT INTO StateMachineLog ( state_machine_id, timestamp, entry_type, subject, object ) VALUES ( ?, ?, ?, ?, ? )";
+        let mut statement = connection.prepare(sql)?;
+        for entry in self.entries.iter_mut().filter(|entry| entry.id.is_none()) {
+            let (entry_type, subject, object) = entry.entry.columns();
+            let rowid = statement.insert(params![self.state_machine, entry.millis(), entry_type, subject, object])?;
+            entry.id = Some(rowid);
+        }
+        Ok(&self.state_machine)
+    }
+
+    /// As the log is append only, existing entries are never changed. New entries are saved by
+    /// insert().
+    fn update(&self, _connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn delete(&self, connection: &PooledConnection<SqliteConnectionManager>) -> Result<(), Self::Error> {
+        let sql = "DELETE FROM StateMachineLog WHERE state_machine_id = ?";
+        connection.execute(sql, params![self.state_machine])?;
+        Ok(())
+    }
+
+    /// Selects all entries of the log of a state machine in the order they were logged.
+    fn select(connection: &PooledConnection<SqliteConnectionManager>, key_value: &StateId) -> Result<Option<Self>, Self::Error>
+    where
+        Self: Sized,
+    {
+        let sql = "SELECT rowid, timestamp, entry_type, subject, object FROM StateMachineLog
+                   WHERE state_machine_id = ? ORDER BY rowid";
+        let mut statement = connection.prepare(sql)?;
+        let rows = statement.query_map(params![key_value], |row| {
+            let millis: i64 = row.get(1)?;
+            let entry_type: String = row.get(2)?;
+            let entry = match entry_type.as_str() {
+                "variable-setting" => LogEntryType::VariableSetting(row.get(3)?, row.get(4)?),
+                "transaction" => LogEntryType::Transaction(row.get(3)?, row.get(4)?),
+                "deferral" => LogEntryType::Deferral(row.get(3)?),
+                "redispatch" => LogEntryType::Redispatch(row.get(3)?),
+                "raise" => LogEntryType::Raise(row.get(3)?),
+                _ => LogEntryType::Event(row.get(3)?),
+            };
+            Ok(StateMachineLogEntry {
+                id: Some(row.get(0)?),
+                timestamp: UNIX_EPOCH + Duration::from_millis(millis as u64),
+                entry,
+            })
+        })?;
+        let entries = rows.collect::<Result<Vec<StateMachineLogEntry>, rusqlite::Error>>()?;
+        Ok(Some(StateMachineLog { state_machine: key_value.clone(), entries }))
+    }
 }
 
 /// The log entry will document when a operation was conducted.
 #[allow(dead_code)]
-struct StateMachineLogEntry {
+#[derive(Clone)]
+pub struct StateMachineLogEntry {
+    id: Option<i64>,
     timestamp: SystemTime,
     entry: LogEntryType,
 }
+#[allow(dead_code)]
+impl StateMachineLogEntry {
+    pub fn timestamp(&self) -> &SystemTime {
+        &self.timestamp
+    }
 
-/// The relevant entry types.
+    pub fn entry(&self) -> &LogEntryType {
+        &self.entry
+    }
+
+    /// The timestamp in milliseconds since the epoch, as it is stored in the database.
+    fn millis(&self) -> i64 {
+        self.timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// The relevant entry types. A deferred event is logged, when it is put into the queue of the
+/// state machine and when it is taken from the queue to be dispatched again. An event, raised by
+/// an action, is logged, when it is taken from the internal queue.
 #[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum LogEntryType {
-    Event(String),
+    Event(EventId),
     VariableSetting(VariableId, String),
     Transaction(NodeId, NodeId),
+    Deferral(EventId),
+    Redispatch(EventId),
+    Raise(EventId),
+}
+impl LogEntryType {
+    /// Provides the type, subject and object columns of the entry in the database.
+    pub fn columns(&self) -> (&'static str, String, Option<String>) {
+        match self {
+            Self::Event(event) => ("event", event.to_string(), None),
+            Self::VariableSetting(variable, value) => ("variable-setting", variable.to_string(), Some(value.clone())),
+            Self::Transaction(from, to) => ("transaction", from.into(), Some(to.into())),
+            Self::Deferral(event) => ("deferral", event.to_string(), None),
+            Self::Redispatch(event) => ("redispatch", event.to_string(), None),
+            Self::Raise(event) => ("raise", event.to_string(), None),
+        }
+    }
+}
+
+/// Provides the entry as object with the type, the subject and the optional object of the entry.
+impl From<&LogEntryType> for Value {
+    fn from(entry: &LogEntryType) -> Self {
+        let (entry_type, subject, object) = entry.columns();
+        let mut content: BTreeMap<String, Value> = BTreeMap::new();
+        content.insert("type".into(), Value::String(entry_type.into()));
+        content.insert("subject".into(), Value::String(subject));
+        if let Some(object) = object {
+            content.insert("object".into(), Value::String(object));
+        }
+        Value::Object(content)
+    }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use r2d2::Pool;
+
+    fn create_db_connection() -> PooledConnection<SqliteConnectionManager> {
+        let manager = r2d2_sqlite::SqliteConnectionManager::memory();
+        let pool = Pool::builder().max_size(10).build(manager).unwrap();
+        pool.get().unwrap()
+    }
+
+    #[test]
+    fn test_state_machine_log_crud() {
+        let connection = create_db_connection();
+        StateMachineLog::create(&connection).unwrap();
+        let state_machine: StateId = "sms:///4711/Simple-Task".into();
+        let mut log = StateMachineLog::new(state_machine.clone());
+        log.log(LogEntryType::Event("sme:///start".parse().unwrap()));
+        log.log(LogEntryType::VariableSetting("smv:///4711/Simple-Task/count".parse().unwrap(), "3".into()));
+        log.insert(&connection).unwrap();
+        log.log(LogEntryType::Transaction(NodeId::new("Simple-Task/New"), NodeId::new("Simple-Task/Done")));
+        log.insert(&connection).unwrap();
+
+        let selected = StateMachineLog::select(&connection, &state_machine).unwrap().unwrap();
+        assert_eq!(3, selected.entries().len());
+        assert_eq!(
+            &LogEntryType::Transaction(NodeId::new("Simple-Task/New"), NodeId::new("Simple-Task/Done")),
+            selected.entries()[2].entry()
+        );
+        selected.delete(&connection).unwrap();
+        let selected = StateMachineLog::select(&connection, &state_machine).unwrap().unwrap();
+        assert!(selected.entries().is_empty());
+    }
+}
@@ -0,0 +1,439 @@
+use crate::error::StateChartError;
+use crate::ids::NodeId;
+use crate::node::{Node, NodeKind};
+use crate::predicates;
+use crate::state_charts::{Guard, PredicateCall, Transition, TransitionKind, VariableValue};
+
+/// Validates the semantic of a state chart, which can't be expressed by the specification. The
+/// state chart must be valid, before it is saved.
+pub fn validate(state_chart: &Node) -> Result<(), StateChartError> {
+    validate_node(state_chart, state_chart)
+}
+
+/// Validates the node and all its sub-nodes against the rules of the state chart.
+fn validate_node(state_chart: &Node, node: &Node) -> Result<(), StateChartError> {
+    if let Some(start_node) = node.start_node() {
+        if target(state_chart, start_node)?.kind().is_pseudo() {
+            return Err(StateChartError::InvalidPseudoNode(start_node.clone()));
+        }
+    }
+    for transition in node.out_transitions() {
+        if transition.condition().is_some() && !matches!(transition.guard(), Guard::Event(_)) {
+            return Err(StateChartError::MisplacedCondition(node.id().clone()));
+        }
+        if let Some(to) = transition.to() {
+            target(state_chart, to)?;
+            if transition.kind() == TransitionKind::Internal && node.find(to).is_none() {
+                return Err(StateChartError::InvalidInternalTransition(node.id().clone()));
+            }
+        }
+    }
+    let has_else = node.out_transitions().iter().any(|transition| matches!(transition.guard(), Guard::Else));
+    match node.kind() {
+        NodeKind::Choice | NodeKind::Junction => validate_pseudo_node(state_chart, node, has_else)?,
+        NodeKind::History | NodeKind::DeepHistory => validate_history_node(state_chart, node)?,
+        _ if has_else => return Err(StateChartError::MisplacedElse(node.id().clone())),
+        NodeKind::Parallel => validate_parallel_node(node)?,
+        NodeKind::Final if !node.out_transitions().is_empty() || !node.nodes().is_empty() => {
+            return Err(StateChartError::InvalidFinalNode(node.id().clone()))
+        }
+        _ => {}
+    }
+    if !node.kind().is_pseudo() {
+        validate_conflicts(node)?;
+    }
+    if node.kind() == NodeKind::Parallel {
+        validate_region_conflicts(node)?;
+    }
+    for sub_node in node.nodes() {
+        validate_node(state_chart, sub_node)?;
+    }
+    Ok(())
+}
+
+/// A pseudo node must have predicate or else transitions with target only, no sub-nodes and no
+/// deferred events, as it is never active. A choice must always select a branch, while a junction
+/// without enabled branch disables the transition. Junctions must not form a cycle, as they are
+/// followed without leaving the transition.
+fn validate_pseudo_node(state_chart: &Node, node: &Node, has_else: bool) -> Result<(), StateChartError> {
+    let invalid = || StateChartError::InvalidPseudoNode(node.id().clone());
+    if !node.nodes().is_empty() || node.out_transitions().is_empty() || !node.deferred_events().is_empty() {
+        return Err(invalid());
+    }
+    if node
+        .out_transitions()
+        .iter()
+        .any(|transition| {
+            matches!(transition.guard(), Guard::Event(_) | Guard::Completion) || transition.to().is_none()
+        })
+    {
+        return Err(invalid());
+    }
+    if node.kind() == NodeKind::Choice && !has_else && !covers_all_cases(node.out_transitions()) {
+        return Err(StateChartError::IncompleteChoice(node.id().clone()));
+    }
+    if node.kind() == NodeKind::Junction && reaches_junction(state_chart, node.id(), node, &mut Vec::new())? {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// All sub-nodes of a parallel node are active. Therefore, it has no start node.
+fn validate_parallel_node(node: &Node) -> Result<(), StateChartError> {
+    let is_region = |sub_node: &Node| {
+        matches!(sub_node.kind(), NodeKind::State | NodeKind::Parallel) || sub_node.kind().is_history()
+    };
+    if node.start_node().is_some() || node.nodes().is_empty() || !node.nodes().iter().all(is_region) {
+        return Err(StateChartError::InvalidParallelNode(node.id().clone()));
+    }
+    Ok(())
+}
+
+/// A history node belongs to a node with other sub-nodes. It may have a single completion
+/// transition to a descendant of its parent, which is taken, if the parent wasn't active before.
+fn validate_history_node(state_chart: &Node, node: &Node) -> Result<(), StateChartError> {
+    let invalid = || StateChartError::InvalidPseudoNode(node.id().clone());
+    let parent = match state_chart.path_to(node.id()) {
+        Some(path) if path.len() > 1 => path[path.len() - 2],
+        _ => return Err(invalid()),
+    };
+    if !node.nodes().is_empty() || node.out_transitions().len() > 1 || parent.nodes().len() < 2 {
+        return Err(invalid());
+    }
+    for transition in node.out_transitions() {
+        match transition.to() {
+            Some(to) if matches!(transition.guard(), Guard::Completion) && to != node.id() && parent.find(to).is_some() => {}
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(())
+}
+
+/// Two transitions of a node, which can be enabled together, must have different priorities.
+/// Otherwise, the order of the transitions in the state chart decides, which one is taken. The
+/// branches of choices and junctions aren't checked, as they are meant to be evaluated in order.
+fn validate_conflicts(node: &Node) -> Result<(), StateChartError> {
+    let transitions = node.out_transitions();
+    for (index, transition) in transitions.iter().enumerate() {
+        for (other_index, other) in transitions.iter().enumerate().skip(index + 1) {
+            if conflicts(transition, other) {
+                return Err(StateChartError::ConflictingTransitions(node.id().clone(), index, node.id().clone(), other_index));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The transitions of the regions of a parallel node are taken together, as long as they stay in
+/// their region. A transition, which leaves its region, also leaves the other regions. It must
+/// have a different priority than the transitions of the other regions, which can be enabled
+/// together with it.
+fn validate_region_conflicts(node: &Node) -> Result<(), StateChartError> {
+    let regions: Vec<&Node> = node.nodes().iter().filter(|region| !region.kind().is_history()).collect();
+    let region_transitions: Vec<Vec<(&Node, usize, bool)>> = regions.iter().map(|region| leaving_transitions(region)).collect();
+    for (index, transitions) in region_transitions.iter().enumerate() {
+        for other_transitions in &region_transitions[index + 1..] {
+            for (source, transition_index, leaves) in transitions {
+                for (other_source, other_index, other_leaves) in other_transitions {
+                    let transition = &source.out_transitions()[*transition_index];
+                    let other = &other_source.out_transitions()[*other_index];
+                    if (*leaves || *other_leaves) && conflicts(transition, other) {
+                        return Err(StateChartError::ConflictingTransitions(
+                            source.id().clone(),
+                            *transition_index,
+                            other_source.id().clone(),
+                            *other_index,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Provides the transitions of the region and its descendants, which aren't pseudo nodes, with
+/// their index and if they leave the region.
+fn leaving_transitions(region: &Node) -> Vec<(&Node, usize, bool)> {
+    let mut transitions = Vec::new();
+    let mut nodes = vec![region];
+    while let Some(node) = nodes.pop() {
+        if !node.kind().is_pseudo() {
+            for (index, transition) in node.out_transitions().iter().enumerate() {
+                let leaves = transition.to().is_some_and(|to| region.find(to).is_none());
+                transitions.push((node, index, leaves));
+            }
+        }
+        nodes.extend(node.nodes());
+    }
+    transitions
+}
+
+/// Two transitions conflict, if they have the same priority and can be enabled together. The
+/// transitions on the same event don't conflict, if their conditions exclude each other.
+fn conflicts(a: &Transition, b: &Transition) -> bool {
+    let conditions_exclude = matches!((a.condition(), b.condition()), (Some(a), Some(b)) if excludes(a, b));
+    a.priority() == b.priority() && may_overlap(a.guard(), b.guard()) && !conditions_exclude
+}
+
+/// Two guards can be true together, if they wait for the same event or if both are eventless,
+/// unless their predicates exclude each other. A completion transition is always enabled.
+fn may_overlap(a: &Guard, b: &Guard) -> bool {
+    match (a, b) {
+        (Guard::Event(a), Guard::Event(b)) => a == b,
+        (Guard::Predicate(a), Guard::Predicate(b)) => !excludes(a, b),
+        (Guard::Completion | Guard::Predicate(_), Guard::Completion | Guard::Predicate(_)) => true,
+        _ => false,
+    }
+}
+
+/// Two predicates exclude each other, if one is the negation of the other or if both compare the
+/// same argument for equality with different literals.
+fn excludes(a: &PredicateCall, b: &PredicateCall) -> bool {
+    let is_literal = |value: &VariableValue| !matches!(value, VariableValue::Reference(_));
+    let different_literals = match (a.parameters().as_slice(), b.parameters().as_slice()) {
+        ([a_left, a_right], [b_left, b_right]) => {
+            a.name().as_str() == "equals"
+                && b.name().as_str() == "equals"
+                && a_left.value() == b_left.value()
+                && is_literal(a_right.value())
+                && is_literal(b_right.value())
+                && a_right.value() != b_right.value()
+        }
+        _ => false,
+    };
+    different_literals || is_negation(a, b)
+}
+
+/// Two guards cover all cases, if the predicate of one is the negation of the predicate of the
+/// other with the same arguments.
+fn covers_all_cases(transitions: &[Transition]) -> bool {
+    let predicate_calls: Vec<&PredicateCall> = transitions
+        .iter()
+        .filter_map(|transition| match transition.guard() {
+            Guard::Predicate(predicate_call) => Some(predicate_call),
+            _ => None,
+        })
+        .collect();
+    predicate_calls.iter().any(|a| predicate_calls.iter().any(|b| is_negation(a, b)))
+}
+
+fn is_negation(a: &PredicateCall, b: &PredicateCall) -> bool {
+    let arguments = |predicate_call: &PredicateCall| {
+        predicate_call.parameters().iter().map(|parameter| parameter.value().clone()).collect::<Vec<_>>()
+    };
+    predicates::negation(a.name().as_str()) == Some(b.name().as_str()) && arguments(a) == arguments(b)
+}
+
+/// Checks, if the junction can be reached again by following the junctions from the given node.
+fn reaches_junction(
+    state_chart: &Node,
+    junction: &NodeId,
+    node: &Node,
+    visited: &mut Vec<NodeId>,
+) -> Result<bool, StateChartError> {
+    for to in node.out_transitions().iter().filter_map(|transition| transition.to()) {
+        let next = target(state_chart, to)?;
+        if next.kind() != NodeKind::Junction || visited.contains(next.id()) {
+            continue;
+        }
+        if next.id() == junction {
+            return Ok(true);
+        }
+        visited.push(next.id().clone());
+        if reaches_junction(state_chart, junction, next, visited)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Provides the node, which is referenced by a transition or as start node.
+fn target<'a>(state_chart: &'a Node, node_id: &NodeId) -> Result<&'a Node, StateChartError> {
+    state_chart
+        .find(node_id)
+        .ok_or_else(|| StateChartError::UnknownNode(node_id.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use open_api_matcher::{OpenApi, ValidatedValue};
+
+    fn parse_state_chart(sc: &str) -> Node {
+        let open_api_file = std::fs::File::open("StateMachines.yml").unwrap();
+        let open_api = OpenApi::new(&open_api_file).unwrap();
+        let sc_schema = open_api.get_schema("#/components/schemas/Node").unwrap();
+        let vvsc = ValidatedValue::new(sc, &sc_schema, &open_api).unwrap();
+        (&vvsc).try_into().unwrap()
+    }
+
+    /// A state chart with a single choice, which has the given out transitions.
+    fn choice_chart(kind: &str, transitions: &str) -> Node {
+        parse_state_chart(&format!(
+            r#"{{
+                "id": "scn:///Order",
+                "start-node": "Open",
+                "attributes": [ {{ "name": "amount", "type": "integer", "value": 0 }} ],
+                "nodes": [
+                    {{ "id": "scn:///Order/Open", "out-transitions": [ {{ "guard": "sme:///submit", "to": "Check" }} ] }},
+                    {{ "id": "scn:///Order/Check", "kind": "{kind}", "out-transitions": [ {transitions} ] }},
+                    {{ "id": "scn:///Order/Small" }},
+                    {{ "id": "scn:///Order/Large" }}
+                ]
+            }}"#
+        ))
+    }
+
+    const GREATER: &str = r#"{ "guard": { "name": "greater", "parameters": [
+        { "name": "left", "value": "smv:amount" }, { "name": "right", "value": 100 } ] }, "to": "Large" }"#;
+    const LESSER_OR_EQUALS: &str = r#"{ "guard": { "name": "lesser-or-equals", "parameters": [
+        { "name": "left", "value": "smv:amount" }, { "name": "right", "value": 100 } ] }, "to": "Small" }"#;
+    const ELSE: &str = r#"{ "guard": "else", "to": "Small" }"#;
+
+    #[test]
+    fn test_validate_choice() {
+        validate(&choice_chart("choice", &format!("{GREATER}, {ELSE}"))).unwrap();
+        validate(&choice_chart("choice", &format!("{GREATER}, {LESSER_OR_EQUALS}"))).unwrap();
+        match validate(&choice_chart("choice", GREATER)) {
+            Err(StateChartError::IncompleteChoice(node_id)) => assert_eq!(NodeId::new("Order/Check"), node_id),
+            _ => panic!("Expected an incomplete choice!"),
+        }
+        // A junction without enabled branch disables the transition.
+        validate(&choice_chart("junction", GREATER)).unwrap();
+    }
+
+    #[test]
+    fn test_validate_pseudo_nodes() {
+        let event = r#"{ "guard": "sme:///large", "to": "Large" }"#;
+        match validate(&choice_chart("choice", &format!("{event}, {ELSE}"))) {
+            Err(StateChartError::InvalidPseudoNode(_)) => {}
+            _ => panic!("Expected an invalid pseudo node!"),
+        }
+        match validate(&choice_chart("state", ELSE)) {
+            Err(StateChartError::MisplacedElse(_)) => {}
+            _ => panic!("Expected a misplaced else!"),
+        }
+        let cycle = r#"{ "guard": "else", "to": "Check" }"#;
+        match validate(&choice_chart("junction", &format!("{GREATER}, {cycle}"))) {
+            Err(StateChartError::InvalidPseudoNode(_)) => {}
+            _ => panic!("Expected an invalid pseudo node!"),
+        }
+        let internal = r#"{ "guard": "else", "to": "Small", "kind": "internal" }"#;
+        match validate(&choice_chart("junction", internal)) {
+            Err(StateChartError::InvalidInternalTransition(_)) => {}
+            _ => panic!("Expected an invalid internal transition!"),
+        }
+        let unknown = r#"{ "guard": "else", "to": "Unknown" }"#;
+        match validate(&choice_chart("choice", unknown)) {
+            Err(StateChartError::UnknownNode(_)) => {}
+            _ => panic!("Expected an unknown node!"),
+        }
+    }
+
+    #[test]
+    fn test_validate_parallel_final_and_history() {
+        let chart = |nodes: &str| {
+            parse_state_chart(&format!(
+                r#"{{ "id": "scn:///Player", "start-node": "On", "nodes": [
+                    {{ "id": "scn:///Player/Off" }},
+                    {{ "id": "scn:///Player/On", "kind": "parallel", "nodes": [ {nodes} ] }}
+                ] }}"#
+            ))
+        };
+        validate(&chart(r#"{ "id": "scn:///Player/On/Playback" }, { "id": "scn:///Player/On/Volume" }"#)).unwrap();
+        match validate(&chart(r#"{ "id": "scn:///Player/On/Playback" }, { "id": "scn:///Player/On/Done", "kind": "final" }"#)) {
+            Err(StateChartError::InvalidParallelNode(_)) => {}
+            _ => panic!("Expected an invalid parallel node!"),
+        }
+        let final_with_transition = r#"{ "id": "scn:///Player/On/Playback", "start-node": "Done", "nodes": [
+            { "id": "scn:///Player/On/Playback/Done", "kind": "final", "out-transitions": [ { "guard": "sme:///go", "to": "Done" } ] } ] }"#;
+        match validate(&chart(final_with_transition)) {
+            Err(StateChartError::InvalidFinalNode(_)) => {}
+            _ => panic!("Expected an invalid final node!"),
+        }
+        let history = |transition: &str| {
+            format!(
+                r#"{{ "id": "scn:///Player/On/Playback", "start-node": "Stopped", "nodes": [
+                    {{ "id": "scn:///Player/On/Playback/Last", "kind": "history", "out-transitions": [ {transition} ] }},
+                    {{ "id": "scn:///Player/On/Playback/Stopped" }} ] }}"#
+            )
+        };
+        validate(&chart(&history(r#"{ "to": "Stopped" }"#))).unwrap();
+        match validate(&chart(&history(r#"{ "guard": "sme:///go", "to": "Stopped" }"#))) {
+            Err(StateChartError::InvalidPseudoNode(_)) => {}
+            _ => panic!("Expected an invalid history node!"),
+        }
+    }
+
+    #[test]
+    fn test_validate_conflicts() {
+        let chart = |transitions: &str| {
+            parse_state_chart(&format!(
+                r#"{{ "id": "scn:///Order", "start-node": "Open",
+                    "attributes": [ {{ "name": "amount", "type": "integer", "value": 0 }} ],
+                    "nodes": [
+                        {{ "id": "scn:///Order/Open", "out-transitions": [ {transitions} ] }},
+                        {{ "id": "scn:///Order/Small" }},
+                        {{ "id": "scn:///Order/Large" }}
+                    ] }}"#
+            ))
+        };
+        let conflict = |node: Node| match validate(&node) {
+            Err(StateChartError::ConflictingTransitions(source, 0, other, 1)) => {
+                assert_eq!(NodeId::new("Order/Open"), source);
+                assert_eq!(source, other);
+            }
+            result => panic!("Expected conflicting transitions, but got {result:?}!"),
+        };
+        conflict(chart(r#"{ "guard": "sme:///submit", "to": "Small" }, { "guard": "sme:///submit", "to": "Large" }"#));
+        validate(&chart(r#"{ "guard": "sme:///submit", "to": "Small" }, { "guard": "sme:///submit", "to": "Large", "priority": 1 }"#)).unwrap();
+        validate(&chart(r#"{ "guard": "sme:///submit", "to": "Small" }, { "guard": "sme:///order", "to": "Large" }"#)).unwrap();
+        conflict(chart(&format!(r#"{GREATER}, {{ "to": "Small" }}"#)));
+        validate(&chart(&format!("{GREATER}, {LESSER_OR_EQUALS}"))).unwrap();
+        let equals = |value: i64| {
+            format!(
+                r#"{{ "guard": {{ "name": "equals", "parameters": [
+                    {{ "name": "left", "value": "smv:amount" }}, {{ "name": "right", "value": {value} }} ] }}, "to": "Large" }}"#
+            )
+        };
+        validate(&chart(&format!("{}, {}", equals(1), equals(2)))).unwrap();
+        conflict(chart(&format!("{}, {}", equals(1), equals(1))));
+
+        // Transitions on the same event don't conflict, if their conditions exclude each other.
+        let on_submit = |guard: &str| guard.replacen(r#""guard""#, r#""guard": "sme:///submit", "condition""#, 1);
+        validate(&chart(&format!("{}, {}", on_submit(GREATER), on_submit(LESSER_OR_EQUALS)))).unwrap();
+        conflict(chart(&format!(r#"{}, {{ "guard": "sme:///submit", "to": "Small" }}"#, on_submit(GREATER))));
+        match validate(&chart(&GREATER.replacen(r#""guard""#, r#""condition""#, 1))) {
+            Err(StateChartError::MisplacedCondition(node_id)) => assert_eq!(NodeId::new("Order/Open"), node_id),
+            result => panic!("Expected a misplaced condition, but got {result:?}!"),
+        }
+
+        // Transitions of orthogonal regions conflict, if one of them leaves its region.
+        let regions = |first: &str, second: &str| {
+            parse_state_chart(&format!(
+                r#"{{ "id": "scn:///Player", "start-node": "On", "nodes": [
+                    {{ "id": "scn:///Player/Off" }},
+                    {{ "id": "scn:///Player/On", "kind": "parallel", "nodes": [
+                        {{ "id": "scn:///Player/On/Playback", "start-node": "Stopped", "nodes": [
+                            {{ "id": "scn:///Player/On/Playback/Stopped", "out-transitions": [ {first} ] }},
+                            {{ "id": "scn:///Player/On/Playback/Playing" }} ] }},
+                        {{ "id": "scn:///Player/On/Volume", "out-transitions": [ {second} ] }} ] }}
+                ] }}"#
+            ))
+        };
+        validate(&regions(r#"{ "guard": "sme:///power", "to": "Playing" }"#, r#"{ "guard": "sme:///power" }"#)).unwrap();
+        match validate(&regions(r#"{ "guard": "sme:///power", "to": "Playing" }"#, r#"{ "guard": "sme:///power", "to": "../../Off" }"#)) {
+            Err(StateChartError::ConflictingTransitions(source, 0, other, 0)) => {
+                assert_eq!(NodeId::new("Player/On/Playback/Stopped"), source);
+                assert_eq!(NodeId::new("Player/On/Volume"), other);
+            }
+            result => panic!("Expected conflicting transitions, but got {result:?}!"),
+        }
+        validate(&regions(
+            r#"{ "guard": "sme:///power", "to": "Playing" }"#,
+            r#"{ "guard": "sme:///power", "to": "../../Off", "priority": 1 }"#,
+        ))
+        .unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+// Generated from the state chart scn:///Simple-Task. Don't edit it.
+
+/// The leaves of the state chart Simple-Task.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum State {
+    New,
+    InProgress,
+    Done,
+}
+#[allow(dead_code)]
+impl State {
+    /// The id of the node in the state chart.
+    pub fn node_id(&self) -> &'static str {
+        match self {
+            State::New => "scn:///Simple-Task/New",
+            State::InProgress => "scn:///Simple-Task/In-Progress",
+            State::Done => "scn:///Simple-Task/Done",
+        }
+    }
+}
+
+/// The events of the state chart Simple-Task.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Event {
+    Start,
+    Cancel,
+    Done,
+}
+#[allow(dead_code)]
+impl Event {
+    /// The id of the event in the state chart.
+    pub fn event_id(&self) -> &'static str {
+        match self {
+            Event::Start => "sme:///start",
+            Event::Cancel => "sme:///cancel",
+            Event::Done => "sme:///done",
+        }
+    }
+}
+
+/// A state machine of the state chart Simple-Task.
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Machine {
+    state: State,
+}
+#[allow(dead_code)]
+impl Machine {
+    pub fn new() -> Self {
+        Machine { state: State::New }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Sends the event to the machine. Provides false, if no active node has a transition for
+    /// the event.
+    pub fn send(&mut self, event: Event) -> bool {
+        self.state = match (self.state, event) {
+            (State::New, Event::Start) => State::InProgress,
+            (State::InProgress, Event::Cancel) => State::New,
+            (State::InProgress, Event::Done) => State::Done,
+            _ => return false,
+        };
+        true
+    }
+}
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}